@@ -0,0 +1,69 @@
+/*
+ * lillux.h: C ABI for the lillux process lifecycle manager.
+ *
+ * Link against liblillux_ffi. Every call but lillux_open, lillux_close,
+ * and lillux_string_free returns the `lillux --output json` envelope,
+ *
+ *     {"ok": true|false, "data": ..., "error": null|{...}}
+ *
+ * as a NUL-terminated string the caller releases with lillux_string_free.
+ * A failure's "error" has
+ *
+ *     "code"      "not_found", "already_exists", "permission_denied",
+ *                 "invalid_argument", "timeout", "unsupported", "os_error",
+ *                 "invalid_config" or "failed"
+ *     "message"   what went wrong, for people
+ *     "os_error"  the errno, or null
+ *     "errno"     its symbolic name ("ENOENT"), or null
+ *     "syscall"   the system call that failed, when one did
+ *     "path"      the file it failed on, when there was one
+ *     "pid"       the process it concerned, when there was one
+ *
+ * `params` is a JSON object as for the `lillux exec serve` method of the
+ * same name; NULL means `{}`. Calls are thread-safe.
+ */
+
+#ifndef LILLUX_H
+#define LILLUX_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LILLUX_ABI_VERSION 1
+
+typedef struct LilluxHandle lillux_handle;
+
+/* The LILLUX_ABI_VERSION the library was built with. */
+uint32_t lillux_abi_version(void);
+
+/* A handle on the registry database at `registry`, or on the default
+ * registry when NULL. Returns NULL if `registry` is not UTF-8. */
+lillux_handle *lillux_open(const char *registry);
+
+/* Release a handle. Processes it spawned keep running. */
+void lillux_close(lillux_handle *handle);
+
+/* {"cmd", "args", "name", "tags", "envs", "log", "stdin", "stdin_fifo"} */
+char *lillux_spawn(const lillux_handle *handle, const char *params);
+
+/* {"pid"} or {"name"}, "grace" seconds before SIGKILL (default 3), and
+ * for a name "self_only" to spare the rest of its process group, for a pid
+ * "group" to take that group along */
+char *lillux_kill(const lillux_handle *handle, const char *params);
+
+/* {"pid"} or {"name"} */
+char *lillux_status(const lillux_handle *handle, const char *params);
+
+/* {"pid"} or {"name"}, and an optional "timeout_ms"; blocks */
+char *lillux_wait(const lillux_handle *handle, const char *params);
+
+void lillux_string_free(char *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LILLUX_H */
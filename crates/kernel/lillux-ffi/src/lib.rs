@@ -0,0 +1,280 @@
+//! C ABI for the `lillux exec` lifecycle manager, for runtimes that would
+//! rather embed it than fork `lillux` per operation. The declarations are
+//! in `include/lillux.h`.
+//!
+//! A handle is an in-process `lillux exec serve`: spawn, kill, status, and
+//! wait take that method's params as a JSON string and return the
+//! `--output json` envelope (`{"ok", "data", "error"}`) as a string the
+//! caller frees with [`lillux_string_free`]. Processes spawned through a
+//! handle are children of the host process; a thread per child reaps it,
+//! so `wait` reports the real exit code. Calls are thread-safe and never
+//! unwind into the caller.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use lillux::exec::batch;
+use lillux::exec::serve::Server;
+use lillux::output;
+use serde_json::{json, Value};
+
+/// Bumped whenever a declaration in `lillux.h` changes incompatibly.
+pub const LILLUX_ABI_VERSION: u32 = 1;
+
+/// Opaque to C: `lillux_handle`.
+pub struct LilluxHandle {
+    server: Arc<Server>,
+}
+
+#[no_mangle]
+pub extern "C" fn lillux_abi_version() -> u32 {
+    LILLUX_ABI_VERSION
+}
+
+/// A handle on the registry at `registry`, or the default registry when it
+/// is NULL. Returns NULL when `registry` is not UTF-8.
+///
+/// # Safety
+///
+/// `registry` is NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lillux_open(registry: *const c_char) -> *mut LilluxHandle {
+    let registry = if registry.is_null() {
+        None
+    } else {
+        match CStr::from_ptr(registry).to_str() {
+            Ok(registry) => Some(registry.to_string()),
+            Err(_) => return std::ptr::null_mut(),
+        }
+    };
+    Box::into_raw(Box::new(LilluxHandle {
+        server: Server::new(registry),
+    }))
+}
+
+/// Release `handle`. Its children keep running and are still reaped.
+///
+/// # Safety
+///
+/// `handle` is NULL or came from [`lillux_open`] and is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn lillux_close(handle: *mut LilluxHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Spawn a detached, registered process. `params`: `{"cmd", "args",
+/// "name", "tags", "envs", "log", "stdin", "stdin_fifo"}`.
+///
+/// # Safety
+///
+/// `handle` came from [`lillux_open`]; `params` is NULL (`{}`) or a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lillux_spawn(
+    handle: *const LilluxHandle,
+    params: *const c_char,
+) -> *mut c_char {
+    call(handle, "spawn", params)
+}
+
+/// Stop a process. `params`: `{"pid"}` or `{"name"}`, `"grace"` seconds
+/// between SIGTERM and SIGKILL (default 3), and for a name `"self_only"`
+/// to spare the rest of its process group, for a pid `"group"` to take
+/// that group along.
+///
+/// # Safety
+///
+/// As for [`lillux_spawn`].
+#[no_mangle]
+pub unsafe extern "C" fn lillux_kill(
+    handle: *const LilluxHandle,
+    params: *const c_char,
+) -> *mut c_char {
+    call(handle, "kill", params)
+}
+
+/// Status of a process. `params`: `{"pid"}` or `{"name"}`.
+///
+/// # Safety
+///
+/// As for [`lillux_spawn`].
+#[no_mangle]
+pub unsafe extern "C" fn lillux_status(
+    handle: *const LilluxHandle,
+    params: *const c_char,
+) -> *mut c_char {
+    call(handle, "status", params)
+}
+
+/// Block until a process exits. `params`: `{"pid"}` or `{"name"}`, and an
+/// optional `"timeout_ms"`.
+///
+/// # Safety
+///
+/// As for [`lillux_spawn`].
+#[no_mangle]
+pub unsafe extern "C" fn lillux_wait(
+    handle: *const LilluxHandle,
+    params: *const c_char,
+) -> *mut c_char {
+    call(handle, "wait", params)
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+///
+/// `result` is NULL or came from this library and is not used again.
+#[no_mangle]
+pub unsafe extern "C" fn lillux_string_free(result: *mut c_char) {
+    if !result.is_null() {
+        drop(CString::from_raw(result));
+    }
+}
+
+unsafe fn call(handle: *const LilluxHandle, method: &str, params: *const c_char) -> *mut c_char {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let Some(handle) = handle.as_ref() else {
+            return failure("handle is required");
+        };
+        let params = if params.is_null() {
+            json!({})
+        } else {
+            let parsed = CStr::from_ptr(params)
+                .to_str()
+                .map_err(|e| e.to_string())
+                .and_then(|params| serde_json::from_str(params).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(params) => params,
+                Err(e) => return failure(&format!("Invalid params: {e}")),
+            }
+        };
+        batch::call(&handle.server, method, params)
+    }))
+    .unwrap_or_else(|_| failure(&format!("lillux panicked during {method}")));
+    // Serialized JSON escapes NUL, so this cannot fail.
+    CString::new(result.to_string())
+        .unwrap_or_default()
+        .into_raw()
+}
+
+fn failure(message: &str) -> Value {
+    output::envelope(json!({ "error": message }))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn invoke(
+        f: unsafe extern "C" fn(*const LilluxHandle, *const c_char) -> *mut c_char,
+        handle: *const LilluxHandle,
+        params: Value,
+    ) -> Value {
+        let params = CString::new(params.to_string()).unwrap();
+        unsafe {
+            let raw = f(handle, params.as_ptr());
+            let result = serde_json::from_str(CStr::from_ptr(raw).to_str().unwrap()).unwrap();
+            lillux_string_free(raw);
+            result
+        }
+    }
+
+    #[test]
+    fn spawn_status_wait_and_kill_through_the_c_abi() {
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = CString::new(tmp.path().join("registry.db").to_str().unwrap()).unwrap();
+        let handle = unsafe { lillux_open(registry.as_ptr()) };
+        assert!(!handle.is_null());
+
+        let spawned = invoke(
+            lillux_spawn,
+            handle,
+            json!({ "cmd": "/bin/sh", "args": ["-c", "exit 4"], "name": "four" }),
+        );
+        assert_eq!(spawned["ok"], true, "{spawned}");
+        let waited = invoke(
+            lillux_wait,
+            handle,
+            json!({ "name": "four", "timeout_ms": 10_000 }),
+        );
+        assert_eq!(waited["data"]["exit_code"], 4, "{waited}");
+
+        let sleeper = invoke(
+            lillux_spawn,
+            handle,
+            json!({ "cmd": "/bin/sleep", "args": ["30"], "name": "sleeper" }),
+        );
+        let status = invoke(lillux_status, handle, json!({ "name": "sleeper" }));
+        assert_eq!(status["data"]["pid"], sleeper["data"]["pid"], "{status}");
+        let killed = invoke(
+            lillux_kill,
+            handle,
+            json!({ "name": "sleeper", "grace": 1.0 }),
+        );
+        assert_eq!(killed["ok"], true, "{killed}");
+
+        let missing = invoke(lillux_kill, handle, json!({ "name": "nobody" }));
+        assert_eq!(missing["error"]["code"], "not_found", "{missing}");
+        let unparsable = unsafe {
+            let raw = lillux_status(handle, c"{".as_ptr());
+            let text = CStr::from_ptr(raw).to_str().unwrap().to_string();
+            lillux_string_free(raw);
+            text
+        };
+        assert!(unparsable.contains("Invalid params"), "{unparsable}");
+        unsafe { lillux_close(handle) };
+    }
+
+    #[test]
+    fn the_header_documents_the_errors_the_library_returns() {
+        let header = include_str!("../include/lillux.h");
+        for name in output::ERROR_FIELDS.iter().chain(output::CODES) {
+            assert!(
+                header.contains(&format!("\"{name}\"")),
+                "lillux.h omits {name}"
+            );
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let registry = CString::new(tmp.path().join("registry.db").to_str().unwrap()).unwrap();
+        let handle = unsafe { lillux_open(registry.as_ptr()) };
+        let errors = [
+            invoke(lillux_kill, handle, json!({ "name": "nobody" })),
+            invoke(
+                lillux_spawn,
+                handle,
+                json!({ "cmd": "/nonexistent/lillux-ffi" }),
+            ),
+            invoke(
+                lillux_spawn,
+                handle,
+                json!({ "cmd": "/bin/sleep", "args": ["30"], "name": "once" }),
+            ),
+            invoke(
+                lillux_spawn,
+                handle,
+                json!({ "cmd": "/bin/sleep", "args": ["30"], "name": "once" }),
+            ),
+        ];
+        invoke(lillux_kill, handle, json!({ "name": "once", "grace": 1.0 }));
+        unsafe { lillux_close(handle) };
+
+        let mut seen = Vec::new();
+        for result in errors.iter().filter(|result| result["ok"] == false) {
+            for (key, value) in result["error"].as_object().unwrap() {
+                assert!(output::ERROR_FIELDS.contains(&key.as_str()), "{result}");
+                if !value.is_null() && !seen.contains(key) {
+                    seen.push(key.clone());
+                }
+            }
+        }
+        seen.sort();
+        let mut expected = output::ERROR_FIELDS.to_vec();
+        expected.sort();
+        assert_eq!(seen, expected, "{errors:?}");
+    }
+}
@@ -0,0 +1,49 @@
+"""Registered processes managed by lillux, without a subprocess per call.
+
+The same spawn, kill, status, and wait as ``lillux exec``, sharing its
+registry, run in-process. Calls return the result as a dict and raise
+``LilluxError`` (with ``code``, ``os_error``, and ``data``) on failure;
+``code`` is one of the ``lillux --output json`` error codes, such as
+``"not_found"``.
+
+The ``_async`` forms run the blocking call on a worker thread, which
+holds no GIL while it waits::
+
+    import lillux_exec
+
+    web = lillux_exec.spawn("./server", ["--port", "8080"], name="web", log="/tmp/web.log")
+    print(lillux_exec.status(name="web")["alive"])
+    await lillux_exec.kill_async(name="web", grace=5.0)
+"""
+
+import asyncio
+
+from ._native import LilluxError, kill, spawn, status, wait
+
+__all__ = [
+    "LilluxError",
+    "kill",
+    "kill_async",
+    "spawn",
+    "spawn_async",
+    "status",
+    "status_async",
+    "wait",
+    "wait_async",
+]
+
+
+async def spawn_async(*args, **kwargs):
+    return await asyncio.to_thread(spawn, *args, **kwargs)
+
+
+async def kill_async(**kwargs):
+    return await asyncio.to_thread(kill, **kwargs)
+
+
+async def status_async(**kwargs):
+    return await asyncio.to_thread(status, **kwargs)
+
+
+async def wait_async(**kwargs):
+    return await asyncio.to_thread(wait, **kwargs)
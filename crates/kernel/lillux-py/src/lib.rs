@@ -0,0 +1,147 @@
+//! `lillux_exec._native`: the registered-process calls of `lillux exec
+//! serve`, in-process, for the Python package in `python/lillux_exec`.
+//!
+//! Each call runs with the GIL released, so the package's `_async` forms
+//! can hand them to worker threads. A successful call returns the
+//! envelope's `data` as Python objects; a failed one raises `LilluxError`
+//! carrying the error's `code` and `os_error`, and the envelope's `data`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use lillux::exec::batch;
+use lillux::exec::serve::Server;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde_json::{json, Value};
+
+create_exception!(_native, LilluxError, PyException);
+
+/// One server per registry for the life of the interpreter: it reaps the
+/// children spawned through it, which `wait` relies on.
+fn server(registry: Option<String>) -> Arc<Server> {
+    static SERVERS: OnceLock<Mutex<HashMap<Option<String>, Arc<Server>>>> = OnceLock::new();
+    let mut servers = SERVERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    servers
+        .entry(registry.clone())
+        .or_insert_with(|| Server::new(registry))
+        .clone()
+}
+
+fn call(
+    py: Python<'_>,
+    registry: Option<String>,
+    method: &str,
+    params: Value,
+) -> PyResult<PyObject> {
+    let envelope = py.allow_threads(|| batch::call(&server(registry), method, params));
+    if envelope["ok"] == true {
+        return from_json(py, &envelope["data"]);
+    }
+    let error = &envelope["error"];
+    let message = error["message"].as_str().unwrap_or("lillux call failed");
+    let raised = LilluxError::new_err(message.to_string());
+    let value = raised.value(py);
+    value.setattr("code", error["code"].as_str())?;
+    value.setattr("os_error", error["os_error"].as_i64())?;
+    value.setattr("data", from_json(py, &envelope["data"])?)?;
+    Err(raised)
+}
+
+fn from_json(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?
+        .unbind())
+}
+
+/// Start `cmd` detached and registered; returns `{"pid", "name"}`.
+#[pyfunction]
+#[pyo3(signature = (cmd, args = Vec::new(), *, name = None, tags = Vec::new(), env = None, log = None, stdin = None, registry = None))]
+#[allow(clippy::too_many_arguments)]
+fn spawn(
+    py: Python<'_>,
+    cmd: String,
+    args: Vec<String>,
+    name: Option<String>,
+    tags: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    log: Option<String>,
+    stdin: Option<String>,
+    registry: Option<String>,
+) -> PyResult<PyObject> {
+    let envs: Vec<String> = env
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let params = json!({
+        "cmd": cmd, "args": args, "name": name, "tags": tags,
+        "envs": envs, "log": log, "stdin": stdin,
+    });
+    call(py, registry, "spawn", params)
+}
+
+/// Stop the process with `pid` or registered as `name`: SIGTERM, then
+/// SIGKILL after `grace` seconds. A name covers the rest of its process
+/// group unless `self_only`; a pid only with `group`.
+#[pyfunction]
+#[pyo3(signature = (*, pid = None, name = None, grace = 3.0, self_only = false, group = false, registry = None))]
+fn kill(
+    py: Python<'_>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    self_only: bool,
+    group: bool,
+    registry: Option<String>,
+) -> PyResult<PyObject> {
+    let params = json!({
+        "pid": pid,
+        "name": name,
+        "grace": grace,
+        "self_only": self_only,
+        "group": group,
+    });
+    call(py, registry, "kill", params)
+}
+
+#[pyfunction]
+#[pyo3(signature = (*, pid = None, name = None, registry = None))]
+fn status(
+    py: Python<'_>,
+    pid: Option<u32>,
+    name: Option<String>,
+    registry: Option<String>,
+) -> PyResult<PyObject> {
+    call(py, registry, "status", json!({ "pid": pid, "name": name }))
+}
+
+/// Block until the process exits, or for at most `timeout` seconds.
+#[pyfunction]
+#[pyo3(signature = (*, pid = None, name = None, timeout = None, registry = None))]
+fn wait(
+    py: Python<'_>,
+    pid: Option<u32>,
+    name: Option<String>,
+    timeout: Option<f64>,
+    registry: Option<String>,
+) -> PyResult<PyObject> {
+    let timeout_ms = timeout.map(|seconds| (seconds.max(0.0) * 1000.0) as u64);
+    let params = json!({ "pid": pid, "name": name, "timeout_ms": timeout_ms });
+    call(py, registry, "wait", params)
+}
+
+#[pymodule]
+fn _native(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("LilluxError", module.py().get_type::<LilluxError>())?;
+    module.add_function(wrap_pyfunction!(spawn, module)?)?;
+    module.add_function(wrap_pyfunction!(kill, module)?)?;
+    module.add_function(wrap_pyfunction!(status, module)?)?;
+    module.add_function(wrap_pyfunction!(wait, module)?)?;
+    Ok(())
+}
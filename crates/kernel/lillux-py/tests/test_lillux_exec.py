@@ -0,0 +1,42 @@
+"""Tests for the lillux_exec bindings; run after `maturin develop`."""
+
+from __future__ import annotations
+
+import asyncio
+
+import pytest
+
+import lillux_exec
+
+
+@pytest.fixture
+def registry(tmp_path):
+    return str(tmp_path / "registry.db")
+
+
+def test_wait_reports_the_real_exit_code(registry):
+    spawned = lillux_exec.spawn("/bin/sh", ["-c", "exit 6"], name="six", registry=registry)
+    exited = lillux_exec.wait(name="six", timeout=10, registry=registry)
+    assert exited["pid"] == spawned["pid"]
+    assert exited["exit_code"] == 6
+
+
+def test_failures_raise_with_the_envelope_code(registry):
+    with pytest.raises(lillux_exec.LilluxError) as raised:
+        lillux_exec.kill(name="nobody", registry=registry)
+    assert raised.value.code == "not_found"
+    assert raised.value.data == {"name": "nobody"}
+
+
+def test_async_forms_run_off_the_event_loop(registry):
+    async def scenario():
+        spawned = await lillux_exec.spawn_async(
+            "/bin/sleep", ["30"], name="sleeper", registry=registry
+        )
+        status = await lillux_exec.status_async(name="sleeper", registry=registry)
+        killed = await lillux_exec.kill_async(name="sleeper", grace=1.0, registry=registry)
+        return spawned, status, killed
+
+    spawned, status, killed = asyncio.run(scenario())
+    assert status["alive"] and status["pid"] == spawned["pid"]
+    assert killed["method"] == "terminated"
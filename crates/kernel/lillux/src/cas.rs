@@ -997,6 +997,8 @@ fn store_exact_entry(
 
 use clap::Subcommand;
 
+use crate::exec::sys_error::{ErrorCode, SysError};
+
 #[derive(Subcommand)]
 pub enum CasAction {
     /// Store content from stdin
@@ -1045,8 +1047,11 @@ pub fn run(action: CasAction) -> serde_json::Value {
             _ => unreachable!(),
         };
         if !valid_hash(h) {
-            eprintln!("invalid hash: expected 64 hex chars");
-            std::process::exit(1);
+            return SysError::new(
+                ErrorCode::InvalidArgument,
+                "invalid hash: expected 64 hex chars",
+            )
+            .failed();
         }
     }
     match action {
@@ -1121,9 +1126,6 @@ fn cli_fetch(root: &str, hash: &str, blob: bool) -> serde_json::Value {
             let _ = std::io::Write::write_all(&mut std::io::stdout(), &bytes);
             std::process::exit(0);
         }
-        _ => {
-            eprintln!("not found");
-            std::process::exit(1);
-        }
+        _ => SysError::new(ErrorCode::NotFound, "not found").failed(),
     }
 }
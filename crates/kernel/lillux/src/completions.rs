@@ -0,0 +1,118 @@
+//! Shell completion scripts and man pages for the `lillux` command tree.
+//!
+//! Scripts come from clap_complete. For bash, zsh, and fish they also
+//! complete `--name` with the live registered processes, asking
+//! `lillux --output text exec names` each time so new names show up
+//! without regenerating anything.
+
+use std::path::{Path, PathBuf};
+
+use clap::Command;
+use clap_complete::Shell;
+
+/// Runs at completion time; prints one live name per line.
+const NAMES: &str = "lillux --output text exec names 2>/dev/null";
+
+/// The completion script for `shell`.
+pub fn script(shell: Shell, mut cmd: Command) -> String {
+    let bin = cmd.get_name().to_string();
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut cmd, &bin, &mut out);
+    let mut script = String::from_utf8_lossy(&out).into_owned();
+    script.push_str(&names(shell, &bin));
+    script
+}
+
+/// Registered-name completion for `--name`, layered over the generated
+/// function where the shell allows it.
+fn names(shell: Shell, bin: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"
+_{bin}_with_names() {{
+    if [[ "${{COMP_WORDS[COMP_CWORD-1]}}" == "--name" ]]; then
+        COMPREPLY=( $(compgen -W "$({NAMES})" -- "${{COMP_WORDS[COMP_CWORD]}}") )
+        return 0
+    fi
+    _{bin} "$@"
+}}
+complete -F _{bin}_with_names -o nosort -o bashdefault -o default {bin}
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"
+_{bin}_with_names() {{
+    if [[ ${{words[CURRENT-1]}} == --name ]]; then
+        compadd -- ${{(f)"$({NAMES})"}}
+    else
+        _{bin} "$@"
+    fi
+}}
+compdef _{bin}_with_names {bin}
+"#
+        ),
+        Shell::Fish => format!("\ncomplete -c {bin} -l name -f -a '({NAMES})'\n"),
+        _ => String::new(),
+    }
+}
+
+/// Write `<bin>.1` and a page per subcommand (`<bin>-exec-spawn.1`, ...)
+/// into `dir`. Returns the pages written.
+pub fn man_pages(cmd: Command, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+    let mut pages = Vec::new();
+    write_pages(cmd, dir, &mut pages)?;
+    Ok(pages)
+}
+
+fn write_pages(cmd: Command, dir: &Path, pages: &mut Vec<PathBuf>) -> Result<(), String> {
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        write_pages(sub.clone(), dir, pages)?;
+    }
+    let page = clap_mangen::Man::new(cmd);
+    let path = page
+        .generate_to(dir)
+        .map_err(|e| format!("Failed to write {}: {e}", page.get_filename()))?;
+    pages.push(path);
+    Ok(())
+}
+
+/// The top-level man page.
+pub fn man_page(cmd: Command) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    clap_mangen::Man::new(cmd)
+        .render(&mut out)
+        .map_err(|e| format!("Failed to render the man page: {e}"))?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli() -> Command {
+        Command::new("lillux").subcommand(
+            Command::new("exec")
+                .subcommand(Command::new("kill").arg(clap::Arg::new("name").long("name"))),
+        )
+    }
+
+    #[test]
+    fn scripts_complete_registered_names_and_pages_cover_subcommands() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell, cli());
+            assert!(script.contains("exec names"), "{shell}: {script}");
+        }
+        assert!(!script(Shell::Elvish, cli()).contains("exec names"));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let pages = man_pages(cli(), tmp.path()).unwrap();
+        let names: Vec<_> = pages
+            .iter()
+            .map(|page| page.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["lillux-exec-kill.1", "lillux-exec.1", "lillux.1"]);
+    }
+}
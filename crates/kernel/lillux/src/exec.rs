@@ -11,7 +11,100 @@ use std::time::{Duration, Instant};
 #[cfg(target_os = "linux")]
 use std::os::fd::{AsFd as _, AsRawFd as _, BorrowedFd, FromRawFd as _, OwnedFd};
 
-use clap::Subcommand;
+#[cfg(feature = "orchestration")]
+pub mod api;
+#[cfg(feature = "orchestration")]
+pub mod audit;
+#[cfg(feature = "orchestration")]
+pub mod batch;
+#[cfg(feature = "orchestration")]
+mod cli;
+#[cfg(feature = "orchestration")]
+pub mod config;
+#[cfg(feature = "orchestration")]
+pub mod container;
+#[cfg(feature = "orchestration")]
+pub mod cores;
+#[cfg(feature = "orchestration")]
+pub mod cron;
+#[cfg(feature = "orchestration")]
+pub mod describe;
+#[cfg(feature = "orchestration")]
+pub mod doctor;
+#[cfg(feature = "orchestration")]
+pub mod environ;
+#[cfg(feature = "orchestration")]
+pub mod fifo;
+#[cfg(feature = "orchestration")]
+pub mod health;
+#[cfg(feature = "orchestration")]
+pub mod launchd;
+#[cfg(feature = "orchestration")]
+pub mod lock;
+#[cfg(feature = "orchestration")]
+pub mod log_cap;
+#[cfg(feature = "orchestration")]
+pub mod logs;
+#[cfg(feature = "orchestration")]
+pub mod mcp;
+#[cfg(feature = "orchestration")]
+pub mod metrics;
+#[cfg(feature = "orchestration")]
+pub mod on_exit;
+#[cfg(feature = "orchestration")]
+pub mod otel;
+#[cfg(feature = "orchestration")]
+pub mod pipeline;
+#[cfg(feature = "orchestration")]
+pub mod port_alloc;
+#[cfg(feature = "orchestration")]
+pub mod ports;
+#[cfg(feature = "orchestration")]
+pub mod proxy;
+#[cfg(feature = "orchestration")]
+pub mod ps;
+#[cfg(feature = "orchestration")]
+pub mod rate_limit;
+#[cfg(feature = "orchestration")]
+pub mod registry;
+#[cfg(feature = "orchestration")]
+pub mod reload;
+#[cfg(feature = "orchestration")]
+pub mod remote;
+#[cfg(feature = "orchestration")]
+pub mod retry;
+#[cfg(feature = "orchestration")]
+pub mod rusage;
+#[cfg(feature = "orchestration")]
+pub mod sample;
+#[cfg(feature = "orchestration")]
+pub mod schedule;
+#[cfg(feature = "orchestration")]
+pub mod serve;
+#[cfg(feature = "orchestration")]
+pub mod subreaper;
+#[cfg(feature = "orchestration")]
+pub mod systemd;
+
+pub mod procinfo;
+pub mod sandbox;
+pub mod sys_error;
+
+#[cfg(feature = "orchestration")]
+pub use api::{ExecError, KillOutcome, Killer, ProcessStatus, Spawned, Spawner};
+#[cfg(feature = "async")]
+use cli::kill_recorded_async;
+#[cfg(feature = "orchestration")]
+pub use cli::{command, run, ExecAction, ExecOptions, ExportTarget, ListFormat, ScheduleAction};
+#[cfg(feature = "orchestration")]
+use cli::{
+    kill_covers_group, kill_recorded, kill_registered, list_registered, logged_entry, named_entry,
+    process_status, spawn_recorded, spawn_registered, Killed, StatusDetail,
+};
+pub use procinfo::ProcessInfo;
+#[cfg(feature = "orchestration")]
+pub use registry::{PipelineStage, Registry, RegistryEntry};
+pub use sys_error::SysError;
 
 // ---------------------------------------------------------------------------
 // Library types — clean Rust API, no JSON
@@ -3790,12 +3883,24 @@ pub fn lib_spawn_detached(
     envs: &[(String, String)],
 ) -> Result<SpawnResult, String> {
     let envs_str: Vec<String> = envs.iter().map(|(k, v)| format!("{k}={v}")).collect();
-    spawn_detached(cmd, args, log, &envs_str, None).map(|pid| SpawnResult { pid })
+    spawn_detached(
+        cmd,
+        args,
+        DetachedLog::file(log),
+        &envs_str,
+        DetachedStdin::Null,
+        false,
+        None,
+    )
+    .map(|pid| SpawnResult { pid })
+    .map_err(String::from)
 }
 
 /// Kill a process by PID. Returns the method used: "terminated", "killed", or "already_dead".
 pub fn lib_kill(pid: u32, grace: f64) -> Result<String, String> {
-    kill_process(pid, grace).map(|s| s.to_string())
+    kill_process(pid, grace)
+        .map(|s| s.to_string())
+        .map_err(String::from)
 }
 
 /// Check if a process is alive.
@@ -3803,87 +3908,9 @@ pub fn lib_is_alive(pid: u32) -> bool {
     is_alive(pid)
 }
 
-// ---------------------------------------------------------------------------
-// CLI types and entry point
-// ---------------------------------------------------------------------------
-
-#[derive(Subcommand)]
-pub enum ExecAction {
-    /// Run a command, wait for completion, capture output
-    Run {
-        #[arg(long)]
-        cmd: String,
-        #[arg(long = "arg", allow_hyphen_values = true)]
-        args: Vec<String>,
-        #[arg(long)]
-        cwd: Option<String>,
-        #[arg(long)]
-        stdin: Option<String>,
-        #[arg(long)]
-        stdin_pipe: bool,
-        #[arg(long = "env")]
-        envs: Vec<String>,
-        #[arg(long, default_value_t = 300.0)]
-        timeout: f64,
-    },
-    /// Spawn a detached/daemonized child process
-    Spawn {
-        #[arg(long)]
-        cmd: String,
-        #[arg(long = "arg", allow_hyphen_values = true)]
-        args: Vec<String>,
-        #[arg(long)]
-        log: Option<String>,
-        #[arg(long = "env")]
-        envs: Vec<String>,
-        #[arg(long)]
-        stdin: Option<String>,
-        #[arg(long)]
-        stdin_pipe: bool,
-    },
-    /// Kill a process by PID
-    Kill {
-        #[arg(long)]
-        pid: u32,
-        #[arg(long, default_value_t = 3.0)]
-        grace: f64,
-    },
-    /// Stream a command's output with raw passthrough (no JSON wrapping)
-    Stream {
-        #[arg(long)]
-        cmd: String,
-        #[arg(long = "arg", allow_hyphen_values = true)]
-        args: Vec<String>,
-        #[arg(long)]
-        cwd: Option<String>,
-        #[arg(long)]
-        stdin: Option<String>,
-        #[arg(long)]
-        stdin_pipe: bool,
-        #[arg(long = "env")]
-        envs: Vec<String>,
-        #[arg(long, default_value_t = 300.0)]
-        timeout: f64,
-    },
-    /// Check if a process is alive
-    Status {
-        #[arg(long)]
-        pid: u32,
-    },
-}
-
-fn resolve_stdin(stdin_arg: Option<String>, stdin_pipe: bool) -> Option<String> {
-    if let Some(data) = stdin_arg {
-        return Some(data);
-    }
-    if stdin_pipe {
-        let mut buf = String::new();
-        let _ = std::io::stdin().read_to_string(&mut buf);
-        if !buf.is_empty() {
-            return Some(buf);
-        }
-    }
-    None
+/// Inspect a live process. Returns `None` if it does not exist.
+pub fn lib_process_info(pid: u32) -> Option<ProcessInfo> {
+    procinfo::inspect(pid)
 }
 
 /// Apply env key=value pairs to a Command. Callers should call
@@ -3904,263 +3931,65 @@ fn write_stdin(child: &mut process::Child, data: Option<&str>) {
     }
 }
 
-fn setup_log(command: &mut process::Command, log: Option<&str>) -> Result<(), String> {
-    if let Some(path) = log {
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .map_err(|e| format!("Failed to open log file: {e}"))?;
-        let file2 = file
-            .try_clone()
-            .map_err(|e| format!("Failed to clone log fd: {e}"))?;
-        command.stdout(file).stderr(file2);
-    } else {
-        command.stdout(Stdio::null()).stderr(Stdio::null());
-    }
-    Ok(())
-}
-
-pub fn run(action: ExecAction) -> serde_json::Value {
-    match action {
-        ExecAction::Run {
-            cmd,
-            args,
-            cwd,
-            stdin,
-            stdin_pipe,
-            envs,
-            timeout,
-        } => do_exec(
-            &cmd,
-            &args,
-            cwd.as_deref(),
-            resolve_stdin(stdin, stdin_pipe).as_deref(),
-            &envs,
-            timeout,
-        ),
-        ExecAction::Spawn {
-            cmd,
-            args,
-            log,
-            envs,
-            stdin,
-            stdin_pipe,
-        } => {
-            match spawn_detached(
-                &cmd,
-                &args,
-                log.as_deref(),
-                &envs,
-                resolve_stdin(stdin, stdin_pipe).as_deref(),
-            ) {
-                Ok(pid) => serde_json::json!({ "success": true, "pid": pid }),
-                Err(e) => serde_json::json!({ "success": false, "error": e }),
-            }
+fn setup_log(command: &mut process::Command, log: DetachedLog<'_>) -> Result<(), SysError> {
+    match log {
+        DetachedLog::Null => {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
         }
-        ExecAction::Stream {
-            cmd,
-            args,
-            cwd,
-            stdin,
-            stdin_pipe,
-            envs,
-            timeout,
-        } => {
-            let code = do_stream(
-                &cmd,
-                &args,
-                cwd.as_deref(),
-                resolve_stdin(stdin, stdin_pipe).as_deref(),
-                &envs,
-                timeout,
-            );
-            process::exit(code);
+        DetachedLog::File(path) => {
+            let file = open_log(path)?;
+            let file2 = file
+                .try_clone()
+                .map_err(|e| format!("Failed to clone log fd: {e}"))?;
+            command.stdout(file).stderr(file2);
+        }
+        DetachedLog::Pipe(pipe) => {
+            let pipe2 = pipe
+                .try_clone()
+                .map_err(|e| format!("Failed to clone log pipe: {e}"))?;
+            command.stdout(pipe).stderr(pipe2);
         }
-        ExecAction::Kill { pid, grace } => match kill_process(pid, grace) {
-            Ok(method) => serde_json::json!({ "success": true, "pid": pid, "method": method }),
-            Err(e) => serde_json::json!({ "success": false, "pid": pid, "error": e }),
-        },
-        ExecAction::Status { pid } => serde_json::json!({ "pid": pid, "alive": is_alive(pid) }),
     }
+    Ok(())
 }
 
-fn do_exec(
-    cmd: &str,
-    args: &[String],
-    cwd: Option<&str>,
-    stdin_data: Option<&str>,
-    envs: &[String],
-    timeout: f64,
-) -> serde_json::Value {
-    let r = lib_run(SubprocessRequest {
-        cmd: cmd.to_string(),
-        argv0: None,
-        args: args.to_vec(),
-        cwd: cwd.map(|s| s.to_string()),
-        envs: envs
-            .iter()
-            .filter_map(|e| {
-                e.split_once('=')
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-            })
-            .collect(),
-        stdin_data: stdin_data.map(|s| s.to_string()),
-        timeout,
-        limits: None,
-        inherited_fds: Vec::new(),
-        supervised_status: None,
-    });
-    serde_json::json!({
-        "success": r.success, "stdout": r.stdout, "stderr": r.stderr,
-        "return_code": r.exit_code, "duration_ms": r.duration_ms,
-        "timed_out": r.timed_out,
-        "output_limit_exceeded": r.output_limit_exceeded.map(OutputLimitExceeded::as_str),
-        "stdout_truncated": r.stdout_truncated,
-        "stderr_truncated": r.stderr_truncated,
-    })
+/// Create or empty the log at `path` for a child to write. It is opened
+/// for appending so `reload --rotate-log` can truncate it underneath the
+/// child.
+fn open_log(path: &str) -> Result<std::fs::File, SysError> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|file| file.set_len(0).map(|()| file))
+        .map_err(|e| SysError::io("Failed to open log file", "open", &e).path(path))
 }
 
-/// Stream mode: raw passthrough of child stdout/stderr, no JSON wrapping.
-/// Returns: child exit code, 124 on timeout, 125 on spawn failure.
-fn do_stream(
-    cmd: &str,
-    args: &[String],
-    cwd: Option<&str>,
-    stdin_data: Option<&str>,
-    envs: &[String],
-    timeout: f64,
-) -> i32 {
-    let mut command = process::Command::new(cmd);
-    command.args(args);
-    command.env_clear();
-    set_envs(&mut command, envs);
-    // Set PYTHONUNBUFFERED for Python children to ensure streaming latency
-    command.env("PYTHONUNBUFFERED", "1");
-    if let Some(dir) = cwd {
-        command.current_dir(dir);
-    }
-    command.stdin(if stdin_data.is_some() {
-        Stdio::piped()
-    } else {
-        Stdio::null()
-    });
-    command.stdout(Stdio::piped()).stderr(Stdio::piped());
-
-    let mut child = match command.spawn() {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to spawn: {e}");
-            return 125;
-        }
-    };
-
-    let stderr_handle = child.stderr.take();
-    let stderr_thread = thread::spawn(move || {
-        let mut buf = [0u8; 8192];
-        if let Some(mut err) = stderr_handle {
-            let mut stderr_out = std::io::stderr();
-            loop {
-                match err.read(&mut buf) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        let _ = stderr_out.write_all(&buf[..n]);
-                        let _ = stderr_out.flush();
-                    }
-                    Err(_) => break,
-                }
-            }
-        }
-    });
-
-    // Forward stdout: raw chunks with flush
-    let stdout_handle = child.stdout.take();
-    let stdout_thread = thread::spawn(move || {
-        let mut buf = [0u8; 8192];
-        if let Some(mut out) = stdout_handle {
-            let mut stdout_out = std::io::stdout();
-            loop {
-                match out.read(&mut buf) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        let _ = stdout_out.write_all(&buf[..n]);
-                        let _ = stdout_out.flush();
-                    }
-                    Err(_) => break,
-                }
-            }
-        }
-    });
+/// What a detached child reads on stdin.
+#[cfg_attr(not(feature = "orchestration"), allow(dead_code))]
+enum DetachedStdin<'a> {
+    Null,
+    /// Written through a pipe once, right after spawn.
+    Data(&'a str),
+    /// An open file handed to the child, e.g. a `--stdin-fifo`.
+    File(std::fs::File),
+    /// The read end of a pipe, e.g. a `log-writer`'s input.
+    Pipe(std::io::PipeReader),
+}
 
-    // Wait with timeout. A non-positive timeout is the no-timeout sentinel.
-    let timeout_rx = if let Some(timeout_dur) = request_timeout_duration(timeout) {
-        let (tx, rx) = std::sync::mpsc::channel();
-        let _timer = thread::spawn(move || {
-            thread::sleep(timeout_dur);
-            let _ = tx.send(());
-        });
-        Some(rx)
-    } else {
-        None
-    };
-    let stream_stop = Arc::new(AtomicBool::new(false));
-    let mut stdin_thread = match spawn_stdin_writer(
-        child.stdin.take(),
-        stdin_data.map(str::to_owned),
-        Arc::clone(&stream_stop),
-    ) {
-        Ok(thread) => thread,
-        Err(error) => {
-            let _ = child.kill();
-            let _ = child.wait();
-            stream_stop.store(true, Ordering::Release);
-            let _ = stdout_thread.join();
-            let _ = stderr_thread.join();
-            eprintln!("Failed to configure nonblocking stdin: {error}");
-            return 125;
-        }
-    };
+/// Where a detached child's stdout and stderr go.
+#[cfg_attr(not(feature = "orchestration"), allow(dead_code))]
+enum DetachedLog<'a> {
+    Null,
+    /// A log file, created or emptied for the child to append to.
+    File(&'a str),
+    /// A pipe to the `log-writer` enforcing `spawn --log-max-bytes`.
+    Pipe(std::io::PipeWriter),
+}
 
-    loop {
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                stream_stop.store(true, Ordering::Release);
-                if let Some(handle) = stdin_thread.take() {
-                    let _ = handle.join();
-                }
-                let _ = stdout_thread.join();
-                let _ = stderr_thread.join();
-                return status.code().unwrap_or(1);
-            }
-            Ok(None) => {
-                if timeout_rx.as_ref().is_some_and(|rx| rx.try_recv().is_ok()) {
-                    let _ = child.kill();
-                    let _ = child.wait();
-                    stream_stop.store(true, Ordering::Release);
-                    if let Some(handle) = stdin_thread.take() {
-                        let _ = handle.join();
-                    }
-                    let _ = stdout_thread.join();
-                    let _ = stderr_thread.join();
-                    eprintln!("Command timed out after {timeout} seconds");
-                    return 124;
-                }
-                thread::sleep(Duration::from_millis(10));
-            }
-            Err(e) => {
-                let _ = child.kill();
-                let _ = child.wait();
-                stream_stop.store(true, Ordering::Release);
-                if let Some(handle) = stdin_thread.take() {
-                    let _ = handle.join();
-                }
-                let _ = stdout_thread.join();
-                let _ = stderr_thread.join();
-                eprintln!("Wait failed: {e}");
-                return 125;
-            }
-        }
+impl<'a> DetachedLog<'a> {
+    fn file(path: Option<&'a str>) -> Self {
+        path.map_or(DetachedLog::Null, DetachedLog::File)
     }
 }
 
@@ -4168,93 +3997,221 @@ fn do_stream(
 fn spawn_detached(
     cmd: &str,
     args: &[String],
-    log: Option<&str>,
+    log: DetachedLog<'_>,
     envs: &[String],
-    stdin_data: Option<&str>,
-) -> Result<u32, String> {
+    stdin: DetachedStdin<'_>,
+    core_dumps: bool,
+    sandbox: Option<&sandbox::Sandbox>,
+) -> Result<u32, SysError> {
     use std::os::unix::process::CommandExt;
+    let sandbox = sandbox.map(sandbox::prepare).transpose()?;
     let mut command = process::Command::new(cmd);
     command.args(args);
     command.env_clear();
     set_envs(&mut command, envs);
-    command.stdin(if stdin_data.is_some() {
-        Stdio::piped()
-    } else {
-        Stdio::null()
-    });
+    let stdin_data = match stdin {
+        DetachedStdin::Null => {
+            command.stdin(Stdio::null());
+            None
+        }
+        DetachedStdin::Data(data) => {
+            command.stdin(Stdio::piped());
+            Some(data)
+        }
+        DetachedStdin::File(file) => {
+            command.stdin(file);
+            None
+        }
+        DetachedStdin::Pipe(pipe) => {
+            command.stdin(pipe);
+            None
+        }
+    };
     setup_log(&mut command, log)?;
     unsafe {
-        command.pre_exec(|| {
+        command.pre_exec(move || {
             libc::setsid();
+            if core_dumps {
+                raise_core_limit();
+            }
+            if let Some(sandbox) = &sandbox {
+                sandbox.install()?;
+            }
             Ok(())
         });
     }
     let mut child = command
         .spawn()
-        .map_err(|e| format!("Failed to spawn: {e}"))?;
+        .map_err(|e| SysError::io("Failed to spawn", "execve", &e).path(cmd))?;
     write_stdin(&mut child, stdin_data);
     Ok(child.id())
 }
 
+/// Raise the soft `RLIMIT_CORE` to the hard limit, for `spawn --core-dumps`. Runs in the child
+/// between fork and exec, so it only makes the one system call pair.
+#[cfg(unix)]
+fn raise_core_limit() {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+            limit.rlim_cur = limit.rlim_max;
+            libc::setrlimit(libc::RLIMIT_CORE, &limit);
+        }
+    }
+}
+
 #[cfg(windows)]
 fn spawn_detached(
     cmd: &str,
     args: &[String],
-    log: Option<&str>,
+    log: DetachedLog<'_>,
     envs: &[String],
-    stdin_data: Option<&str>,
-) -> Result<u32, String> {
+    stdin: DetachedStdin<'_>,
+    _core_dumps: bool,
+    sandbox: Option<&sandbox::Sandbox>,
+) -> Result<u32, SysError> {
     use std::os::windows::process::CommandExt;
+    use sys_error::ErrorCode;
+    if sandbox.is_some() {
+        return Err(SysError::new(
+            ErrorCode::Unsupported,
+            "--sandbox is not supported on this platform",
+        ));
+    }
     let mut command = process::Command::new(cmd);
     command.args(args);
     command.env_clear();
     set_envs(&mut command, envs);
-    command.stdin(if stdin_data.is_some() {
-        Stdio::piped()
-    } else {
-        Stdio::null()
-    });
+    let stdin_data = match stdin {
+        DetachedStdin::Null => {
+            command.stdin(Stdio::null());
+            None
+        }
+        DetachedStdin::Data(data) => {
+            command.stdin(Stdio::piped());
+            Some(data)
+        }
+        DetachedStdin::File(file) => {
+            command.stdin(file);
+            None
+        }
+        DetachedStdin::Pipe(pipe) => {
+            command.stdin(pipe);
+            None
+        }
+    };
     setup_log(&mut command, log)?;
     command.creation_flags(0x00000200 | 0x00000008); // CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS
     let mut child = command
         .spawn()
-        .map_err(|e| format!("Failed to spawn: {e}"))?;
+        .map_err(|e| SysError::io("Failed to spawn", "CreateProcessW", &e).path(cmd))?;
     write_stdin(&mut child, stdin_data);
     Ok(child.id())
 }
 
 #[cfg(unix)]
-fn kill_process(pid: u32, grace: f64) -> Result<&'static str, String> {
-    let pid = pid as i32;
-    if unsafe { libc::kill(pid, 0) } != 0 {
-        return Ok("already_dead");
+fn kill_process(pid: u32, grace: f64) -> Result<&'static str, SysError> {
+    terminate(pid as i32, grace)
+}
+
+/// Whether `pid` (a negative value names a process group) has exited. A
+/// zombie has, though it still takes signals.
+#[cfg(unix)]
+fn gone(pid: i32) -> bool {
+    match u32::try_from(pid) {
+        Ok(pid) => !is_alive(pid),
+        Err(_) => (unsafe { libc::kill(pid, 0) }) != 0,
     }
-    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
-        return Err(format!(
-            "SIGTERM failed: {}",
-            std::io::Error::last_os_error()
-        ));
+}
+
+/// SIGTERM `pid` (a negative value names a process group), escalating to
+/// SIGKILL after `grace` seconds.
+#[cfg(unix)]
+fn terminate(pid: i32, grace: f64) -> Result<&'static str, SysError> {
+    let mut termination = Termination::new(pid, grace);
+    loop {
+        match termination.step() {
+            Step::Wait(pause) => thread::sleep(pause),
+            Step::Done(outcome) => return outcome,
+        }
     }
-    for _ in 0..(grace / 0.1).ceil() as u32 {
-        thread::sleep(Duration::from_millis(100));
-        if unsafe { libc::kill(pid, 0) } != 0 {
-            return Ok("terminated");
+}
+
+/// [`terminate`] for async callers: the grace period is spent in
+/// `tokio::time::sleep`, not on a blocked thread.
+#[cfg(all(unix, feature = "async"))]
+async fn terminate_async(pid: i32, grace: f64) -> Result<&'static str, SysError> {
+    let mut termination = Termination::new(pid, grace);
+    loop {
+        match termination.step() {
+            Step::Wait(pause) => tokio::time::sleep(pause).await,
+            Step::Done(outcome) => return outcome,
         }
     }
-    if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
-        if unsafe { libc::kill(pid, 0) } != 0 {
-            return Ok("terminated");
+}
+
+/// How often a terminating process is checked during its grace period.
+#[cfg(unix)]
+const TERMINATE_POLL: Duration = Duration::from_millis(100);
+
+/// The signals [`terminate`] sends, as steps the caller waits between
+/// however suits it.
+#[cfg(unix)]
+struct Termination {
+    pid: i32,
+    signalled: bool,
+    /// Checks left before SIGKILL.
+    polls: u32,
+}
+
+#[cfg(unix)]
+enum Step {
+    Wait(Duration),
+    Done(Result<&'static str, SysError>),
+}
+
+#[cfg(unix)]
+impl Termination {
+    fn new(pid: i32, grace: f64) -> Self {
+        Self {
+            pid,
+            signalled: false,
+            polls: (grace / TERMINATE_POLL.as_secs_f64()).ceil() as u32,
         }
-        return Err(format!(
-            "SIGKILL failed: {}",
-            std::io::Error::last_os_error()
-        ));
     }
-    Ok("killed")
+
+    fn step(&mut self) -> Step {
+        let pid = self.pid;
+        if !self.signalled {
+            if gone(pid) {
+                return Step::Done(Ok("already_dead"));
+            }
+            if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+                return Step::Done(Err(SysError::last("SIGTERM failed", "kill").pid(pid)));
+            }
+            self.signalled = true;
+        } else if gone(pid) {
+            return Step::Done(Ok("terminated"));
+        }
+        if self.polls > 0 {
+            self.polls -= 1;
+            return Step::Wait(TERMINATE_POLL);
+        }
+        if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
+            if gone(pid) {
+                return Step::Done(Ok("terminated"));
+            }
+            return Step::Done(Err(SysError::last("SIGKILL failed", "kill").pid(pid)));
+        }
+        Step::Done(Ok("killed"))
+    }
 }
 
 #[cfg(windows)]
-fn kill_process(pid: u32, grace: f64) -> Result<&'static str, String> {
+fn kill_process(pid: u32, grace: f64) -> Result<&'static str, SysError> {
     use windows_sys::Win32::Foundation::{CloseHandle, WAIT_OBJECT_0};
     use windows_sys::Win32::System::Threading::*;
     let handle = unsafe {
@@ -4276,16 +4233,16 @@ fn kill_process(pid: u32, grace: f64) -> Result<&'static str, String> {
     if ok != 0 {
         Ok("killed")
     } else {
-        Err(format!(
-            "TerminateProcess failed: {}",
-            std::io::Error::last_os_error()
-        ))
+        Err(SysError::last("TerminateProcess failed", "TerminateProcess").pid(pid as i32))
     }
 }
 
 #[cfg(unix)]
 fn is_alive(pid: u32) -> bool {
-    unsafe { libc::kill(pid as i32, 0) == 0 }
+    // A zombie has exited; only its exit status is left. Linux can tell
+    // from `/proc`, elsewhere that would take a `ps` per call.
+    let signalable = unsafe { libc::kill(pid as i32, 0) } == 0;
+    signalable && !(cfg!(target_os = "linux") && procinfo::state(pid) == Some("zombie"))
 }
 
 #[cfg(windows)]
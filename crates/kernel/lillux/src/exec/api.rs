@@ -0,0 +1,403 @@
+//! Typed access to registered processes for programs embedding lillux.
+//!
+//! The `lillux exec` subcommands answer in JSON for callers across a
+//! process boundary. This is the same spawn, kill, and status, registry
+//! and audit log included, for Rust callers: builders in, structs and an
+//! [`ExecError`] out. With the `async` feature each call also has an
+//! `_async` form for tokio callers.
+//!
+//! ```no_run
+//! use lillux::{Killer, Spawner};
+//!
+//! let web = Spawner::new("./server")
+//!     .arg("--port")
+//!     .arg("8080")
+//!     .env("RUST_LOG", "info")
+//!     .name("web")
+//!     .log("/tmp/web.log")
+//!     .spawn()?;
+//! println!("web is pid {}", web.pid);
+//! Killer::name("web").grace(5.0).kill()?;
+//! # Ok::<(), lillux::ExecError>(())
+//! ```
+
+use std::time::Duration;
+
+use super::procinfo::ProcessInfo;
+use super::registry::{Registry, RegistryEntry};
+use super::retry::SpawnRetry;
+use super::sys_error::{ErrorCode, SysError};
+use super::Killed;
+
+/// Why an operation on a registered process failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// No process is registered, or live, under this name.
+    NotFound(String),
+    /// A live process already holds the name.
+    AlreadyRunning { name: String, pid: u32 },
+    /// The registry could not be opened or read.
+    Registry(String),
+    /// The operation itself failed, e.g. the command could not be started;
+    /// the error keeps the failing system call and its errno.
+    Failed(SysError),
+}
+
+impl std::fmt::Display for ExecError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(name) => write!(formatter, "No live process named '{name}'"),
+            Self::AlreadyRunning { name, pid } => write!(
+                formatter,
+                "A live process named '{name}' is already registered (pid {pid})"
+            ),
+            Self::Registry(detail) => formatter.write_str(detail),
+            Self::Failed(error) => error.fmt(formatter),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+impl ExecError {
+    /// `error` from an operation on `name`, with the cases callers match
+    /// on pulled out by their code.
+    fn of(error: SysError, name: Option<&str>) -> Self {
+        match (error.code, name, error.pid) {
+            (ErrorCode::NotFound, Some(name), _) => Self::NotFound(name.to_string()),
+            (ErrorCode::AlreadyExists, Some(name), Some(pid)) => Self::AlreadyRunning {
+                name: name.to_string(),
+                pid: pid as u32,
+            },
+            _ => Self::Failed(error),
+        }
+    }
+}
+
+/// Builder for a detached, registered process: `lillux exec spawn`.
+#[derive(Debug, Clone)]
+pub struct Spawner {
+    registry: Option<String>,
+    entry: RegistryEntry,
+    stdin: Option<String>,
+    retry: SpawnRetry,
+}
+
+/// A process started by [`Spawner::spawn`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spawned {
+    pub pid: u32,
+    pub name: Option<String>,
+}
+
+impl Spawner {
+    pub fn new(cmd: impl Into<String>) -> Self {
+        Self {
+            registry: None,
+            entry: RegistryEntry {
+                cmd: cmd.into(),
+                ..Default::default()
+            },
+            stdin: None,
+            retry: SpawnRetry::default(),
+        }
+    }
+
+    /// Registry database to record the process in; see [`Registry::locate`].
+    pub fn registry(mut self, path: impl Into<String>) -> Self {
+        self.registry = Some(path.into());
+        self
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.entry.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.entry.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add a variable to the environment, which starts out empty.
+    pub fn env(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        self.entry.envs.push(format!("{key}={value}"));
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.entry.name = Some(name.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.entry.tags.push(tag.into());
+        self
+    }
+
+    /// File receiving stdout and stderr.
+    pub fn log(mut self, path: impl Into<String>) -> Self {
+        self.entry.log = Some(path.into());
+        self
+    }
+
+    /// Data written to the process's stdin, which is then closed.
+    pub fn stdin(mut self, data: impl Into<String>) -> Self {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// Try the spawn up to `retries` more times when it fails
+    /// transiently, pausing `backoff`, then twice that, and so on.
+    pub fn spawn_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retry = SpawnRetry { retries, backoff };
+        self
+    }
+
+    pub fn spawn(self) -> Result<Spawned, ExecError> {
+        let name = self.entry.name.clone();
+        let (outcome, _) = super::spawn_recorded(
+            self.registry.as_deref(),
+            self.entry,
+            self.stdin.as_deref(),
+            &self.retry,
+        );
+        match outcome {
+            Ok(spawned) => Ok(Spawned {
+                pid: spawned.pid,
+                name: spawned.name,
+            }),
+            Err(failure) => Err(ExecError::of(failure.error, name.as_deref())),
+        }
+    }
+}
+
+/// How [`Killer::kill`] stopped the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillOutcome {
+    /// Exited within the grace period after SIGTERM.
+    Terminated,
+    /// Still running after the grace period and killed.
+    Killed,
+    /// Had already exited.
+    AlreadyDead,
+}
+
+/// Builder for stopping a process: `lillux exec kill`.
+#[derive(Debug, Clone)]
+pub struct Killer {
+    registry: Option<String>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    self_only: bool,
+    group: bool,
+}
+
+impl Killer {
+    pub fn pid(pid: u32) -> Self {
+        Self {
+            registry: None,
+            pid: Some(pid),
+            name: None,
+            grace: 3.0,
+            self_only: false,
+            group: false,
+        }
+    }
+
+    /// The live process registered under `name`.
+    pub fn name(name: impl Into<String>) -> Self {
+        Self {
+            registry: None,
+            pid: None,
+            name: Some(name.into()),
+            grace: 3.0,
+            self_only: false,
+            group: false,
+        }
+    }
+
+    pub fn registry(mut self, path: impl Into<String>) -> Self {
+        self.registry = Some(path.into());
+        self
+    }
+
+    /// Seconds between SIGTERM and SIGKILL.
+    pub fn grace(mut self, seconds: f64) -> Self {
+        self.grace = seconds;
+        self
+    }
+
+    /// For a [`name`](Killer::name): only the process itself, not the rest
+    /// of the process group it was spawned leading.
+    pub fn self_only(mut self) -> Self {
+        self.self_only = true;
+        self
+    }
+
+    /// For a [`pid`](Killer::pid) that is registered: the rest of the
+    /// process group it was spawned leading too, as a name covers by
+    /// default.
+    pub fn group(mut self) -> Self {
+        self.group = true;
+        self
+    }
+
+    fn covers_group(&self) -> bool {
+        super::kill_covers_group(self.name.as_deref(), self.self_only, self.group)
+    }
+
+    pub fn kill(self) -> Result<KillOutcome, ExecError> {
+        let (outcome, _) = super::kill_recorded(
+            self.registry.as_deref(),
+            self.pid,
+            self.name.clone(),
+            self.grace,
+            self.covers_group(),
+        );
+        kill_outcome(outcome, self.name.as_deref())
+    }
+}
+
+fn kill_outcome(
+    outcome: Result<Killed, SysError>,
+    name: Option<&str>,
+) -> Result<KillOutcome, ExecError> {
+    match outcome.map_err(|e| ExecError::of(e, name))?.method {
+        "terminated" => Ok(KillOutcome::Terminated),
+        "already_dead" => Ok(KillOutcome::AlreadyDead),
+        _ => Ok(KillOutcome::Killed),
+    }
+}
+
+/// A registered process as `lillux exec status --name` sees it.
+#[derive(Debug, Clone)]
+pub struct ProcessStatus {
+    pub pid: u32,
+    pub name: String,
+    pub alive: bool,
+    /// How it ended, once that has been observed.
+    pub exit_status: Option<String>,
+    /// Resource usage and identity while it runs.
+    pub info: Option<ProcessInfo>,
+}
+
+impl ProcessStatus {
+    /// The running instance of `name`, else its most recent run.
+    pub fn of(registry: Option<&str>, name: &str) -> Result<Self, ExecError> {
+        let registry = locate(registry)?;
+        let entry = match registry.find_live(name).map_err(ExecError::Registry)? {
+            Some(entry) => entry,
+            None => registry
+                .find_latest(name)
+                .map_err(ExecError::Registry)?
+                .ok_or_else(|| ExecError::NotFound(name.to_string()))?,
+        };
+        let info = entry.live_info();
+        Ok(Self {
+            pid: entry.pid,
+            name: name.to_string(),
+            alive: info.is_some(),
+            exit_status: entry.exit_status,
+            info,
+        })
+    }
+}
+
+/// Async counterparts, with the `async` feature. Waits (kill grace
+/// periods, exits) are tokio timers; registry and `/proc` reads run on the
+/// blocking pool.
+#[cfg(feature = "async")]
+mod nonblocking {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// How often [`ProcessStatus::wait_exit`] looks again.
+    const EXIT_POLL: Duration = Duration::from_millis(100);
+
+    async fn blocking<T: Send + 'static>(
+        f: impl FnOnce() -> Result<T, ExecError> + Send + 'static,
+    ) -> Result<T, ExecError> {
+        tokio::task::spawn_blocking(f)
+            .await
+            .map_err(|e| ExecError::Failed(format!("Blocking task failed: {e}").into()))?
+    }
+
+    impl Spawner {
+        pub async fn spawn_async(self) -> Result<Spawned, ExecError> {
+            blocking(move || self.spawn()).await
+        }
+    }
+
+    impl Killer {
+        /// [`kill`](Killer::kill) without blocking a thread for the grace
+        /// period.
+        pub async fn kill_async(self) -> Result<KillOutcome, ExecError> {
+            let group = self.covers_group();
+            let (outcome, _) = crate::exec::kill_recorded_async(
+                self.registry,
+                self.pid,
+                self.name.clone(),
+                self.grace,
+                group,
+            )
+            .await;
+            kill_outcome(outcome, self.name.as_deref())
+        }
+    }
+
+    impl ProcessStatus {
+        pub async fn of_async(registry: Option<String>, name: String) -> Result<Self, ExecError> {
+            blocking(move || Self::of(registry.as_deref(), &name)).await
+        }
+
+        /// The status of every process in `names`, looked up concurrently,
+        /// in the order given.
+        pub async fn all_async(
+            registry: Option<String>,
+            names: Vec<String>,
+        ) -> Vec<Result<Self, ExecError>> {
+            let lookups: Vec<_> = names
+                .into_iter()
+                .map(|name| tokio::spawn(Self::of_async(registry.clone(), name)))
+                .collect();
+            let mut statuses = Vec::with_capacity(lookups.len());
+            for lookup in lookups {
+                statuses.push(
+                    lookup
+                        .await
+                        .unwrap_or_else(|e| Err(ExecError::Failed(e.to_string().into()))),
+                );
+            }
+            statuses
+        }
+
+        /// Wait until the process registered as `name` is gone, or until
+        /// `timeout` passes; the status returned then still reads `alive`.
+        pub async fn wait_exit(
+            registry: Option<String>,
+            name: String,
+            timeout: Option<Duration>,
+        ) -> Result<Self, ExecError> {
+            let deadline = timeout.map(|timeout| Instant::now() + timeout);
+            loop {
+                let status = Self::of_async(registry.clone(), name.clone()).await?;
+                if !status.alive || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Ok(status);
+                }
+                tokio::time::sleep(EXIT_POLL).await;
+            }
+        }
+    }
+}
+
+fn locate(registry: Option<&str>) -> Result<Registry, ExecError> {
+    Registry::locate(registry).map_err(ExecError::Registry)
+}
@@ -0,0 +1,174 @@
+//! `lillux exec audit`: the append-only log of operations that change
+//! what is running.
+//!
+//! Every spawn, kill, reload, pipeline, send, schedule change, and
+//! registry gc is appended to the `audit` table of the registry with its
+//! arguments, outcome, and the caller's trace id (from `TRACEPARENT`).
+//! SQLite triggers refuse updates and deletes, and each record carries
+//! the SHA-256 of its predecessor's hash and its own content, so an edit
+//! made behind the triggers' back breaks the chain and `audit verify`
+//! reports where. Anchoring the `head` hash elsewhere also catches truncation.
+
+use clap::Subcommand;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::registry::{Registry, RegistryEntry};
+
+/// `prev_hash` of the first record.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// Print audit records, oldest first
+    Query {
+        /// Only records from the last DURATION (e.g. `30m`, `1h`, `7d`)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only records of this operation (`spawn`, `kill`, ...)
+        #[arg(long)]
+        operation: Option<String>,
+        /// Only records carrying this trace id
+        #[arg(long)]
+        trace_id: Option<String>,
+    },
+    /// Recompute the hash chain and report the first record that breaks it
+    Verify,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRecord {
+    pub seq: i64,
+    pub at: String,
+    pub at_ms: i64,
+    pub operation: String,
+    pub trace_id: Option<String>,
+    pub args: Value,
+    pub outcome: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// The hash chained into the next record: SHA-256 over the previous hash
+/// and this record's content exactly as stored.
+pub fn chain_hash(
+    prev_hash: &str,
+    seq: i64,
+    at_ms: i64,
+    operation: &str,
+    trace_id: Option<&str>,
+    args: &str,
+    outcome: &str,
+) -> String {
+    let content = json!([prev_hash, seq, at_ms, operation, trace_id, args, outcome]);
+    crate::cas::sha256_hex(content.to_string().as_bytes())
+}
+
+/// Arguments of a spawn as logged. Only the names of environment
+/// variables are kept: a record can never be scrubbed of a secret later.
+pub fn entry_args(entry: &RegistryEntry) -> Value {
+    let mut args = json!({
+        "name": entry.name,
+        "cmd": entry.cmd,
+        "args": entry.args,
+        "tags": entry.tags,
+        "log": entry.log,
+        "env": env_names(&entry.envs),
+    });
+    if let Some(container) = &entry.container {
+        args["backend"] = json!(container.runtime);
+        args["image"] = container.image.clone().into();
+    }
+    args
+}
+
+pub fn env_names(envs: &[String]) -> Vec<&str> {
+    envs.iter()
+        .map(|env| env.split_once('=').map_or(env.as_str(), |(key, _)| key))
+        .collect()
+}
+
+/// Append `operation` and its `result` to the log. The operation already
+/// happened, so a failure to log it is reported as `audit_error` on the
+/// result rather than replacing it.
+pub fn record(registry: Option<&str>, operation: &str, args: Value, result: &mut Value) {
+    let trace_id = super::otel::traceparent()
+        .and_then(|traceparent| traceparent.split('-').nth(1).map(str::to_string));
+    if let Err(e) = Registry::locate(registry)
+        .and_then(|registry| registry.append_audit(operation, trace_id.as_deref(), &args, result))
+    {
+        if let Some(result) = result.as_object_mut() {
+            result.insert("audit_error".to_string(), e.into());
+        }
+    }
+}
+
+pub fn run(registry: Option<&str>, action: AuditAction) -> Value {
+    let registry = match Registry::locate(registry) {
+        Ok(registry) => registry,
+        Err(e) => return json!({ "error": e }),
+    };
+    match action {
+        AuditAction::Query {
+            since,
+            operation,
+            trace_id,
+        } => {
+            let since_ms = match since.as_deref().map(crate::time::parse_duration) {
+                Some(Ok(window)) => crate::time::timestamp_millis() - window.as_millis() as i64,
+                Some(Err(e)) => return json!({ "error": e }),
+                None => 0,
+            };
+            match registry.audit_records(since_ms) {
+                Ok(records) => Value::Array(
+                    records
+                        .into_iter()
+                        .filter(|r| operation.as_ref().is_none_or(|op| &r.operation == op))
+                        .filter(|r| {
+                            trace_id
+                                .as_ref()
+                                .is_none_or(|id| r.trace_id.as_ref() == Some(id))
+                        })
+                        .map(|r| serde_json::to_value(r).unwrap_or_default())
+                        .collect(),
+                ),
+                Err(e) => json!({ "error": e }),
+            }
+        }
+        AuditAction::Verify => match registry.audit_records(0) {
+            Ok(records) => verify(&records),
+            Err(e) => json!({ "error": e }),
+        },
+    }
+}
+
+/// Check that `records` (the whole log, in order) form an unbroken chain.
+pub fn verify(records: &[AuditRecord]) -> Value {
+    let mut prev = GENESIS_HASH;
+    for record in records {
+        let expected = chain_hash(
+            prev,
+            record.seq,
+            record.at_ms,
+            &record.operation,
+            record.trace_id.as_deref(),
+            &record.args.to_string(),
+            &record.outcome.to_string(),
+        );
+        let error = if record.prev_hash != prev {
+            "prev_hash does not match the preceding record"
+        } else if record.hash != expected {
+            "content does not match its hash"
+        } else {
+            prev = &record.hash;
+            continue;
+        };
+        return json!({
+            "valid": false,
+            "records": records.len(),
+            "broken_at": record.seq,
+            "error": error,
+        });
+    }
+    json!({ "valid": true, "records": records.len(), "head": prev })
+}
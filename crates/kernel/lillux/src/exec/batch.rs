@@ -0,0 +1,91 @@
+//! `lillux exec batch`: run newline-delimited commands from stdin through
+//! one process, one result line per command.
+//!
+//! A command is `{"id": ..., "method": "spawn", "params": {...}}`, with the
+//! methods and parameters of `serve`. Commands run in order, each to
+//! completion, so results come back in input order: the `--output`
+//! envelope with the command's `id` echoed. Unlike `serve` there are no
+//! notifications, and the process exits once stdin closes: 0 when every
+//! command succeeded, [`output::EXIT_PARTIAL`] when only some did, and the
+//! first failure's status when none did.
+
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use super::serve::Server;
+use crate::output;
+
+/// Run every command in `input`, writing results to `out`. Returns the exit
+/// status; with `stop_on_error`, the first failure ends the batch.
+pub fn run(
+    registry: Option<String>,
+    input: impl BufRead,
+    out: &mut impl Write,
+    stop_on_error: bool,
+) -> i32 {
+    let server = Server::new(registry);
+    let mut succeeded = 0;
+    let mut first_failure = None;
+    for line in input.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = execute(&server, &line);
+        let _ = writeln!(out, "{result}");
+        let _ = out.flush();
+        if result["ok"] == true {
+            succeeded += 1;
+        } else {
+            first_failure.get_or_insert(output::exit_code(&result));
+            if stop_on_error {
+                break;
+            }
+        }
+    }
+    match first_failure {
+        None => output::EXIT_OK,
+        Some(_) if succeeded > 0 => output::EXIT_PARTIAL,
+        Some(status) => status,
+    }
+}
+
+/// The result line for one command.
+pub fn execute(server: &std::sync::Arc<Server>, line: &str) -> Value {
+    let command: Value = match serde_json::from_str(line) {
+        Ok(command) => command,
+        Err(e) => {
+            let mut result = invalid(format!("Invalid command: {e}"));
+            result["id"] = Value::Null;
+            return result;
+        }
+    };
+    let id = command.get("id").cloned().unwrap_or(Value::Null);
+    let mut result = match command.get("method").and_then(Value::as_str) {
+        Some(method) => {
+            let params = command.get("params").cloned().unwrap_or(json!({}));
+            call(server, method, params)
+        }
+        None => invalid("Command has no method".to_string()),
+    };
+    result["id"] = id;
+    result
+}
+
+/// `method` with `params` on `server`, in the output envelope.
+pub fn call(server: &std::sync::Arc<Server>, method: &str, params: Value) -> Value {
+    match server.call(method, params) {
+        Ok(result) => output::envelope(result),
+        // The method ran and failed: its own result says how.
+        Err((_, _, Some(failed))) => output::envelope(failed),
+        Err((_, message, None)) => invalid(message),
+    }
+}
+
+/// A command that could not be run as given.
+fn invalid(message: String) -> Value {
+    let mut error = output::describe(&message);
+    error["code"] = "invalid_argument".into();
+    json!({ "ok": false, "data": null, "error": error })
+}
@@ -0,0 +1,2504 @@
+//! The `lillux exec` command tree: clap's view of every subcommand and
+//! [`run`], which dispatches one to the orchestration modules beside this
+//! one. The subprocess primitives it builds on stay in the parent module.
+
+use clap::{Subcommand, ValueEnum};
+
+use super::sys_error::ErrorCode;
+use super::*;
+use crate::output;
+
+#[derive(Subcommand)]
+pub enum ExecAction {
+    /// Run a command, wait for completion, capture output
+    Run {
+        #[arg(long)]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long)]
+        cwd: Option<String>,
+        #[arg(long)]
+        stdin: Option<String>,
+        #[arg(long)]
+        stdin_pipe: bool,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        #[arg(long, default_value_t = 300.0)]
+        timeout: f64,
+    },
+    /// Spawn a detached/daemonized child process
+    Spawn {
+        #[arg(long)]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long)]
+        log: Option<String>,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        #[arg(long)]
+        stdin: Option<String>,
+        #[arg(long)]
+        stdin_pipe: bool,
+        /// Connect stdin to a FIFO at this path (created if missing) so
+        /// `lillux exec send` can write to it later
+        #[arg(long, conflicts_with_all = ["stdin", "stdin_pipe"])]
+        stdin_fifo: Option<String>,
+        /// Register the process under this name (must not name a live process)
+        #[arg(long)]
+        name: Option<String>,
+        /// Free-form label recorded in the registry; repeatable
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Pass systemd's `NOTIFY_SOCKET` through so the child can report
+        /// `READY=1` and watchdog pings itself (needs `NotifyAccess=all`)
+        #[arg(long)]
+        sd_notify: bool,
+        /// Run `--cmd` inside a container of `--image` instead of as a bare
+        /// process; `kill`, `status`, and `logs` then go through the runtime
+        #[arg(
+            long,
+            value_enum,
+            requires = "image",
+            conflicts_with_all = ["log", "stdin", "stdin_pipe", "stdin_fifo", "sd_notify"]
+        )]
+        backend: Option<container::Runtime>,
+        #[arg(long, requires = "backend")]
+        image: Option<String>,
+        /// Bind mount `HOST:CONTAINER[:OPTIONS]` into the container; repeatable
+        #[arg(long = "mount", requires = "backend")]
+        mounts: Vec<String>,
+        /// Publish `[IP:]HOST:CONTAINER[/PROTO]` from the container; repeatable
+        #[arg(long = "publish", requires = "backend")]
+        ports: Vec<String>,
+        /// Start with the ports reserved by `port alloc --hold NAME` for
+        /// this `--name`, as `LILLUX_PORTS` and `PORT`
+        #[arg(long, requires = "name")]
+        claim_ports: bool,
+        /// Signal `reload` sends to this process (default `SIGHUP`)
+        #[arg(long, value_name = "SIGNAL", requires = "name")]
+        reload_signal: Option<String>,
+        /// Enable core dumps and collect any crash dump into this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "backend")]
+        core_dumps: Option<String>,
+        /// Isolate the process with a preset (Linux): `no-network`,
+        /// `workspace-only` writes beneath the working directory, or
+        /// `strict` for both plus a syscall filter and tighter limits
+        #[arg(long, value_enum, default_value_t, conflicts_with = "backend")]
+        sandbox: sandbox::Preset,
+        /// Try again this many times when the spawn fails transiently
+        /// (`ETXTBSY`, `EAGAIN`, a sharing violation on Windows)
+        #[arg(long, default_value_t = 0, conflicts_with = "backend")]
+        spawn_retries: u32,
+        /// Pause before the first retry, doubled after each, e.g. `500ms`
+        #[arg(long, value_name = "DURATION", default_value = "500ms")]
+        spawn_backoff: String,
+        /// Keep the log under this many bytes (at least 1024), marking
+        /// where output was dropped
+        #[arg(
+            long,
+            value_name = "BYTES",
+            value_parser = clap::value_parser!(u64).range(log_cap::MIN_BYTES..),
+            conflicts_with = "backend"
+        )]
+        log_max_bytes: Option<u64>,
+        /// What to drop once the log is full
+        #[arg(long, value_enum, default_value_t = log_cap::Overflow::Stop)]
+        log_overflow: log_cap::Overflow,
+        #[command(flatten)]
+        health: Box<health::HealthArgs>,
+    },
+    /// Run commands connected by pipes (`a | b | c`) as one detached unit
+    Pipeline {
+        /// A stage as a JSON argv array, e.g. '["grep","-v","debug"]';
+        /// repeat in pipeline order
+        #[arg(long = "stage", required = true)]
+        stages: Vec<String>,
+        /// File receiving the last stage's stdout and every stage's stderr
+        #[arg(long)]
+        log: Option<String>,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        /// Register the pipeline under this name (must not name a live process)
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Prefix each stage's lines in `--log` with `[command]`, keeping
+        /// each stage's own output in `<log stem>.<command>.<ext>` too
+        #[arg(long, requires = "log")]
+        multiplex: bool,
+        /// Supervise in this process instead of detaching a supervisor
+        #[arg(long, hide = true)]
+        foreground: bool,
+    },
+    /// Answer JSON-RPC 2.0 requests (`spawn`, `kill`, `status`, `wait`,
+    /// `list`, `logs`) from one long-lived process
+    Serve {
+        /// Newline-delimited requests on stdin, responses and `exited`
+        /// notifications on stdout
+        #[arg(long, required = true)]
+        stdio: bool,
+        /// Spawn at most N processes a minute, in bursts of up to N
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_spawns_per_minute: Option<u32>,
+        /// What to do with a spawn over the limit
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = rate_limit::OnLimit::Reject,
+            requires = "max_spawns_per_minute"
+        )]
+        on_spawn_limit: rate_limit::OnLimit,
+    },
+    /// Serve the `serve` JSON-RPC methods to any number of clients over a
+    /// Unix socket
+    Daemon {
+        /// Socket path; created owner-only, replacing a stale socket
+        #[arg(long)]
+        socket: String,
+        /// Serve Prometheus metrics at `http://ADDRESS/metrics`, e.g.
+        /// `127.0.0.1:9464`
+        #[arg(long, value_name = "ADDRESS")]
+        metrics: Option<String>,
+        /// Adopt and reap orphaned descendants of spawned processes
+        /// (Linux `PR_SET_CHILD_SUBREAPER`)
+        #[arg(long)]
+        subreaper: bool,
+        /// Spawn at most N processes a minute, in bursts of up to N
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_spawns_per_minute: Option<u32>,
+        /// What to do with a spawn over the limit
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = rate_limit::OnLimit::Reject,
+            requires = "max_spawns_per_minute"
+        )]
+        on_spawn_limit: rate_limit::OnLimit,
+    },
+    /// Serve `spawn`, `kill`, `status`, `list`, and `logs` as Model Context
+    /// Protocol tools over stdio
+    Mcp,
+    /// Defaults from `~/.config/lillux/config.toml` and `.lillux.toml`
+    Config {
+        #[command(subcommand)]
+        action: config::ConfigAction,
+    },
+    /// Run the `serve` methods for newline-delimited commands on stdin
+    /// (`{"id", "method", "params"}`), one result line each, in order
+    Batch {
+        /// Stop at the first command that fails
+        #[arg(long)]
+        stop_on_error: bool,
+    },
+    /// Print a versioned description of every subcommand, its arguments,
+    /// and its output
+    Describe {
+        #[arg(long, value_enum, default_value_t = describe::DescribeFormat::JsonSchema)]
+        format: describe::DescribeFormat,
+    },
+    /// Spawn commands on a cron schedule (UTC) from `exec daemon`
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Query or verify the append-only log of spawns, kills, and other
+    /// operations that change what is running
+    Audit {
+        #[command(subcommand)]
+        action: audit::AuditAction,
+    },
+    /// Named advisory locks (`flock`/`LockFileEx`) shared by every session
+    Lock {
+        #[command(subcommand)]
+        action: lock::LockAction,
+    },
+    /// Allocate free ports for servers about to be spawned
+    Port {
+        #[command(subcommand)]
+        action: port_alloc::PortAction,
+    },
+    /// Snapshot the environment a process was or will be given, and diff
+    /// snapshots
+    Env {
+        #[command(subcommand)]
+        action: environ::EnvAction,
+    },
+    /// Render a registered process as a service definition
+    Export {
+        #[command(subcommand)]
+        target: ExportTarget,
+    },
+    /// Write to the stdin FIFO of a process spawned with `--stdin-fifo`
+    Send {
+        #[arg(long)]
+        name: String,
+        #[arg(long, required_unless_present = "data_stdin")]
+        data: Option<String>,
+        /// Read the data from stdin instead of `--data`
+        #[arg(long, conflicts_with = "data")]
+        data_stdin: bool,
+        /// Append a newline to the data
+        #[arg(long)]
+        newline: bool,
+    },
+    /// List processes recorded by `spawn`
+    List {
+        /// Only entries carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only entries whose name starts with this prefix
+        #[arg(long)]
+        name_prefix: Option<String>,
+        #[arg(long, value_enum, default_value_t = ListFormat::Json)]
+        format: ListFormat,
+    },
+    /// Prune registry entries for dead processes
+    Gc {
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Exit 0 when a live registered process has this name, 3 (not found)
+    /// otherwise
+    Exists {
+        #[arg(long)]
+        name: String,
+    },
+    /// Names of the live registered processes, sorted; used by shell
+    /// completion
+    Names,
+    /// Run a hook once a process exits, from a detached watcher
+    OnExit {
+        #[arg(long)]
+        pid: u32,
+        /// Hook command; exit metadata arrives as `LILLUX_EXIT_*` env vars
+        #[arg(long = "exec")]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        /// File receiving the hook's stdout and stderr
+        #[arg(long)]
+        log: Option<String>,
+        /// Watch in this process instead of detaching a watcher
+        #[arg(long, hide = true)]
+        foreground: bool,
+        #[arg(long, hide = true)]
+        started_at_ms: Option<u64>,
+    },
+    /// Print the log of a process spawned with `--name` and `--log`
+    Logs {
+        #[arg(long)]
+        name: String,
+        /// Keep printing new lines until the process exits; follows rotation
+        #[arg(long)]
+        follow: bool,
+        /// Lines of history to print first
+        #[arg(long, default_value_t = 10)]
+        lines: usize,
+        /// Print the whole history written within this long, e.g. `2h`,
+        /// including a rotated (and possibly compressed) archive
+        #[arg(long, value_name = "DURATION", conflicts_with = "lines")]
+        since: Option<String>,
+        #[arg(long, value_enum, default_value_t = logs::LogFormat::Plain)]
+        format: logs::LogFormat,
+    },
+    /// Stream the live output of a process spawned with `--name` and
+    /// `--log` until it exits
+    Attach {
+        #[arg(long)]
+        name: String,
+    },
+    /// Record CPU, memory, I/O, and thread counts of a process over time
+    Sample {
+        #[arg(long)]
+        pid: u32,
+        #[arg(long, default_value = "1s")]
+        interval: String,
+        /// Stop after this long even if the process is still running
+        #[arg(long, default_value = "60s")]
+        duration: String,
+        /// Output file; samples stream to stdout when omitted
+        #[arg(long)]
+        out: Option<String>,
+        #[arg(long, value_enum, default_value_t = sample::SampleFormat::Ndjson)]
+        format: sample::SampleFormat,
+    },
+    /// Print the descendant tree of a process as nested JSON
+    Tree {
+        #[arg(long)]
+        pid: u32,
+    },
+    /// List every process on the host in one JSON shape: pid, ppid, name,
+    /// cmdline, owner, rss, cpu, and start time
+    Ps {
+        /// Only processes whose name contains this (case-insensitive)
+        #[arg(long)]
+        filter: Option<String>,
+        /// Only processes owned by this user name or uid
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// Diagnose why a command would fail to spawn: PATH resolution,
+    /// permissions, interpreter, architecture, shared libraries, and log
+    Doctor {
+        #[arg(long)]
+        cmd: String,
+        /// Environment the spawn would get; its `PATH` is used for lookup
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        /// Log file the spawn would write
+        #[arg(long)]
+        log: Option<String>,
+    },
+    /// List TCP/UDP sockets a process is listening on
+    Ports {
+        #[arg(long)]
+        pid: u32,
+        /// Include sockets held by descendants of the process
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Kill a process by PID
+    Kill {
+        #[arg(long, required_unless_present = "name", conflicts_with = "name")]
+        pid: Option<u32>,
+        /// Kill the live registered process with this name
+        #[arg(long)]
+        name: Option<String>,
+        /// Seconds between SIGTERM and SIGKILL (default: `grace` from the
+        /// config file, else 3)
+        #[arg(long)]
+        grace: Option<f64>,
+        /// With `--name`: only the process itself, not the rest of the
+        /// process group it was spawned leading
+        #[arg(long, requires = "name")]
+        self_only: bool,
+        /// With `--pid`: the rest of the process group the registered
+        /// process was spawned leading, as `--name` does by default
+        #[arg(long, requires = "pid")]
+        group: bool,
+    },
+    /// Collect and list the crash dumps of a process spawned with
+    /// `--core-dumps`
+    Cores {
+        #[arg(long)]
+        name: String,
+    },
+    /// Send a registered process its reload signal and report whether it
+    /// survived
+    Reload {
+        #[arg(long)]
+        name: String,
+        /// Signal to send instead of the one recorded at spawn
+        #[arg(long)]
+        signal: Option<String>,
+        /// Archive the log to `<log>.1` and truncate it before signalling
+        #[arg(long)]
+        rotate_log: bool,
+        /// Compress the archive in the background, to `<log>.1.gz` or
+        /// `<log>.1.zst`
+        #[arg(long, value_enum, requires = "rotate_log")]
+        log_compress: Option<logs::LogCompression>,
+        /// How long the process must stay up after the signal to count as
+        /// having survived
+        #[arg(long, default_value = "1s")]
+        settle: String,
+    },
+    /// Copy stdin into a capped log; what `spawn --log-max-bytes` runs
+    /// detached
+    #[command(hide = true)]
+    LogWriter {
+        #[arg(long)]
+        path: String,
+        #[arg(long)]
+        max_bytes: u64,
+        #[arg(long, value_enum)]
+        overflow: log_cap::Overflow,
+    },
+    /// Compress a rotated log; what `reload --log-compress` runs detached
+    #[command(hide = true)]
+    CompressLog {
+        #[arg(long)]
+        path: String,
+        #[arg(long, value_enum)]
+        codec: logs::LogCompression,
+    },
+    /// Stream a command's output with raw passthrough (no JSON wrapping)
+    Stream {
+        #[arg(long)]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long)]
+        cwd: Option<String>,
+        #[arg(long)]
+        stdin: Option<String>,
+        #[arg(long)]
+        stdin_pipe: bool,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        #[arg(long, default_value_t = 300.0)]
+        timeout: f64,
+    },
+    /// Run a command in the foreground in lillux's place: SIGINT, SIGTERM,
+    /// and SIGHUP are passed on and its exit status becomes lillux's (a
+    /// container ENTRYPOINT shim)
+    Proxy {
+        #[arg(long)]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long)]
+        cwd: Option<String>,
+        /// `KEY=VALUE` added to the inherited environment; repeatable
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        /// Also copy the child's stdout and stderr into this file
+        #[arg(long)]
+        log: Option<String>,
+        /// Most file descriptors the child may hold open
+        #[arg(long)]
+        max_open_files: Option<u64>,
+        /// Seconds before the child is sent SIGTERM, then SIGKILL, and
+        /// lillux exits 124; 0 waits forever
+        #[arg(long, default_value_t = 0.0)]
+        timeout: f64,
+    },
+    /// Report liveness, resource usage, and identity of one or more processes
+    Status {
+        /// PID to inspect; repeat to inspect several in one call
+        #[arg(long = "pid")]
+        pids: Vec<u32>,
+        /// Read a JSON array of PIDs from stdin (merged with any `--pid`)
+        #[arg(long)]
+        pids_stdin: bool,
+        /// Registered process name to inspect; repeatable
+        #[arg(long = "name")]
+        names: Vec<String>,
+        /// Report open file descriptor counts and the soft limit
+        #[arg(long)]
+        fds: bool,
+        /// With `--fds`, also list every open descriptor and its target
+        #[arg(long, requires = "fds")]
+        detail: bool,
+        /// Leave out the rest of a registered process's process group
+        #[arg(long)]
+        self_only: bool,
+        /// Emit an NDJSON sample every `--interval` until the process exits
+        #[arg(long)]
+        follow: bool,
+        /// Sampling interval for `--follow` (e.g. `500ms`, `2s`)
+        #[arg(long, default_value = "2s", requires = "follow")]
+        interval: String,
+    },
+}
+
+impl ExecAction {
+    /// Whether the action opens the registry, and so needs to know which
+    /// one the config files name.
+    fn uses_registry(&self) -> bool {
+        !matches!(
+            self,
+            Self::Run { .. }
+                | Self::Stream { .. }
+                | Self::Proxy { .. }
+                | Self::Config { .. }
+                | Self::Describe { .. }
+                | Self::OnExit { .. }
+                | Self::Sample { .. }
+                | Self::Tree { .. }
+                | Self::Ps { .. }
+                | Self::Doctor { .. }
+                | Self::Ports { .. }
+                | Self::LogWriter { .. }
+                | Self::CompressLog { .. }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+pub enum ScheduleAction {
+    /// Register a command to spawn whenever the expression matches
+    Add {
+        /// Schedule name; every run is registered under it
+        #[arg(long)]
+        name: String,
+        /// Five fields (minute hour day month weekday) or `@hourly`,
+        /// `@daily`, `@weekly`, `@monthly`, `@yearly`
+        #[arg(long)]
+        cron: String,
+        /// What to do when the previous run is still live at a firing
+        #[arg(long, value_enum, default_value_t = schedule::Overlap::Skip)]
+        overlap: schedule::Overlap,
+        #[arg(long)]
+        cmd: String,
+        #[arg(long = "arg", allow_hyphen_values = true)]
+        args: Vec<String>,
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        #[arg(long)]
+        log: Option<String>,
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Show every schedule with its next firing and last run
+    List,
+    /// Delete a schedule; a run already started keeps running
+    Remove {
+        #[arg(long)]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ExportTarget {
+    /// A systemd unit running the same command, environment, and log
+    Systemd {
+        #[arg(long)]
+        name: String,
+    },
+    /// A launchd property list for a LaunchAgent or LaunchDaemon
+    Launchd {
+        #[arg(long)]
+        name: String,
+        /// Job label (default: `lillux.<name>`)
+        #[arg(long)]
+        label: Option<String>,
+        /// Have launchd restart the program whenever it exits
+        #[arg(long)]
+        keep_alive: bool,
+    },
+}
+
+/// Options accepted by every exec subcommand.
+#[derive(clap::Args)]
+pub struct ExecOptions {
+    /// SQLite registry of spawned processes (default: per-user state dir)
+    #[arg(long, global = true)]
+    pub registry: Option<String>,
+    /// Run the command on this host over ssh (`user@box`) and relay its
+    /// output and exit status
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+}
+
+/// The `exec` command tree as clap sees it, for introspection.
+pub fn command() -> clap::Command {
+    use clap::Args;
+    ExecOptions::augment_args(ExecAction::augment_subcommands(clap::Command::new("exec")))
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListFormat {
+    Json,
+    Table,
+}
+
+fn resolve_stdin(stdin_arg: Option<String>, stdin_pipe: bool) -> Option<String> {
+    if let Some(data) = stdin_arg {
+        return Some(data);
+    }
+    if stdin_pipe {
+        let mut buf = String::new();
+        let _ = std::io::stdin().read_to_string(&mut buf);
+        if !buf.is_empty() {
+            return Some(buf);
+        }
+    }
+    None
+}
+
+/// Dispatch an exec action. `registry` is the `--registry` database path;
+/// `None` falls back to [`Registry::locate`]'s defaults.
+pub fn run(action: ExecAction, registry: Option<&str>) -> serde_json::Value {
+    let _traces = otel::init();
+    let config = config::Lazy::default();
+    let flag = registry;
+    let registry = match action.uses_registry() {
+        true => match config.registry(flag) {
+            Ok(registry) => registry,
+            Err(e) => return e.failed(),
+        },
+        false => None,
+    };
+    let registry = registry.as_deref();
+    match action {
+        ExecAction::Run {
+            cmd,
+            args,
+            cwd,
+            stdin,
+            stdin_pipe,
+            envs,
+            timeout,
+        } => do_exec(
+            &cmd,
+            &args,
+            cwd.as_deref(),
+            resolve_stdin(stdin, stdin_pipe).as_deref(),
+            &envs,
+            timeout,
+        ),
+        ExecAction::Spawn {
+            cmd,
+            args,
+            log,
+            envs,
+            stdin,
+            stdin_pipe,
+            stdin_fifo,
+            name,
+            tags,
+            sd_notify,
+            backend,
+            image,
+            mounts,
+            ports,
+            claim_ports,
+            reload_signal,
+            core_dumps,
+            sandbox,
+            spawn_retries,
+            spawn_backoff,
+            log_max_bytes,
+            log_overflow,
+            health,
+        } => {
+            let spawn_retry = match crate::time::parse_duration(&spawn_backoff) {
+                Ok(backoff) => retry::SpawnRetry {
+                    retries: spawn_retries,
+                    backoff,
+                },
+                Err(e) => return serde_json::json!({ "success": false, "error": e }),
+            };
+            if sandbox == sandbox::Preset::Strict && core_dumps.is_some() {
+                return serde_json::json!({
+                    "success": false,
+                    "error": "--core-dumps has no effect under --sandbox strict, which disables core dumps",
+                });
+            }
+            // The workspace is the working directory, which only a sandbox
+            // needs: a spawn from a deleted directory still works without.
+            let sandbox = match sandbox {
+                sandbox::Preset::None => None,
+                preset => match std::env::current_dir()
+                    .map_err(|e| format!("Failed to read the working directory: {e}"))
+                    .and_then(|cwd| sandbox::sandbox(preset, &cwd))
+                {
+                    Ok(sandbox) => sandbox,
+                    Err(e) => return serde_json::json!({ "success": false, "error": e }),
+                },
+            };
+            let reload_signal = match reload_signal.as_deref().map(reload::normalize_signal) {
+                Some(Ok(signal)) => Some(signal),
+                Some(Err(e)) => return serde_json::json!({ "success": false, "error": e }),
+                None => None,
+            };
+            let core_dumps = match core_dumps.as_deref().map(cores::prepare) {
+                Some(Ok(cores)) => Some(cores),
+                Some(Err(e)) => return serde_json::json!({ "success": false, "error": e }),
+                None => None,
+            };
+            let log = match config.log(log, name.as_deref(), &cmd) {
+                Ok(log) => log,
+                Err(e) => return e.failed(),
+            };
+            if log_max_bytes.is_some() && log.is_none() {
+                return SysError::new(ErrorCode::InvalidArgument, "--log-max-bytes needs a --log")
+                    .failed();
+            }
+            let mut envs = envs;
+            if let (true, Some(name)) = (claim_ports, &name) {
+                match port_alloc::claim(registry, name) {
+                    Ok(claimed) => envs.extend(port_alloc::envs(&claimed)),
+                    Err(e) => return e.failed(),
+                }
+            }
+            spawn_registered(
+                registry,
+                RegistryEntry {
+                    name,
+                    tags,
+                    cmd,
+                    args,
+                    log,
+                    stdin_fifo,
+                    envs,
+                    sd_notify,
+                    container: backend.map(|runtime| container::Container {
+                        runtime,
+                        id: String::new(),
+                        image: image.unwrap_or_default(),
+                        mounts,
+                        ports,
+                    }),
+                    health: match (*health).into_check() {
+                        Ok(health) => health,
+                        Err(e) => return serde_json::json!({ "success": false, "error": e }),
+                    },
+                    reload_signal,
+                    core_dumps,
+                    sandbox,
+                    log_cap: log_max_bytes.map(|max_bytes| log_cap::LogCap {
+                        max_bytes,
+                        overflow: log_overflow,
+                    }),
+                    ..Default::default()
+                },
+                resolve_stdin(stdin, stdin_pipe).as_deref(),
+                &spawn_retry,
+            )
+        }
+        ExecAction::Pipeline {
+            stages,
+            log,
+            envs,
+            name,
+            tags,
+            multiplex,
+            foreground,
+        } => {
+            let audited = serde_json::json!({
+                "stages": stages,
+                "name": name,
+                "tags": tags,
+                "log": log,
+                "multiplex": multiplex,
+                "env": audit::env_names(&envs),
+            });
+            let mut result = match stages
+                .iter()
+                .map(|raw| pipeline::parse_stage(raw))
+                .collect()
+            {
+                Ok(stages) => run_pipeline(
+                    registry,
+                    pipeline::Pipeline {
+                        stages,
+                        name,
+                        tags,
+                        log,
+                        envs,
+                        multiplex,
+                    },
+                    foreground,
+                ),
+                Err(e) => SysError::new(ErrorCode::InvalidArgument, e).failed(),
+            };
+            audit::record(registry, "pipeline", audited, &mut result);
+            result
+        }
+        ExecAction::Serve {
+            stdio: _,
+            max_spawns_per_minute,
+            on_spawn_limit,
+        } => {
+            serve::serve_stdio(
+                registry.map(str::to_string),
+                max_spawns_per_minute.map(|n| rate_limit::SpawnLimit::new(n, on_spawn_limit)),
+            );
+            process::exit(output::EXIT_OK);
+        }
+        ExecAction::Daemon {
+            socket,
+            metrics,
+            subreaper,
+            max_spawns_per_minute,
+            on_spawn_limit,
+        } => {
+            match serve::serve_socket(
+                registry.map(str::to_string),
+                &socket,
+                metrics.as_deref(),
+                subreaper,
+                max_spawns_per_minute.map(|n| rate_limit::SpawnLimit::new(n, on_spawn_limit)),
+            ) {
+                Ok(()) => process::exit(output::EXIT_OK),
+                Err(e) => e.failed(),
+            }
+        }
+        ExecAction::Mcp => {
+            mcp::serve_stdio(registry.map(str::to_string));
+            process::exit(output::EXIT_OK);
+        }
+        ExecAction::Config {
+            action: config::ConfigAction::Show,
+        } => match config.get() {
+            Ok(config) => config.show(flag),
+            Err(e) => e.failed(),
+        },
+        ExecAction::Batch { stop_on_error } => {
+            process::exit(batch::run(
+                registry.map(str::to_string),
+                std::io::stdin().lock(),
+                &mut std::io::stdout(),
+                stop_on_error,
+            ));
+        }
+        ExecAction::Describe { format } => match format {
+            describe::DescribeFormat::JsonSchema => describe::describe(&command()),
+        },
+        ExecAction::Schedule { action } => match action {
+            ScheduleAction::Add {
+                name,
+                cron,
+                overlap,
+                cmd,
+                args,
+                envs,
+                log,
+                tags,
+            } => schedule::add(
+                registry,
+                schedule::ScheduleEntry {
+                    template: RegistryEntry {
+                        name: Some(name.clone()),
+                        tags,
+                        cmd,
+                        args,
+                        log,
+                        envs,
+                        ..Default::default()
+                    },
+                    name,
+                    cron,
+                    overlap,
+                    created_at: crate::time::iso8601_now(),
+                    last_fire_at: None,
+                    last_result: None,
+                    pending: false,
+                },
+            ),
+            ScheduleAction::List => schedule::list(registry),
+            ScheduleAction::Remove { name } => schedule::remove(registry, &name),
+        },
+        ExecAction::Audit { action } => audit::run(registry, action),
+        ExecAction::Lock { action } => lock::run(registry, action),
+        ExecAction::Port { action } => port_alloc::run(registry, action),
+        ExecAction::Env { action } => environ::run(registry, action),
+        ExecAction::Export { target } => {
+            let name = match &target {
+                ExportTarget::Systemd { name } | ExportTarget::Launchd { name, .. } => name,
+            };
+            match named_entry(registry, name) {
+                Ok(entry) => {
+                    let entry = exported_command(entry);
+                    match &target {
+                        ExportTarget::Systemd { .. } => print!("{}", systemd::render_unit(&entry)),
+                        ExportTarget::Launchd {
+                            label, keep_alive, ..
+                        } => {
+                            let label = label.clone().unwrap_or_else(|| format!("lillux.{name}"));
+                            print!("{}", launchd::render_plist(&entry, &label, *keep_alive));
+                        }
+                    }
+                    process::exit(output::EXIT_OK);
+                }
+                Err(e) => e.failed_with(serde_json::json!({ "name": name })),
+            }
+        }
+        ExecAction::Send {
+            name,
+            data,
+            data_stdin: _,
+            newline,
+        } => {
+            // The data itself is not logged; the outcome records its size.
+            let audited = serde_json::json!({ "name": name, "newline": newline });
+            let mut result = send_to_stdin(registry, &name, data, newline);
+            audit::record(registry, "send", audited, &mut result);
+            result
+        }
+        ExecAction::List {
+            tag,
+            name_prefix,
+            format,
+        } => list_registered(registry, tag.as_deref(), name_prefix.as_deref(), format),
+        ExecAction::Stream {
+            cmd,
+            args,
+            cwd,
+            stdin,
+            stdin_pipe,
+            envs,
+            timeout,
+        } => {
+            let code = do_stream(
+                &cmd,
+                &args,
+                cwd.as_deref(),
+                resolve_stdin(stdin, stdin_pipe).as_deref(),
+                &envs,
+                timeout,
+            );
+            process::exit(code);
+        }
+        ExecAction::Proxy {
+            cmd,
+            args,
+            cwd,
+            envs,
+            log,
+            max_open_files,
+            timeout,
+        } => {
+            let proxy = proxy::Proxy {
+                cmd,
+                args,
+                cwd,
+                envs,
+                log,
+                max_open_files,
+                timeout: request_timeout_duration(timeout),
+            };
+            match proxy::run(&proxy) {
+                Ok(code) => process::exit(code),
+                Err(e) => serde_json::json!({ "success": false, "cmd": proxy.cmd, "error": e }),
+            }
+        }
+        ExecAction::Gc { dry_run } => {
+            match Registry::locate(registry).and_then(|registry| registry.gc(dry_run)) {
+                Ok(report) => {
+                    let mut result = serde_json::to_value(report).unwrap_or_default();
+                    result["dry_run"] = dry_run.into();
+                    if !dry_run {
+                        audit::record(registry, "gc", serde_json::json!({}), &mut result);
+                    }
+                    result
+                }
+                Err(e) => serde_json::json!({ "error": e }),
+            }
+        }
+        ExecAction::Names => match Registry::locate(registry).and_then(|r| r.entries()) {
+            Ok(entries) => {
+                let names: std::collections::BTreeSet<String> = entries
+                    .into_iter()
+                    .filter(RegistryEntry::is_live)
+                    .filter_map(|entry| entry.name)
+                    .collect();
+                serde_json::json!(names)
+            }
+            Err(e) => serde_json::json!({ "error": e }),
+        },
+        ExecAction::Exists { name } => {
+            let found = match Registry::locate(registry).and_then(|r| r.find_live(&name)) {
+                Ok(found) => found,
+                Err(e) => return serde_json::json!({ "name": name, "error": e }),
+            };
+            match found {
+                Some(entry) => {
+                    serde_json::json!({ "name": name, "exists": true, "pid": entry.pid })
+                }
+                None => {
+                    output::emit(serde_json::json!({ "name": name, "exists": false }));
+                    process::exit(output::EXIT_NOT_FOUND);
+                }
+            }
+        }
+        ExecAction::OnExit {
+            pid,
+            cmd,
+            args,
+            envs,
+            log,
+            foreground,
+            started_at_ms,
+        } => {
+            let mut hook = on_exit::ExitHook {
+                pid,
+                started_at_ms,
+                cmd,
+                args,
+                envs,
+                log,
+            };
+            if foreground {
+                process::exit(on_exit::watch_and_run(&hook));
+            }
+            let Some(info) = procinfo::inspect(pid) else {
+                return no_such_process(pid);
+            };
+            hook.started_at_ms = info.start_time_ms;
+            match on_exit::arm(&hook) {
+                Ok(watcher) => {
+                    serde_json::json!({ "success": true, "pid": pid, "watcher_pid": watcher })
+                }
+                Err(e) => serde_json::json!({ "success": false, "pid": pid, "error": e }),
+            }
+        }
+        ExecAction::Logs {
+            name,
+            follow,
+            lines,
+            since,
+            format,
+        } => show_logs(registry, &name, follow, lines, since.as_deref(), format),
+        ExecAction::Attach { name } => attach(registry, &name),
+        ExecAction::Sample {
+            pid,
+            interval,
+            duration,
+            out,
+            format,
+        } => sample_process(pid, &interval, &duration, out, format),
+        ExecAction::Tree { pid } => match procinfo::descendant_tree(pid) {
+            Ok(Some(tree)) => tree,
+            Ok(None) => no_such_process(pid),
+            Err(e) => serde_json::json!({ "pid": pid, "error": e }),
+        },
+        ExecAction::Ps { filter, user } => ps::run(filter.as_deref(), user.as_deref()),
+        ExecAction::Doctor { cmd, envs, log } => doctor::run(&cmd, &envs, log.as_deref()),
+        ExecAction::Ports { pid, tree } => listening_ports(pid, tree),
+        ExecAction::Kill {
+            pid,
+            name,
+            grace,
+            self_only,
+            group,
+        } => match config.grace(grace) {
+            Ok(grace) => {
+                let group = kill_covers_group(name.as_deref(), self_only, group);
+                kill_registered(registry, pid, name, grace, group)
+            }
+            Err(e) => e.failed(),
+        },
+        ExecAction::Cores { name } => cores::run(registry, &name),
+        ExecAction::Reload {
+            name,
+            signal,
+            rotate_log,
+            log_compress,
+            settle,
+        } => {
+            let audited = serde_json::json!({
+                "name": name,
+                "signal": signal,
+                "rotate_log": rotate_log,
+                "log_compress": log_compress.map(logs::LogCompression::as_str),
+            });
+            let mut result = reload::run(
+                registry,
+                &name,
+                signal.as_deref(),
+                rotate_log,
+                log_compress,
+                &settle,
+            );
+            audit::record(registry, "reload", audited, &mut result);
+            result
+        }
+        ExecAction::LogWriter {
+            path,
+            max_bytes,
+            overflow,
+        } => {
+            let cap = log_cap::LogCap {
+                max_bytes,
+                overflow,
+            };
+            match log_cap::write(std::path::Path::new(&path), cap, std::io::stdin().lock()) {
+                Ok(()) => process::exit(output::EXIT_OK),
+                Err(e) => serde_json::json!({ "success": false, "path": path, "error": e }),
+            }
+        }
+        ExecAction::CompressLog { path, codec } => {
+            match logs::compress(std::path::Path::new(&path), codec) {
+                Ok(archive) => serde_json::json!({
+                    "success": true,
+                    "path": path,
+                    "archive": archive.display().to_string(),
+                }),
+                Err(e) => serde_json::json!({ "success": false, "path": path, "error": e }),
+            }
+        }
+        ExecAction::Status {
+            pids,
+            pids_stdin,
+            names,
+            fds,
+            detail,
+            self_only,
+            follow,
+            interval,
+        } => otel::in_span(
+            tracing::info_span!("exec.status", error = tracing::field::Empty),
+            || {
+                let extra = StatusDetail { fds, detail };
+                let single = pids.len() + names.len() == 1 && !pids_stdin;
+                let mut pids = pids;
+                let mut missing = Vec::new();
+                let mut registered = HashMap::new();
+                if !names.is_empty() {
+                    let registry = match Registry::locate(registry) {
+                        Ok(registry) => registry,
+                        Err(e) => return serde_json::json!({ "error": e }),
+                    };
+                    for name in names {
+                        match registry.find_live(&name) {
+                            Ok(Some(entry)) => {
+                                pids.push(entry.pid);
+                                registered.insert(entry.pid, entry);
+                            }
+                            Ok(None) => {
+                                let mut status =
+                                    serde_json::json!({ "name": name, "alive": false });
+                                // The leader is gone; what it started may not be.
+                                if let Some(pgid) = registry
+                                    .find_latest(&name)
+                                    .ok()
+                                    .flatten()
+                                    .filter(|_| !self_only)
+                                    .and_then(|entry| leftover_group(&entry))
+                                {
+                                    status["group"] = group_status(pgid);
+                                }
+                                missing.push(status)
+                            }
+                            Err(e) => return serde_json::json!({ "error": e }),
+                        }
+                    }
+                }
+                if pids.is_empty() && !missing.is_empty() {
+                    return match single {
+                        true => missing.remove(0),
+                        false => serde_json::Value::Array(missing),
+                    };
+                }
+                let pids = match collect_pids(pids, pids_stdin) {
+                    Ok(pids) => pids,
+                    Err(e) => return e.failed(),
+                };
+                if !follow {
+                    let mut statuses = status_many(pids, !single, extra);
+                    match &mut statuses {
+                        serde_json::Value::Array(statuses) => {
+                            for status in statuses {
+                                add_registry_state(status, &registered, self_only);
+                            }
+                        }
+                        status => add_registry_state(status, &registered, self_only),
+                    }
+                    return match statuses {
+                        serde_json::Value::Array(mut statuses) => {
+                            statuses.extend(missing);
+                            serde_json::Value::Array(statuses)
+                        }
+                        status => status,
+                    };
+                }
+                match crate::time::parse_duration(&interval) {
+                    Ok(interval) => follow_status(pids, interval, extra),
+                    Err(e) => serde_json::json!({ "error": e }),
+                }
+            },
+        ),
+    }
+}
+
+/// A process [`spawn_process`] started, with what `spawn` reports about it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpawnedProcess {
+    pub pid: u32,
+    pub name: Option<String>,
+    /// Failed attempts before the one that started it.
+    pub attempts: Vec<retry::Attempt>,
+    pub log_writer_pid: Option<u32>,
+    pub container: Option<String>,
+    pub core_dumps_warning: Option<String>,
+    pub sandbox: Option<Vec<String>>,
+    /// Why the running process could not be recorded in the registry.
+    pub registry_error: Option<String>,
+}
+
+/// Why [`spawn_process`] started nothing, with every attempt it made.
+#[derive(Debug, Clone)]
+pub(crate) struct SpawnFailure {
+    pub error: SysError,
+    pub attempts: Vec<retry::Attempt>,
+}
+
+impl From<SysError> for SpawnFailure {
+    fn from(error: SysError) -> Self {
+        Self {
+            error,
+            attempts: Vec::new(),
+        }
+    }
+}
+
+/// `spawn`'s JSON result for `outcome`.
+fn spawn_json(outcome: &Result<SpawnedProcess, SpawnFailure>) -> serde_json::Value {
+    let spawned = match outcome {
+        Ok(spawned) => spawned,
+        Err(failure) => {
+            let mut result = failure.error.failed();
+            if failure.attempts.len() > 1 {
+                result["attempts"] = serde_json::json!(failure.attempts);
+            }
+            return result;
+        }
+    };
+    let mut result = serde_json::json!({ "success": true, "pid": spawned.pid });
+    if let Some(name) = &spawned.name {
+        result["name"] = name.clone().into();
+    }
+    if !spawned.attempts.is_empty() {
+        result["attempts"] = serde_json::json!(spawned.attempts);
+    }
+    if let Some(writer) = spawned.log_writer_pid {
+        result["log_writer_pid"] = writer.into();
+    }
+    if let Some(container) = &spawned.container {
+        result["container"] = container.clone().into();
+    }
+    if let Some(warning) = &spawned.core_dumps_warning {
+        result["core_dumps_warning"] = warning.clone().into();
+    }
+    if let Some(layers) = &spawned.sandbox {
+        result["sandbox"] = layers.clone().into();
+    }
+    if let Some(error) = &spawned.registry_error {
+        result["registry_error"] = error.clone().into();
+    }
+    result
+}
+
+/// [`spawn_process`] in an `exec.spawn` span, recorded in the audit log.
+/// Returns the outcome and the JSON result that was recorded.
+pub(crate) fn spawn_recorded(
+    registry: Option<&str>,
+    entry: RegistryEntry,
+    stdin_data: Option<&str>,
+    spawn_retry: &retry::SpawnRetry,
+) -> (Result<SpawnedProcess, SpawnFailure>, serde_json::Value) {
+    let span = tracing::info_span!(
+        "exec.spawn",
+        cmd = %entry.cmd,
+        name = entry.name.as_deref(),
+        pid = tracing::field::Empty,
+        error = tracing::field::Empty,
+    );
+    let audited = audit::entry_args(&entry);
+    let outcome = span.in_scope(|| spawn_process(registry, entry, stdin_data, spawn_retry));
+    let mut result = spawn_json(&outcome);
+    otel::record(&span, &result);
+    audit::record(registry, "spawn", audited, &mut result);
+    (outcome, result)
+}
+
+/// `spawn` as JSON; see [`spawn_process`].
+pub(super) fn spawn_registered(
+    registry: Option<&str>,
+    entry: RegistryEntry,
+    stdin_data: Option<&str>,
+    spawn_retry: &retry::SpawnRetry,
+) -> serde_json::Value {
+    spawn_recorded(registry, entry, stdin_data, spawn_retry).1
+}
+
+/// Spawn `entry.cmd` detached and record it; `entry` supplies the command,
+/// environment, and registry metadata, the PID and start time are filled
+/// in here.
+fn spawn_process(
+    registry: Option<&str>,
+    mut entry: RegistryEntry,
+    stdin_data: Option<&str>,
+    spawn_retry: &retry::SpawnRetry,
+) -> Result<SpawnedProcess, SpawnFailure> {
+    let registry = Registry::locate(registry);
+    if let (Some(name), Ok(registry)) = (&entry.name, &registry) {
+        if let Some(existing) = registry.find_live(name).map_err(SysError::from)? {
+            return Err(name_taken(name, existing.pid).into());
+        }
+    }
+    let fifo = match &entry.stdin_fifo {
+        Some(path) => Some(fifo::create(path).map_err(SysError::from)?),
+        None => None,
+    };
+    let mut envs = entry.envs.clone();
+    if entry.sd_notify {
+        // Not recorded: the socket belongs to this service manager session.
+        if let Some(socket) = std::env::var_os("NOTIFY_SOCKET") {
+            envs.push(format!("NOTIFY_SOCKET={}", socket.to_string_lossy()));
+        }
+    }
+    // The environment is replaced wholesale, so trace context is passed
+    // on explicitly.
+    if let Some(traceparent) = otel::traceparent() {
+        envs.retain(|env| !env.starts_with("TRACEPARENT="));
+        envs.push(format!("TRACEPARENT={traceparent}"));
+    }
+    let capped = match (entry.log_cap, entry.log.as_deref(), &entry.container) {
+        (Some(cap), Some(log), None) => Some(log_cap::start_writer(log, cap)?),
+        _ => None,
+    };
+    let (spawned, attempts) = match &mut entry.container {
+        Some(container) => (
+            container
+                .start(entry.name.as_deref(), &envs, &entry.cmd, &entry.args)
+                .map_err(SysError::from),
+            Vec::new(),
+        ),
+        None => retry::run(spawn_retry, || {
+            let stdin = match (&fifo, stdin_data) {
+                (Some(file), _) => DetachedStdin::File(
+                    file.try_clone()
+                        .map_err(|e| SysError::io("Failed to reopen stdin fifo", "dup", &e))?,
+                ),
+                (None, Some(data)) => DetachedStdin::Data(data),
+                (None, None) => DetachedStdin::Null,
+            };
+            let log = match &capped {
+                Some((pipe, _)) => DetachedLog::Pipe(
+                    pipe.try_clone()
+                        .map_err(|e| SysError::io("Failed to clone log pipe", "dup", &e))?,
+                ),
+                None => DetachedLog::file(entry.log.as_deref()),
+            };
+            spawn_detached(
+                &entry.cmd,
+                &entry.args,
+                log,
+                &envs,
+                stdin,
+                entry.core_dumps.is_some(),
+                entry.sandbox.as_ref(),
+            )
+        }),
+    };
+    let pid = match spawned {
+        Ok(pid) => pid,
+        Err(error) => return Err(SpawnFailure { error, attempts }),
+    };
+    entry.pid = pid;
+    entry.started_at_ms = procinfo::inspect(pid).and_then(|info| info.start_time_ms);
+    if entry.container.is_none() {
+        entry.pgid = process_group_of(pid);
+    }
+    entry.spawned_at = crate::time::iso8601_now();
+    // The child is already running; a registry failure is reported but
+    // does not turn a successful spawn into an error. Losing the name to a
+    // spawn that got past the check above at the same time does.
+    let registry_error = match (&entry.name, registry) {
+        (Some(name), Ok(registry)) => match registry.record_named(&entry) {
+            Ok(Ok(_)) => None,
+            Ok(Err(holder)) => {
+                abandon(&entry);
+                return Err(name_taken(name, holder.pid).into());
+            }
+            Err(e) => Some(e),
+        },
+        (_, registry) => registry.and_then(|registry| registry.record(&entry)).err(),
+    };
+    Ok(SpawnedProcess {
+        pid,
+        name: entry.name,
+        attempts,
+        log_writer_pid: capped.map(|(_, writer)| writer),
+        container: entry.container.map(|container| container.id),
+        core_dumps_warning: entry.core_dumps.and_then(|_| cores::warning()),
+        sandbox: entry.sandbox.map(|sandbox| sandbox.layers),
+        registry_error,
+    })
+}
+
+/// Stop a child [`spawn_process`] started but could not register.
+fn abandon(entry: &RegistryEntry) {
+    if let Some(container) = &entry.container {
+        let _ = container.stop(0.0);
+        return;
+    }
+    #[cfg(unix)]
+    let _ = match entry.pgid {
+        Some(pgid) => terminate(-(pgid as i32), 0.0),
+        None => terminate(entry.pid as i32, 0.0),
+    };
+    #[cfg(not(unix))]
+    let _ = kill_process(entry.pid, 0.0);
+}
+
+fn run_pipeline(
+    registry: Option<&str>,
+    pipeline: pipeline::Pipeline,
+    foreground: bool,
+) -> serde_json::Value {
+    let registry = match Registry::locate(registry) {
+        Ok(registry) => registry,
+        Err(e) => return serde_json::json!({ "success": false, "error": e }),
+    };
+    if foreground {
+        process::exit(pipeline::supervise(&pipeline, &registry));
+    }
+    if let Some(name) = &pipeline.name {
+        match registry.find_live(name) {
+            Ok(Some(existing)) => return name_taken(name, existing.pid).failed(),
+            Ok(None) => {}
+            Err(e) => return serde_json::json!({ "success": false, "error": e }),
+        }
+    }
+    pipeline::launch(&pipeline, &registry)
+        .unwrap_or_else(|e| serde_json::json!({ "success": false, "error": e }))
+}
+
+fn send_to_stdin(
+    registry: Option<&str>,
+    name: &str,
+    data: Option<String>,
+    newline: bool,
+) -> serde_json::Value {
+    let entry = match Registry::locate(registry).and_then(|r| r.find_live(name)) {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return no_live_process(name),
+        Err(e) => return serde_json::json!({ "success": false, "name": name, "error": e }),
+    };
+    let Some(path) = entry.stdin_fifo else {
+        return serde_json::json!({ "success": false, "name": name, "error": format!("Process '{name}' was spawned without --stdin-fifo") });
+    };
+    // Without `--data`, clap has required `--data-stdin`.
+    let mut bytes = match data {
+        Some(data) => data.into_bytes(),
+        None => {
+            let mut buf = Vec::new();
+            if let Err(e) = std::io::stdin().read_to_end(&mut buf) {
+                return serde_json::json!({ "success": false, "name": name, "error": format!("Failed to read stdin: {e}") });
+            }
+            buf
+        }
+    };
+    if newline {
+        bytes.push(b'\n');
+    }
+    match fifo::send(&path, &bytes) {
+        Ok(()) => {
+            serde_json::json!({ "success": true, "name": name, "pid": entry.pid, "bytes": bytes.len() })
+        }
+        Err(e) => e.failed_with(serde_json::json!({ "name": name })),
+    }
+}
+
+/// A process [`kill_process_registered`] stopped, and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Killed {
+    pub pid: u32,
+    /// `terminated`, `killed`, or `already_dead`.
+    pub method: &'static str,
+    /// The process group that went with it.
+    pub pgid: Option<u32>,
+}
+
+/// `kill`'s JSON result for `outcome`, given the `pid` or `name` asked for.
+fn kill_json(
+    outcome: &Result<Killed, SysError>,
+    pid: Option<u32>,
+    name: Option<&str>,
+) -> serde_json::Value {
+    let mut result = match outcome {
+        Ok(killed) => {
+            let mut result = serde_json::json!({
+                "success": true,
+                "pid": killed.pid,
+                "method": killed.method,
+            });
+            if let Some(pgid) = killed.pgid {
+                result["pgid"] = pgid.into();
+            }
+            result
+        }
+        Err(e) => {
+            let mut result = e.failed();
+            // A group is signalled as `-pgid`; its leader is the target.
+            if let Some(pid) = pid.or(e.pid.map(i32::unsigned_abs)) {
+                result["pid"] = pid.into();
+            }
+            result
+        }
+    };
+    if let Some(name) = name {
+        result["name"] = name.into();
+    }
+    result
+}
+
+/// [`kill_process_registered`] in an `exec.kill` span, recorded in the
+/// audit log. Returns the outcome and the JSON result that was recorded.
+pub(crate) fn kill_recorded(
+    registry: Option<&str>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    group: bool,
+) -> (Result<Killed, SysError>, serde_json::Value) {
+    let span = tracing::info_span!(
+        "exec.kill",
+        name = name.as_deref(),
+        pid = tracing::field::Empty,
+        error = tracing::field::Empty,
+    );
+    let audited = serde_json::json!({ "pid": pid, "name": name, "grace": grace });
+    let outcome =
+        span.in_scope(|| kill_process_registered(registry, pid, name.as_deref(), grace, group));
+    let mut result = kill_json(&outcome, pid, name.as_deref());
+    otel::record(&span, &result);
+    audit::record(registry, "kill", audited, &mut result);
+    (outcome, result)
+}
+
+/// `kill` as JSON; see [`kill_process_registered`].
+pub(super) fn kill_registered(
+    registry: Option<&str>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    group: bool,
+) -> serde_json::Value {
+    kill_recorded(registry, pid, name, grace, group).1
+}
+
+/// Stop what [`kill_target`] picks, with the rest of the process group it
+/// was spawned leading when `group` (see [`kill_covers_group`]), SIGTERM
+/// first and SIGKILL after `grace` seconds, and mark its registry row
+/// ended.
+fn kill_process_registered(
+    registry: Option<&str>,
+    pid: Option<u32>,
+    name: Option<&str>,
+    grace: f64,
+    group: bool,
+) -> Result<Killed, SysError> {
+    let registry = Registry::locate(registry);
+    let (entry, pid) = kill_target(&registry, pid, name, group)?;
+    // A pipeline is torn down as a whole: its supervisor leads the
+    // process group every stage runs in.
+    let pgid = entry.as_ref().and_then(|entry| covered_group(entry, group));
+    let killed = match &entry {
+        Some(RegistryEntry {
+            container: Some(container),
+            ..
+        }) => container.stop(grace).map_err(SysError::from),
+        Some(entry) if !entry.stages.is_empty() => kill_process_group(pid, grace),
+        _ => match pgid {
+            Some(pgid) => kill_process_group(pgid, grace),
+            None => kill_process(pid, grace),
+        },
+    };
+    killed_entry(&registry, entry.as_ref(), pid, pgid, killed)
+}
+
+/// What `kill` acts on: the live process registered as `name` (or, once
+/// it has exited and `group` is covered, what is left of its process
+/// group), else `pid` with its registry row when the process is ours.
+fn kill_target(
+    registry: &Result<Registry, String>,
+    pid: Option<u32>,
+    name: Option<&str>,
+    group: bool,
+) -> Result<(Option<RegistryEntry>, u32), SysError> {
+    let entry = match (name, registry) {
+        (Some(name), Ok(registry)) => match registry.find_live(name)? {
+            Some(entry) => Some(entry),
+            None => match registry.find_latest(name).ok().flatten() {
+                Some(entry) if group && leftover_group(&entry).is_some() => Some(entry),
+                _ => return Err(not_live(name)),
+            },
+        },
+        (Some(_), Err(e)) => return Err(e.clone().into()),
+        // A bare PID still updates its registry row when it is ours.
+        (None, Ok(registry)) => pid
+            .and_then(|pid| registry.find_pid(pid).ok().flatten())
+            .filter(RegistryEntry::is_live),
+        (None, Err(_)) => None,
+    };
+    match entry.as_ref().map(|entry| entry.pid).or(pid) {
+        Some(pid) => Ok((entry, pid)),
+        None => Err(SysError::new(
+            ErrorCode::InvalidArgument,
+            "--pid or --name is required",
+        )),
+    }
+}
+
+/// No live process is registered as `name`.
+fn not_live(name: &str) -> SysError {
+    SysError::new(
+        ErrorCode::NotFound,
+        format!("No live process named '{name}'"),
+    )
+}
+
+/// The failed result for a lookup of `name` that found no live process.
+fn no_live_process(name: &str) -> serde_json::Value {
+    not_live(name).failed_with(serde_json::json!({ "name": name }))
+}
+
+/// The failed result for a `pid` that is not running.
+fn no_such_process(pid: u32) -> serde_json::Value {
+    SysError::new(ErrorCode::NotFound, "No such process")
+        .failed_with(serde_json::json!({ "pid": pid }))
+}
+
+/// Spawning under a `name` that `pid` holds.
+fn name_taken(name: &str, pid: u32) -> SysError {
+    SysError::new(
+        ErrorCode::AlreadyExists,
+        format!("A live process named '{name}' is already registered (pid {pid})"),
+    )
+    .pid(pid as i32)
+}
+
+/// Record how a kill of `pid` went in the registry.
+fn killed_entry(
+    registry: &Result<Registry, String>,
+    entry: Option<&RegistryEntry>,
+    pid: u32,
+    pgid: Option<u32>,
+    killed: Result<&'static str, SysError>,
+) -> Result<Killed, SysError> {
+    let method = killed.map_err(|e| match e.pid {
+        Some(_) => e,
+        None => e.pid(pid as i32),
+    })?;
+    if let (Some(entry), Ok(registry)) = (entry, registry) {
+        let _ = registry.mark_ended(entry.id, method);
+    }
+    Ok(Killed { pid, method, pgid })
+}
+
+pub(super) fn list_registered(
+    registry: Option<&str>,
+    tag: Option<&str>,
+    name_prefix: Option<&str>,
+    format: ListFormat,
+) -> serde_json::Value {
+    let registry = match Registry::locate(registry) {
+        Ok(registry) => registry,
+        Err(e) => return serde_json::json!({ "error": e }),
+    };
+    let entries = match registry.entries() {
+        Ok(entries) => entries,
+        Err(e) => return serde_json::json!({ "error": e }),
+    };
+    let rows: Vec<serde_json::Value> = entries
+        .into_iter()
+        .filter(|entry| tag.is_none_or(|tag| entry.tags.iter().any(|t| t == tag)))
+        .filter(|entry| {
+            name_prefix.is_none_or(|prefix| {
+                entry
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| name.starts_with(prefix))
+            })
+        })
+        .map(|entry| {
+            let info = entry.live_info();
+            let mut exit_status = entry.exit_status.clone();
+            if info.is_none() && exit_status.is_none() {
+                // First observation of the exit; best effort, the listing
+                // itself does not depend on persisting it.
+                let _ = registry.mark_ended(entry.id, "exited");
+                exit_status = Some("exited".to_string());
+            }
+            let zombie = info
+                .is_none()
+                .then(|| entry.process())
+                .flatten()
+                .filter(|process| process.is_defunct());
+            let mut row = serde_json::json!({
+                "name": entry.name,
+                "pid": entry.pid,
+                "alive": info.is_some(),
+                "state": info.as_ref().or(zombie.as_ref()).and_then(|info| info.state),
+                "uptime_ms": info.and_then(|info| info.uptime_ms),
+                "tags": entry.tags,
+                "log": entry.log,
+                "cmd": entry.cmd,
+                "args": entry.args,
+                "spawned_at": entry.spawned_at,
+                "exit_status": exit_status,
+            });
+            if !entry.stages.is_empty() {
+                row["stages"] = serde_json::to_value(&entry.stages).unwrap_or_default();
+            }
+            if let Some(health) = health::describe(&entry) {
+                row["health"] = health;
+            }
+            // Exited, but its parent (a `serve` or `daemon` that spawned
+            // it, say) has not collected the exit status.
+            if let Some(zombie) = zombie {
+                row["zombie"] = true.into();
+                row["parent"] = zombie.ppid.into();
+            }
+            row
+        })
+        .collect();
+    match format {
+        ListFormat::Json => serde_json::Value::Array(rows),
+        ListFormat::Table => {
+            print!("{}", render_table(&rows));
+            process::exit(output::EXIT_OK);
+        }
+    }
+}
+
+fn render_table(rows: &[serde_json::Value]) -> String {
+    let header = ["NAME", "PID", "STATUS", "UPTIME", "TAGS", "LOG"];
+    let cells: Vec<[String; 6]> = rows
+        .iter()
+        .map(|row| {
+            let text = |key: &str| row[key].as_str().unwrap_or("-").to_string();
+            let tags: Vec<&str> = row["tags"]
+                .as_array()
+                .map(|tags| tags.iter().filter_map(|t| t.as_str()).collect())
+                .unwrap_or_default();
+            [
+                text("name"),
+                row["pid"].to_string(),
+                if row["alive"] == true {
+                    "alive"
+                } else if row["zombie"] == true {
+                    "zombie"
+                } else {
+                    "dead"
+                }
+                .to_string(),
+                row["uptime_ms"]
+                    .as_u64()
+                    .map(format_uptime)
+                    .unwrap_or_else(|| "-".to_string()),
+                if tags.is_empty() {
+                    "-".to_string()
+                } else {
+                    tags.join(",")
+                },
+                text("log"),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(&cells) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn format_uptime(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        3600..=86_399 => format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60),
+        _ => format!("{}d{:02}h", secs / 86_400, (secs % 86_400) / 3600),
+    }
+}
+
+/// How often `logs --follow` polls for new output.
+const LOG_FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The registered process `name`: the running instance, else the most
+/// recent run.
+pub(super) fn named_entry(registry: Option<&str>, name: &str) -> Result<RegistryEntry, SysError> {
+    let registry = Registry::locate(registry)?;
+    match registry.find_live(name)? {
+        Some(entry) => Ok(entry),
+        None => registry.find_latest(name)?.ok_or_else(|| {
+            SysError::new(
+                ErrorCode::NotFound,
+                format!("No process named '{name}' is registered"),
+            )
+        }),
+    }
+}
+
+/// `entry` as a command a service manager can run in the foreground: a
+/// container entry becomes the runtime's `run --rm` invocation.
+fn exported_command(entry: RegistryEntry) -> RegistryEntry {
+    let Some(container) = &entry.container else {
+        return entry;
+    };
+    RegistryEntry {
+        cmd: container.runtime.program().to_string(),
+        args: container.run_args(
+            entry.name.as_deref(),
+            &entry.envs,
+            &entry.cmd,
+            &entry.args,
+            false,
+        ),
+        envs: Vec::new(),
+        ..entry.clone()
+    }
+}
+
+/// Attach what the registry knows beyond the PID to the `status` of a
+/// process looked up by name: its health, crash dumps, and its
+/// container's state.
+fn add_registry_state(
+    status: &mut serde_json::Value,
+    registered: &HashMap<u32, RegistryEntry>,
+    self_only: bool,
+) {
+    let pid = status["pid"]
+        .as_u64()
+        .and_then(|pid| u32::try_from(pid).ok());
+    let Some(entry) = pid.and_then(|pid| registered.get(&pid)) else {
+        return;
+    };
+    if let Some(pgid) = covered_group(entry, !self_only) {
+        status["group"] = group_status(pgid);
+    }
+    if let Some(health) = health::describe(entry) {
+        status["health"] = health;
+    }
+    if let Some(cores) = &entry.core_dumps {
+        status["core_dumps"] = cores.dumps.clone().into();
+    }
+    if let Some(sandbox) = &entry.sandbox {
+        status["sandbox"] = serde_json::to_value(sandbox).unwrap_or_default();
+    }
+    if let Some(container) = &entry.container {
+        status["container"] = container
+            .state()
+            .unwrap_or_else(|e| serde_json::json!({ "id": container.id, "error": e }));
+    }
+}
+
+/// Every member of process group `pgid` with its resource usage, and
+/// the totals.
+fn group_status(pgid: u32) -> serde_json::Value {
+    let members = match procinfo::group(pgid) {
+        Ok(members) => members,
+        Err(e) => return serde_json::json!({ "pgid": pgid, "error": e }),
+    };
+    let (mut rss_bytes, mut cpu_percent) = (0, 0.0);
+    let members: Vec<serde_json::Value> = members
+        .into_iter()
+        .map(|member| {
+            let info = procinfo::inspect(member.pid);
+            let rss = info.as_ref().and_then(|info| info.rss_bytes);
+            let cpu = info.as_ref().and_then(|info| info.cpu_percent);
+            rss_bytes += rss.unwrap_or(0);
+            cpu_percent += cpu.unwrap_or(0.0);
+            serde_json::json!({
+                "pid": member.pid,
+                "ppid": member.ppid,
+                "name": member.name,
+                "state": member.state,
+                "rss_bytes": rss,
+                "cpu_percent": cpu,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "pgid": pgid,
+        "count": members.len(),
+        "rss_bytes": rss_bytes,
+        "cpu_percent": cpu_percent,
+        "members": members,
+    })
+}
+
+/// [`named_entry`], requiring that it was spawned with `--log`.
+pub(super) fn logged_entry(registry: Option<&str>, name: &str) -> Result<RegistryEntry, SysError> {
+    let entry = named_entry(registry, name)?;
+    match entry.log {
+        Some(_) => Ok(entry),
+        None => Err(format!("Process '{name}' was spawned without --log").into()),
+    }
+}
+
+fn show_logs(
+    registry: Option<&str>,
+    name: &str,
+    follow: bool,
+    lines: usize,
+    since: Option<&str>,
+    format: logs::LogFormat,
+) -> serde_json::Value {
+    let entry = match named_entry(registry, name) {
+        Ok(entry) => entry,
+        Err(e) => return e.failed_with(serde_json::json!({ "name": name })),
+    };
+    if let Some(container) = &entry.container {
+        return match container.show_logs(name, lines, since, follow, format) {
+            Ok(code) => process::exit(code),
+            Err(e) => serde_json::json!({ "name": name, "error": e }),
+        };
+    }
+    if entry.log.is_none() {
+        return serde_json::json!({ "name": name, "error": format!("Process '{name}' was spawned without --log") });
+    }
+    let path = std::path::Path::new(entry.log.as_deref().unwrap_or_default());
+    let history = match since.map(crate::time::parse_duration) {
+        Some(Ok(window)) => {
+            let cutoff = std::time::SystemTime::now()
+                .checked_sub(window)
+                .unwrap_or(std::time::UNIX_EPOCH);
+            logs::lines_since(path, cutoff)
+        }
+        Some(Err(e)) => Err(e),
+        None => logs::last_lines(path, lines),
+    };
+    let (history, offset) = match history {
+        Ok(history) => history,
+        Err(e) => return serde_json::json!({ "name": name, "error": e }),
+    };
+    let mut stdout = std::io::stdout();
+    // A multiplexed pipeline's prefixes are colored for a reader at a
+    // terminal, never in the file.
+    let labels: Vec<&str> = entry
+        .stages
+        .iter()
+        .filter_map(|stage| stage.label.as_deref())
+        .collect();
+    let prefixes = (matches!(format, logs::LogFormat::Plain)
+        && !labels.is_empty()
+        && std::io::IsTerminal::is_terminal(&stdout))
+    .then(|| logs::Prefixes::new(labels));
+    for line in &history {
+        let line = match &prefixes {
+            Some(prefixes) => prefixes.paint(line),
+            None => std::borrow::Cow::Borrowed(line.as_str()),
+        };
+        if logs::emit(&mut stdout, format, name, &line).is_err() {
+            process::exit(output::EXIT_FAILED);
+        }
+    }
+    if follow {
+        let followed = logs::Tail::open(path, offset).and_then(|mut tail| {
+            logs::follow(
+                &mut tail,
+                name,
+                format,
+                prefixes.as_ref(),
+                LOG_FOLLOW_INTERVAL,
+                || entry.is_live(),
+                &mut stdout,
+            )
+        });
+        if let Err(e) = followed {
+            return serde_json::json!({ "name": name, "error": e });
+        }
+    }
+    let _ = stdout.flush();
+    process::exit(output::EXIT_OK);
+}
+
+/// How often `attach` polls; short enough that interactive output such
+/// as prompts appears promptly.
+const ATTACH_INTERVAL: Duration = Duration::from_millis(50);
+
+fn attach(registry: Option<&str>, name: &str) -> serde_json::Value {
+    let entry = match Registry::locate(registry).and_then(|r| r.find_live(name)) {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return no_live_process(name),
+        Err(e) => return serde_json::json!({ "name": name, "error": e }),
+    };
+    let Some(path) = entry.log.as_deref().map(std::path::Path::new) else {
+        return serde_json::json!({ "name": name, "error": format!("Process '{name}' was spawned without --log") });
+    };
+    // Only output produced from now on; `logs` shows history.
+    let attached = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read log {}: {e}", path.display()))
+        .and_then(|meta| logs::Tail::open(path, meta.len()))
+        .and_then(|mut tail| {
+            logs::relay(
+                &mut tail,
+                ATTACH_INTERVAL,
+                || entry.is_live(),
+                &mut std::io::stdout(),
+            )
+        });
+    match attached {
+        Ok(()) => process::exit(output::EXIT_OK),
+        Err(e) => serde_json::json!({ "name": name, "error": e }),
+    }
+}
+
+fn sample_process(
+    pid: u32,
+    interval: &str,
+    duration: &str,
+    out: Option<String>,
+    format: sample::SampleFormat,
+) -> serde_json::Value {
+    let (interval, duration) = match (
+        crate::time::parse_duration(interval),
+        crate::time::parse_duration(duration),
+    ) {
+        (Ok(interval), Ok(duration)) => (interval, duration),
+        (Err(e), _) | (_, Err(e)) => return serde_json::json!({ "pid": pid, "error": e }),
+    };
+    if !is_alive(pid) {
+        return no_such_process(pid);
+    }
+    let Some(path) = out else {
+        let mut stdout = std::io::stdout();
+        match sample::record(pid, interval, duration, format, &mut stdout) {
+            Ok(_) => process::exit(output::EXIT_OK),
+            Err(e) => return serde_json::json!({ "pid": pid, "error": e }),
+        }
+    };
+    let mut file = match std::fs::File::create(&path) {
+        Ok(file) => std::io::BufWriter::new(file),
+        Err(e) => {
+            return serde_json::json!({ "pid": pid, "error": format!("Failed to create {path}: {e}") })
+        }
+    };
+    match sample::record(pid, interval, duration, format, &mut file) {
+        Ok(run) => serde_json::json!({
+            "pid": pid,
+            "out": path,
+            "samples": run.samples,
+            "exited": run.exited,
+        }),
+        Err(e) => serde_json::json!({ "pid": pid, "error": e }),
+    }
+}
+
+fn listening_ports(pid: u32, tree: bool) -> serde_json::Value {
+    if !is_alive(pid) {
+        return no_such_process(pid);
+    }
+    let mut pids = vec![pid];
+    if tree {
+        match procinfo::descendant_tree(pid) {
+            Ok(Some(root)) => collect_tree_pids(&root["children"], &mut pids),
+            Ok(None) => {}
+            Err(e) => return serde_json::json!({ "pid": pid, "error": e }),
+        }
+    }
+    match ports::listening(&pids) {
+        Ok(sockets) => serde_json::json!({ "pid": pid, "sockets": sockets }),
+        Err(e) => serde_json::json!({ "pid": pid, "error": e }),
+    }
+}
+
+fn collect_tree_pids(children: &serde_json::Value, pids: &mut Vec<u32>) {
+    for child in children.as_array().into_iter().flatten() {
+        if let Some(pid) = child["pid"].as_u64() {
+            pids.push(pid as u32);
+        }
+        collect_tree_pids(&child["children"], pids);
+    }
+}
+
+/// Optional extras gathered by `status` on top of `ProcessInfo`.
+#[derive(Clone, Copy, Default)]
+pub(super) struct StatusDetail {
+    fds: bool,
+    detail: bool,
+}
+
+/// Merge `--pid` values with a JSON array read from stdin.
+fn collect_pids(mut pids: Vec<u32>, pids_stdin: bool) -> Result<Vec<u32>, SysError> {
+    if pids_stdin {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| format!("Failed to read stdin: {e}"))?;
+        let more: Vec<u32> = serde_json::from_str(&buf).map_err(|e| {
+            SysError::new(
+                ErrorCode::InvalidArgument,
+                format!("Expected a JSON array of PIDs: {e}"),
+            )
+        })?;
+        pids.extend(more);
+    }
+    if pids.is_empty() {
+        return Err(SysError::new(
+            ErrorCode::InvalidArgument,
+            "At least one --pid is required",
+        ));
+    }
+    Ok(pids)
+}
+
+/// A single target keeps the object shape; several targets, or any read
+/// from stdin, produce an array in request order.
+fn status_many(pids: Vec<u32>, as_array: bool, extra: StatusDetail) -> serde_json::Value {
+    match pids.as_slice() {
+        [pid] if !as_array => process_status(*pid, extra),
+        _ => serde_json::Value::Array(
+            pids.into_iter()
+                .map(|pid| process_status(pid, extra))
+                .collect(),
+        ),
+    }
+}
+
+/// Stream `status` samples as NDJSON, one line per live PID per interval,
+/// and a final `exited` event for each PID as it disappears.
+fn follow_status(pids: Vec<u32>, interval: Duration, extra: StatusDetail) -> serde_json::Value {
+    let mut remaining = pids;
+    let mut stdout = std::io::stdout();
+    loop {
+        remaining.retain(|&pid| {
+            let mut status = process_status(pid, extra);
+            let alive = status["alive"] == true;
+            status["event"] = if alive { "sample" } else { "exited" }.into();
+            status["timestamp"] = crate::time::iso8601_now().into();
+            let _ = writeln!(stdout, "{status}");
+            alive
+        });
+        let _ = stdout.flush();
+        if remaining.is_empty() {
+            process::exit(output::EXIT_OK);
+        }
+        thread::sleep(interval);
+    }
+}
+
+pub(super) fn process_status(pid: u32, extra: StatusDetail) -> serde_json::Value {
+    let Some(info) = procinfo::inspect(pid) else {
+        return serde_json::json!({ "pid": pid, "alive": false });
+    };
+    let mut status = info.to_json();
+    if extra.fds {
+        status["fds"] = match procinfo::fd_usage(pid, extra.detail) {
+            Ok(usage) => serde_json::to_value(usage).unwrap_or_default(),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+    }
+    status
+}
+
+fn do_exec(
+    cmd: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    stdin_data: Option<&str>,
+    envs: &[String],
+    timeout: f64,
+) -> serde_json::Value {
+    let before = rusage::children();
+    let r = lib_run(SubprocessRequest {
+        cmd: cmd.to_string(),
+        argv0: None,
+        args: args.to_vec(),
+        cwd: cwd.map(|s| s.to_string()),
+        envs: envs
+            .iter()
+            .filter_map(|e| {
+                e.split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+            })
+            .collect(),
+        stdin_data: stdin_data.map(|s| s.to_string()),
+        timeout,
+        limits: None,
+        inherited_fds: Vec::new(),
+        supervised_status: None,
+    });
+    serde_json::json!({
+        "success": r.success, "stdout": r.stdout, "stderr": r.stderr,
+        "return_code": r.exit_code, "duration_ms": r.duration_ms,
+        "timed_out": r.timed_out,
+        "output_limit_exceeded": r.output_limit_exceeded.map(OutputLimitExceeded::as_str),
+        "stdout_truncated": r.stdout_truncated,
+        "stderr_truncated": r.stderr_truncated,
+        "rusage": before.zip(rusage::children()).map(|(before, after)| after.since(before)),
+    })
+}
+
+/// Stream mode: raw passthrough of child stdout/stderr, no JSON wrapping.
+/// Returns: child exit code, 124 on timeout, 125 on spawn failure.
+fn do_stream(
+    cmd: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    stdin_data: Option<&str>,
+    envs: &[String],
+    timeout: f64,
+) -> i32 {
+    let mut command = process::Command::new(cmd);
+    command.args(args);
+    command.env_clear();
+    set_envs(&mut command, envs);
+    // Set PYTHONUNBUFFERED for Python children to ensure streaming latency
+    command.env("PYTHONUNBUFFERED", "1");
+    if let Some(dir) = cwd {
+        command.current_dir(dir);
+    }
+    command.stdin(if stdin_data.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    });
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = match command.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to spawn: {e}");
+            return 125;
+        }
+    };
+
+    let stderr_handle = child.stderr.take();
+    let stderr_thread = thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        if let Some(mut err) = stderr_handle {
+            let mut stderr_out = std::io::stderr();
+            loop {
+                match err.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let _ = stderr_out.write_all(&buf[..n]);
+                        let _ = stderr_out.flush();
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    });
+
+    // Forward stdout: raw chunks with flush
+    let stdout_handle = child.stdout.take();
+    let stdout_thread = thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        if let Some(mut out) = stdout_handle {
+            let mut stdout_out = std::io::stdout();
+            loop {
+                match out.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let _ = stdout_out.write_all(&buf[..n]);
+                        let _ = stdout_out.flush();
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    });
+
+    // Wait with timeout. A non-positive timeout is the no-timeout sentinel.
+    let timeout_rx = if let Some(timeout_dur) = request_timeout_duration(timeout) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _timer = thread::spawn(move || {
+            thread::sleep(timeout_dur);
+            let _ = tx.send(());
+        });
+        Some(rx)
+    } else {
+        None
+    };
+    let stream_stop = Arc::new(AtomicBool::new(false));
+    let mut stdin_thread = match spawn_stdin_writer(
+        child.stdin.take(),
+        stdin_data.map(str::to_owned),
+        Arc::clone(&stream_stop),
+    ) {
+        Ok(thread) => thread,
+        Err(error) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            stream_stop.store(true, Ordering::Release);
+            let _ = stdout_thread.join();
+            let _ = stderr_thread.join();
+            eprintln!("Failed to configure nonblocking stdin: {error}");
+            return 125;
+        }
+    };
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                stream_stop.store(true, Ordering::Release);
+                if let Some(handle) = stdin_thread.take() {
+                    let _ = handle.join();
+                }
+                let _ = stdout_thread.join();
+                let _ = stderr_thread.join();
+                return status.code().unwrap_or(1);
+            }
+            Ok(None) => {
+                if timeout_rx.as_ref().is_some_and(|rx| rx.try_recv().is_ok()) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    stream_stop.store(true, Ordering::Release);
+                    if let Some(handle) = stdin_thread.take() {
+                        let _ = handle.join();
+                    }
+                    let _ = stdout_thread.join();
+                    let _ = stderr_thread.join();
+                    eprintln!("Command timed out after {timeout} seconds");
+                    return 124;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                stream_stop.store(true, Ordering::Release);
+                if let Some(handle) = stdin_thread.take() {
+                    let _ = handle.join();
+                }
+                let _ = stdout_thread.join();
+                let _ = stderr_thread.join();
+                eprintln!("Wait failed: {e}");
+                return 125;
+            }
+        }
+    }
+}
+
+/// Terminate every process in the group led by `pgid`.
+#[cfg(unix)]
+fn kill_process_group(pgid: u32, grace: f64) -> Result<&'static str, SysError> {
+    terminate(-(pgid as i32), grace)
+}
+
+#[cfg(windows)]
+fn kill_process_group(pgid: u32, grace: f64) -> Result<&'static str, SysError> {
+    kill_process(pgid, grace)
+}
+
+/// The process group `pid` leads, if any. A detached spawn calls
+/// `setsid`, so this is its session too.
+#[cfg(unix)]
+fn process_group_of(pid: u32) -> Option<u32> {
+    let pgid = unsafe { libc::getpgid(pid as i32) };
+    (pgid > 1 && pgid as u32 == pid).then_some(pid)
+}
+
+#[cfg(windows)]
+fn process_group_of(_pid: u32) -> Option<u32> {
+    None
+}
+
+/// Whether process group `pgid` still has a member.
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    let probed = unsafe { libc::kill(-(pgid as i32), 0) } == 0;
+    probed || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(windows)]
+fn group_alive(_pgid: u32) -> bool {
+    false
+}
+
+/// The process group `kill` and `status` cover for `entry`, if they
+/// cover its `group` at all.
+fn covered_group(entry: &RegistryEntry, group: bool) -> Option<u32> {
+    entry.pgid.filter(|_| group)
+}
+
+/// Whether `kill` takes the rest of the target's process group along: by
+/// default for a `name`, which stands for everything the process started,
+/// and only with `group` for a bare PID.
+pub(crate) fn kill_covers_group(name: Option<&str>, self_only: bool, group: bool) -> bool {
+    match name {
+        Some(_) => !self_only,
+        None => group,
+    }
+}
+
+/// The group of an exited `entry` whose other members outlived it. The
+/// leader's PID must be unused: one in use could lead a new group of the
+/// same number.
+fn leftover_group(entry: &RegistryEntry) -> Option<u32> {
+    entry
+        .pgid
+        .filter(|&pgid| procinfo::inspect(entry.pid).is_none() && group_alive(pgid))
+}
+
+/// [`kill_recorded`] for async callers. Registry access and container
+/// runtimes run on tokio's blocking pool; a process or pipeline is
+/// terminated with [`terminate_async`].
+#[cfg(feature = "async")]
+pub(crate) async fn kill_recorded_async(
+    registry: Option<String>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    group: bool,
+) -> (Result<Killed, SysError>, serde_json::Value) {
+    let audited = serde_json::json!({ "pid": pid, "name": name, "grace": grace });
+    let outcome =
+        kill_process_registered_async(registry.clone(), pid, name.clone(), grace, group).await;
+    let mut result = kill_json(&outcome, pid, name.as_deref());
+    let result = blocking(move || {
+        audit::record(registry.as_deref(), "kill", audited, &mut result);
+        result
+    })
+    .await
+    .unwrap_or_else(|e| serde_json::json!({ "success": false, "error": e }));
+    (outcome, result)
+}
+
+/// [`kill_process_registered`] without blocking a thread for the grace
+/// period.
+#[cfg(feature = "async")]
+async fn kill_process_registered_async(
+    registry: Option<String>,
+    pid: Option<u32>,
+    name: Option<String>,
+    grace: f64,
+    group: bool,
+) -> Result<Killed, SysError> {
+    let target = {
+        let (registry, name) = (registry.clone(), name.clone());
+        blocking(move || {
+            kill_target(
+                &Registry::locate(registry.as_deref()),
+                pid,
+                name.as_deref(),
+                group,
+            )
+        })
+        .await??
+    };
+    let (entry, pid) = target;
+    let pgid = entry.as_ref().and_then(|entry| covered_group(entry, group));
+    let killed = match &entry {
+        Some(RegistryEntry {
+            container: Some(container),
+            ..
+        }) => {
+            let container = container.clone();
+            blocking(move || container.stop(grace))
+                .await?
+                .map_err(SysError::from)
+        }
+        #[cfg(unix)]
+        Some(entry) if !entry.stages.is_empty() => terminate_async(-(pid as i32), grace).await,
+        #[cfg(unix)]
+        _ => match pgid {
+            Some(pgid) => terminate_async(-(pgid as i32), grace).await,
+            None => terminate_async(pid as i32, grace).await,
+        },
+        #[cfg(not(unix))]
+        _ => blocking(move || kill_process(pid, grace)).await?,
+    };
+    blocking(move || {
+        let registry = Registry::locate(registry.as_deref());
+        killed_entry(&registry, entry.as_ref(), pid, pgid, killed)
+    })
+    .await?
+}
+
+/// Run `f` on tokio's blocking pool.
+#[cfg(feature = "async")]
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Blocking task failed: {e}"))
+}
@@ -0,0 +1,334 @@
+//! Defaults for `lillux exec` from TOML files.
+//!
+//! Two files are read, when they exist: the user's
+//! `$XDG_CONFIG_HOME/lillux/config.toml` (`~/.config/...`; `LILLUX_CONFIG`
+//! names another file) and the first `.lillux.toml` found walking up from
+//! the working directory. The project file overrides the user's, the
+//! environment (`LILLUX_EXEC_REGISTRY`) overrides both, and command-line
+//! flags override everything. Relative paths in a file are relative to
+//! that file's directory. `lillux exec config show` prints the result and
+//! where each setting came from.
+//!
+//! The files are read when a command first needs a setting a flag did not
+//! give, so a broken file fails only those commands, with the
+//! `invalid_config` code.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::registry::REGISTRY_PATH_ENV;
+use super::sys_error::{ErrorCode, SysError};
+
+/// Names a user config file to read instead of the default one.
+pub const CONFIG_PATH_ENV: &str = "LILLUX_CONFIG";
+
+/// Project config, looked for in the working directory and its ancestors.
+const PROJECT_FILE: &str = ".lillux.toml";
+
+/// `kill` grace period when neither `--grace` nor a config file sets one.
+pub const DEFAULT_GRACE: f64 = 3.0;
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the effective configuration and the source of each setting
+    Show,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Registry database, when neither `--registry` nor
+    /// `LILLUX_EXEC_REGISTRY` is given.
+    pub registry: Option<String>,
+    /// Where `spawn` without `--log` logs: `<log_dir>/<name>.log`, or the
+    /// command's file name for an unnamed process.
+    pub log_dir: Option<String>,
+    /// Seconds `kill` waits between SIGTERM and SIGKILL without `--grace`.
+    pub grace: Option<f64>,
+}
+
+/// The merged configuration, with the file each setting came from.
+#[derive(Debug, Default, Clone)]
+pub struct Effective {
+    pub config: Config,
+    /// Files read, user file first.
+    pub files: Vec<PathBuf>,
+    sources: BTreeMap<&'static str, PathBuf>,
+}
+
+impl Effective {
+    /// The registry to open: `flag`, else the configured one unless the
+    /// environment names one (which [`Registry::locate`] then reads).
+    ///
+    /// [`Registry::locate`]: super::registry::Registry::locate
+    pub fn registry(&self, flag: Option<&str>) -> Option<String> {
+        if let Some(flag) = flag {
+            return Some(flag.to_string());
+        }
+        if registry_env_set() {
+            return None;
+        }
+        self.config.registry.clone()
+    }
+
+    pub fn grace(&self, flag: Option<f64>) -> f64 {
+        flag.or(self.config.grace).unwrap_or(DEFAULT_GRACE)
+    }
+
+    /// `--log` for a spawn of `cmd` registered as `name`. A configured
+    /// `log_dir` is created if missing.
+    pub fn log(
+        &self,
+        flag: Option<String>,
+        name: Option<&str>,
+        cmd: &str,
+    ) -> Result<Option<String>, String> {
+        let Some(dir) = self.config.log_dir.as_deref().filter(|_| flag.is_none()) else {
+            return Ok(flag);
+        };
+        let stem = name.map(str::to_string).unwrap_or_else(|| {
+            Path::new(cmd).file_name().map_or_else(
+                || "process".to_string(),
+                |file| file.to_string_lossy().into_owned(),
+            )
+        });
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {dir}: {e}"))?;
+        Ok(Some(
+            Path::new(dir)
+                .join(format!("{stem}.log"))
+                .display()
+                .to_string(),
+        ))
+    }
+
+    fn apply(&mut self, path: &Path, layer: Config) {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let resolve = |value: String| dir.join(value).display().to_string();
+        if let Some(registry) = layer.registry {
+            self.config.registry = Some(resolve(registry));
+            self.sources.insert("registry", path.to_path_buf());
+        }
+        if let Some(log_dir) = layer.log_dir {
+            self.config.log_dir = Some(resolve(log_dir));
+            self.sources.insert("log_dir", path.to_path_buf());
+        }
+        if let Some(grace) = layer.grace {
+            self.config.grace = Some(grace);
+            self.sources.insert("grace", path.to_path_buf());
+        }
+    }
+
+    /// `config show`, for a command line with `--registry flag`.
+    pub fn show(&self, flag: Option<&str>) -> Value {
+        let source = |key: &'static str| {
+            self.sources
+                .get(key)
+                .map_or_else(|| "default".to_string(), |path| path.display().to_string())
+        };
+        let registry_source = if flag.is_some() {
+            "flag".to_string()
+        } else if registry_env_set() {
+            format!("env:{REGISTRY_PATH_ENV}")
+        } else {
+            source("registry")
+        };
+        let registry = super::registry::Registry::locate(self.registry(flag).as_deref())
+            .map(|registry| registry.path().display().to_string());
+        json!({
+            "config": {
+                "registry": registry.ok(),
+                "log_dir": self.config.log_dir,
+                "grace": self.grace(None),
+            },
+            "sources": {
+                "registry": registry_source,
+                "log_dir": source("log_dir"),
+                "grace": source("grace"),
+            },
+            "files": self.files.iter().map(|file| file.display().to_string()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// The configuration, loaded on first use.
+#[derive(Debug, Default)]
+pub struct Lazy {
+    effective: OnceLock<Result<Effective, SysError>>,
+}
+
+impl Lazy {
+    pub fn get(&self) -> Result<&Effective, SysError> {
+        self.effective
+            .get_or_init(load)
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// [`Effective::registry`], reading the files only when neither the
+    /// flag nor the environment names the registry.
+    pub fn registry(&self, flag: Option<&str>) -> Result<Option<String>, SysError> {
+        if flag.is_some() || registry_env_set() {
+            return Ok(flag.map(str::to_string));
+        }
+        Ok(self.get()?.registry(None))
+    }
+
+    pub fn grace(&self, flag: Option<f64>) -> Result<f64, SysError> {
+        match flag {
+            Some(grace) => Ok(grace),
+            None => Ok(self.get()?.grace(None)),
+        }
+    }
+
+    pub fn log(
+        &self,
+        flag: Option<String>,
+        name: Option<&str>,
+        cmd: &str,
+    ) -> Result<Option<String>, SysError> {
+        if flag.is_some() {
+            return Ok(flag);
+        }
+        Ok(self.get()?.log(None, name, cmd)?)
+    }
+}
+
+fn registry_env_set() -> bool {
+    std::env::var_os(REGISTRY_PATH_ENV).is_some_and(|value| !value.is_empty())
+}
+
+/// Read and merge the config files that exist.
+pub fn load() -> Result<Effective, SysError> {
+    let mut effective = Effective::default();
+    let project = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| project_path(&cwd));
+    for path in [user_path(), project].into_iter().flatten() {
+        if !path.is_file() {
+            continue;
+        }
+        let layer = read(&path)?;
+        effective.apply(&path, layer);
+        effective.files.push(path);
+    }
+    Ok(effective)
+}
+
+fn read(path: &Path) -> Result<Config, SysError> {
+    let shown = path.display();
+    let invalid =
+        |message: String| SysError::new(ErrorCode::InvalidConfig, message).path(shown.to_string());
+    let raw = std::fs::read_to_string(path).map_err(|e| SysError {
+        code: ErrorCode::InvalidConfig,
+        ..SysError::io(format!("Failed to read {shown}"), "open", &e).path(shown.to_string())
+    })?;
+    let config: Config =
+        toml::from_str(&raw).map_err(|e| invalid(format!("Invalid config {shown}: {e}")))?;
+    match config.grace {
+        Some(grace) if !(grace >= 0.0 && grace.is_finite()) => Err(invalid(format!(
+            "Invalid config {shown}: grace must be a non-negative number of seconds"
+        ))),
+        _ => Ok(config),
+    }
+}
+
+fn user_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_PATH_ENV).filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|v| !v.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .map(|config| config.join("lillux").join("config.toml"))
+}
+
+/// The nearest `.lillux.toml` at or above `dir`.
+fn project_path(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_settings_override_user_ones_and_flags_override_both() {
+        let tmp = tempfile::tempdir().unwrap();
+        let user = tmp.path().join("config.toml");
+        std::fs::write(&user, "grace = 10\nlog_dir = \"/var/log/lillux\"\n").unwrap();
+        let project = tmp.path().join("app");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(
+            project.join(PROJECT_FILE),
+            "registry = \"state/registry.db\"\ngrace = 1.5\n",
+        )
+        .unwrap();
+
+        let found = project_path(&project.join("src")).unwrap();
+        assert_eq!(found, project.join(PROJECT_FILE));
+        let mut effective = Effective::default();
+        effective.apply(&user, read(&user).unwrap());
+        effective.apply(&found, read(&found).unwrap());
+
+        assert_eq!(effective.grace(None), 1.5);
+        assert_eq!(effective.grace(Some(0.0)), 0.0);
+        assert_eq!(
+            effective.config.registry.as_deref(),
+            Some(project.join("state/registry.db").to_str().unwrap())
+        );
+        effective.config.log_dir = Some(tmp.path().join("logs").display().to_string());
+        assert_eq!(
+            effective.log(None, Some("web"), "./server").unwrap(),
+            Some(tmp.path().join("logs/web.log").display().to_string())
+        );
+        assert_eq!(
+            effective.log(None, None, "/usr/bin/python3").unwrap(),
+            Some(tmp.path().join("logs/python3.log").display().to_string())
+        );
+        assert_eq!(
+            effective.log(Some("own.log".into()), None, "x").unwrap(),
+            Some("own.log".to_string())
+        );
+        assert_eq!(effective.sources["grace"], found);
+        assert_eq!(effective.sources["log_dir"], user);
+
+        std::fs::write(&user, "grace = 2\ncolour = \"red\"\n").unwrap();
+        let err = read(&user).unwrap_err();
+        assert!(err.message.contains("colour"), "{err}");
+        assert_eq!(err.code, ErrorCode::InvalidConfig);
+    }
+
+    #[test]
+    fn a_broken_config_only_fails_settings_no_flag_gave() {
+        let broken = Lazy::default();
+        let err = SysError::new(ErrorCode::InvalidConfig, "Invalid config x");
+        broken.effective.set(Err(err.clone())).unwrap();
+
+        assert_eq!(
+            broken.registry(Some("r.db")).unwrap().as_deref(),
+            Some("r.db")
+        );
+        assert_eq!(broken.grace(Some(1.0)).unwrap(), 1.0);
+        assert_eq!(
+            broken
+                .log(Some("a.log".into()), None, "x")
+                .unwrap()
+                .as_deref(),
+            Some("a.log")
+        );
+        assert_eq!(broken.grace(None).unwrap_err(), err);
+        assert_eq!(broken.log(None, None, "x").unwrap_err(), err);
+    }
+}
@@ -0,0 +1,264 @@
+//! `lillux exec spawn --backend docker|podman`: processes that run inside
+//! a container.
+//!
+//! The container is started detached by the runtime CLI and recorded with
+//! the host PID of its init process, so liveness checks work exactly as
+//! for bare processes. `kill`, `status`, `logs`, and `export` consult the
+//! recorded container instead of the PID where the runtime knows better.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Stdio};
+use std::sync::{Arc, Mutex};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Runtime {
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    pub fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+}
+
+/// The container behind a registry entry and how it was configured.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Container {
+    pub runtime: Runtime,
+    /// Assigned by the runtime once started; empty before then.
+    #[serde(default)]
+    pub id: String,
+    pub image: String,
+    /// `--volume` specs, `HOST:CONTAINER[:OPTIONS]`.
+    #[serde(default)]
+    pub mounts: Vec<String>,
+    /// `--publish` specs, `[IP:]HOST:CONTAINER[/PROTO]`.
+    #[serde(default)]
+    pub ports: Vec<String>,
+}
+
+impl Container {
+    /// `run` arguments for the runtime CLI, up to and including the
+    /// command. `detach` starts it in the background and keeps the
+    /// container after exit so its logs stay readable; otherwise it runs
+    /// in the foreground and is removed on exit.
+    pub fn run_args(
+        &self,
+        name: Option<&str>,
+        envs: &[String],
+        cmd: &str,
+        args: &[String],
+        detach: bool,
+    ) -> Vec<String> {
+        let mut run = vec!["run".to_string()];
+        run.push(if detach { "--detach" } else { "--rm" }.to_string());
+        if let Some(name) = name {
+            run.extend(["--label".to_string(), format!("lillux.name={name}")]);
+        }
+        for env in envs {
+            run.extend(["--env".to_string(), env.clone()]);
+        }
+        for mount in &self.mounts {
+            run.extend(["--volume".to_string(), mount.clone()]);
+        }
+        for port in &self.ports {
+            run.extend(["--publish".to_string(), port.clone()]);
+        }
+        run.push(self.image.clone());
+        run.push(cmd.to_string());
+        run.extend(args.iter().cloned());
+        run
+    }
+
+    /// Start the container detached; fills in `id` and returns the host
+    /// PID of its init process.
+    pub fn start(
+        &mut self,
+        name: Option<&str>,
+        envs: &[String],
+        cmd: &str,
+        args: &[String],
+    ) -> Result<u32, String> {
+        let run = self.run_args(name, envs, cmd, args, true);
+        self.id = self.output(&run)?;
+        let pid = self.output(&[
+            "inspect".to_string(),
+            "--format".to_string(),
+            "{{.State.Pid}}".to_string(),
+            self.id.clone(),
+        ])?;
+        match pid.parse::<u32>() {
+            Ok(pid) if pid > 0 => Ok(pid),
+            _ => Err(format!(
+                "Container {} exited immediately; see `{} logs {}`",
+                self.id,
+                self.runtime.program(),
+                self.id
+            )),
+        }
+    }
+
+    /// Stop the container, giving it `grace` seconds before SIGKILL.
+    pub fn stop(&self, grace: f64) -> Result<&'static str, String> {
+        let seconds = grace.max(0.0).ceil() as u64;
+        self.output(&[
+            "stop".to_string(),
+            "--time".to_string(),
+            seconds.to_string(),
+            self.id.clone(),
+        ])?;
+        Ok("stopped")
+    }
+
+    /// The runtime's view of the container: status, exit code, timestamps.
+    pub fn state(&self) -> Result<serde_json::Value, String> {
+        let state = self.output(&[
+            "inspect".to_string(),
+            "--format".to_string(),
+            "{{json .State}}".to_string(),
+            self.id.clone(),
+        ])?;
+        let state: serde_json::Value = serde_json::from_str(&state).map_err(|e| {
+            format!(
+                "Unexpected inspect output from {}: {e}",
+                self.runtime.program()
+            )
+        })?;
+        Ok(serde_json::json!({
+            "runtime": self.runtime,
+            "id": self.id,
+            "image": self.image,
+            "status": state["Status"],
+            "running": state["Running"],
+            "exit_code": state["ExitCode"],
+            "started_at": state["StartedAt"],
+            "finished_at": state["FinishedAt"],
+        }))
+    }
+
+    /// Print the container's logs (the last `lines`, or everything within
+    /// `since`, then new output with `follow`) and return the runtime's exit code. JSON output wraps
+    /// each line, stdout and stderr alike, as a `line` event.
+    pub fn show_logs(
+        &self,
+        name: &str,
+        lines: usize,
+        since: Option<&str>,
+        follow: bool,
+        format: super::logs::LogFormat,
+    ) -> Result<i32, String> {
+        let program = self.runtime.program();
+        let mut command = process::Command::new(program);
+        match since {
+            // The runtime reads the duration itself (Go syntax, e.g. `2h`).
+            Some(since) => command.args(["logs", "--since", since]),
+            None => command.args(["logs", "--tail", &lines.to_string()]),
+        };
+        if follow {
+            command.arg("--follow");
+        }
+        command.arg(&self.id).stdin(Stdio::null());
+        if matches!(format, super::logs::LogFormat::Plain) {
+            let status = command
+                .status()
+                .map_err(|e| format!("Failed to run {program}: {e}"))?;
+            return Ok(status.code().unwrap_or(1));
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {program}: {e}"))?;
+        let out = Arc::new(Mutex::new(std::io::stdout()));
+        let relay = |stream: Box<dyn std::io::Read + Send>| {
+            let out = Arc::clone(&out);
+            let name = name.to_string();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    let mut out = out.lock().unwrap_or_else(|e| e.into_inner());
+                    if super::logs::emit(&mut *out, format, &name, &line).is_err() {
+                        return;
+                    }
+                    let _ = out.flush();
+                }
+            })
+        };
+        let readers = [
+            child.stdout.take().map(|s| relay(Box::new(s))),
+            child.stderr.take().map(|s| relay(Box::new(s))),
+        ];
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+        let status = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for {program}: {e}"))?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    /// Run the runtime CLI and return its trimmed stdout, or its stderr as
+    /// the error.
+    fn output(&self, args: &[String]) -> Result<String, String> {
+        let program = self.runtime.program();
+        let output = process::Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("Failed to run {program}: {e}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{program} {} failed: {}", args[0], stderr.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_args_pass_environment_mounts_and_ports_before_the_image() {
+        let container = Container {
+            runtime: Runtime::Podman,
+            id: String::new(),
+            image: "nginx:1.27".to_string(),
+            mounts: vec!["/srv/site:/usr/share/nginx/html:ro".to_string()],
+            ports: vec!["8080:80".to_string()],
+        };
+        let args = container.run_args(
+            Some("web"),
+            &["TZ=UTC".to_string()],
+            "nginx",
+            &["-g".to_string(), "daemon off;".to_string()],
+            true,
+        );
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--detach",
+                "--label",
+                "lillux.name=web",
+                "--env",
+                "TZ=UTC",
+                "--volume",
+                "/srv/site:/usr/share/nginx/html:ro",
+                "--publish",
+                "8080:80",
+                "nginx:1.27",
+                "nginx",
+                "-g",
+                "daemon off;",
+            ]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Core dumps of spawned processes (`spawn --core-dumps DIR`).
+//!
+//! The child starts with its soft `RLIMIT_CORE` raised to the hard limit,
+//! so a crash writes a core wherever the kernel's pattern says
+//! (`/proc/sys/kernel/core_pattern` on Linux, `/cores/core.%p` on macOS):
+//! a relative pattern is resolved against the working directory the
+//! process started in. Once the process is gone the dump is found there,
+//! moved into `DIR` as `<name>.<pid>.core`, and recorded in the registry.
+//! The daemon collects when it reaps a child; `exec cores --name` collects
+//! on demand. A pattern piping cores to a handler such as
+//! systemd-coredump leaves nothing to collect and is reported at spawn.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::registry::{Registry, RegistryEntry};
+use super::SysError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreDumps {
+    /// Where collected dumps are kept.
+    pub dir: String,
+    /// Working directory the process started in, where a relative
+    /// pattern writes its core.
+    pub cwd: String,
+    /// Collected dumps, oldest first.
+    #[serde(default)]
+    pub dumps: Vec<String>,
+}
+
+/// Create `dir` and describe where `spawn` should look for dumps later.
+#[cfg(unix)]
+pub fn prepare(dir: &str) -> Result<CoreDumps, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {dir}: {e}"))?;
+    let dir = std::fs::canonicalize(dir).map_err(|e| format!("Failed to resolve {dir}: {e}"))?;
+    let cwd = std::env::current_dir()
+        .map_err(|e| format!("Failed to read the working directory: {e}"))?;
+    Ok(CoreDumps {
+        dir: dir.display().to_string(),
+        cwd: cwd.display().to_string(),
+        dumps: Vec::new(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn prepare(_dir: &str) -> Result<CoreDumps, String> {
+    Err("--core-dumps is not supported on this platform".to_string())
+}
+
+/// Why a crash of a child spawned now would leave no core to collect.
+#[cfg(unix)]
+pub fn warning() -> Option<String> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } == 0 && limit.rlim_max == 0 {
+        return Some("The hard RLIMIT_CORE is 0; no core will be written".to_string());
+    }
+    let pattern = core_pattern();
+    pattern.starts_with('|').then(|| {
+        format!("core_pattern pipes cores to a handler ({pattern}); none will be collected")
+    })
+}
+
+#[cfg(not(unix))]
+pub fn warning() -> Option<String> {
+    None
+}
+
+/// Move any dump `entry` left since it started into its `--core-dumps`
+/// directory and record it. Returns the dumps collected by this call.
+pub fn collect(registry: &Registry, entry: &RegistryEntry) -> Result<Vec<String>, String> {
+    let Some(cores) = &entry.core_dumps else {
+        return Ok(Vec::new());
+    };
+    let since_ms = entry.started_at_ms.unwrap_or_default();
+    let label = entry.name.clone().unwrap_or_else(|| {
+        Path::new(&entry.cmd).file_name().map_or_else(
+            || "core".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    });
+    let mut collected = Vec::new();
+    for (index, found) in candidates(cores, entry.pid, since_ms)
+        .into_iter()
+        .enumerate()
+    {
+        let file = match index {
+            0 => format!("{label}.{}.core", entry.pid),
+            _ => format!("{label}.{}.{index}.core", entry.pid),
+        };
+        let target = Path::new(&cores.dir).join(file);
+        if found != target {
+            move_file(&found, &target)?;
+        }
+        collected.push(target.display().to_string());
+    }
+    if !collected.is_empty() {
+        let mut cores = cores.clone();
+        for dump in &collected {
+            if !cores.dumps.contains(dump) {
+                cores.dumps.push(dump.clone());
+            }
+        }
+        registry.update_core_dumps(entry.id, &cores)?;
+    }
+    Ok(collected)
+}
+
+/// `exec cores --name`: collect if the process is gone, then list its dumps.
+pub fn run(registry: Option<&str>, name: &str) -> Value {
+    list(registry, name).unwrap_or_else(|e| e.failed_with(json!({ "name": name })))
+}
+
+fn list(registry: Option<&str>, name: &str) -> Result<Value, SysError> {
+    let entry = super::named_entry(registry, name)?;
+    let Some(cores) = &entry.core_dumps else {
+        return Err(format!("'{name}' was spawned without --core-dumps").into());
+    };
+    // A zombie is not live, and has already written its core.
+    let alive = entry.is_live();
+    let mut dumps = cores.dumps.clone();
+    if !alive {
+        for dump in collect(&Registry::locate(registry)?, &entry)? {
+            if !dumps.contains(&dump) {
+                dumps.push(dump);
+            }
+        }
+    }
+    Ok(json!({
+        "name": name,
+        "pid": entry.pid,
+        "alive": alive,
+        "dir": cores.dir,
+        "dumps": dumps,
+    }))
+}
+
+/// Files matching the core pattern for `pid` modified at or after
+/// `since_ms`. A pattern without the PID in it (plain `core`) can only be
+/// told apart from other processes' dumps by that time.
+fn candidates(cores: &CoreDumps, pid: u32, since_ms: u64) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for pattern in patterns() {
+        let Some(template) = expand(&pattern, pid) else {
+            continue;
+        };
+        let template = Path::new(&cores.cwd).join(template);
+        let (Some(dir), Some(name)) = (template.parent(), template.file_name()) else {
+            continue;
+        };
+        let name: Vec<char> = name.to_string_lossy().chars().collect();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for file in entries.filter_map(Result::ok) {
+            let file_name: Vec<char> = file.file_name().to_string_lossy().chars().collect();
+            let recent = file
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .is_some_and(|modified| modified.as_millis() as u64 + 1000 >= since_ms);
+            if recent && glob_match(&name, &file_name) && !found.contains(&file.path()) {
+                found.push(file.path());
+            }
+        }
+    }
+    found
+}
+
+/// The kernel's core file name templates on this platform.
+fn patterns() -> Vec<String> {
+    if cfg!(target_os = "macos") {
+        vec!["/cores/core.%p".to_string()]
+    } else if cfg!(target_os = "linux") {
+        let pattern = core_pattern();
+        let uses_pid = std::fs::read_to_string("/proc/sys/kernel/core_uses_pid")
+            .is_ok_and(|raw| raw.trim() == "1");
+        if uses_pid && !pattern.contains("%p") {
+            vec![format!("{pattern}.%p")]
+        } else {
+            vec![pattern]
+        }
+    } else {
+        vec!["core".to_string(), "%e.core".to_string()]
+    }
+}
+
+fn core_pattern() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/core_pattern")
+        .map(|raw| raw.trim().to_string())
+        .unwrap_or_else(|_| "core".to_string())
+}
+
+/// `pattern` as a glob for `pid`'s dump: `%p`/`%P` become the PID, `%%` a
+/// percent sign, and every other specifier (time, host, executable, ...)
+/// a `*`. `None` for a pattern piping to a handler.
+fn expand(pattern: &str, pid: u32) -> Option<String> {
+    if pattern.is_empty() || pattern.starts_with('|') {
+        return None;
+    }
+    let mut glob = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            glob.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('p' | 'P') => glob.push_str(&pid.to_string()),
+            Some('%') => glob.push('%'),
+            Some(_) => glob.push('*'),
+            None => {}
+        }
+    }
+    Some(glob)
+}
+
+/// Whether `name` matches `glob`, where `*` matches any run of characters.
+fn glob_match(glob: &[char], name: &[char]) -> bool {
+    match glob.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+        Some((c, rest)) => name.first() == Some(c) && glob_match(rest, &name[1..]),
+    }
+}
+
+/// Rename `from` to `to`, copying when they are on different filesystems.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)
+        .and_then(|_| std::fs::remove_file(from))
+        .map_err(|e| format!("Failed to move {} to {}: {e}", from.display(), to.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_expand_to_globs_for_the_pid() {
+        assert_eq!(expand("core", 42).unwrap(), "core");
+        assert_eq!(
+            expand("/var/crash/core.%e.%p.%t", 42).unwrap(),
+            "/var/crash/core.*.42.*"
+        );
+        assert_eq!(expand("100%%-%P", 7).unwrap(), "100%-7");
+        assert_eq!(expand("|/usr/lib/systemd/systemd-coredump %P", 7), None);
+
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert!(glob_match(
+            &chars("core.*.42.*"),
+            &chars("core.web.42.1700000000")
+        ));
+        assert!(!glob_match(
+            &chars("core.*.42.*"),
+            &chars("core.web.421.1700000000")
+        ));
+        assert!(!glob_match(&chars("core"), &chars("core.1")));
+    }
+}
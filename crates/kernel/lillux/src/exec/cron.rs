@@ -0,0 +1,194 @@
+//! Five-field cron expressions (`minute hour day-of-month month
+//! day-of-week`) for `lillux exec schedule`, evaluated in UTC.
+//!
+//! Fields accept `*`, numbers, ranges `a-b`, steps `*/n` and `a-b/n`, and
+//! comma-separated lists. Day-of-week runs 0-7 with both 0 and 7 meaning
+//! Sunday. As in Vixie cron, when both day fields are restricted a day
+//! matching either one fires.
+
+/// Upper bound on the search in [`CronExpr::next_after`]; every valid
+/// expression fires within one leap cycle of a February 29th.
+const SEARCH_LIMIT_DAYS: u64 = 366 * 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "Cron expression must have 5 fields (minute hour day month weekday): '{expr}'"
+            ));
+        };
+        // Fold Sunday-as-7 onto 0.
+        let weekdays = field(weekday, 0, 7, "day-of-week")?;
+        let weekdays = (weekdays | weekdays >> 7) & 0x7f;
+        Ok(Self {
+            minutes: field(minute, 0, 59, "minute")?,
+            hours: field(hour, 0, 23, "hour")?,
+            days: field(day, 1, 31, "day-of-month")?,
+            months: field(month, 1, 12, "month")?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the minute containing `unix_secs` is a firing time.
+    pub fn matches(&self, unix_secs: u64) -> bool {
+        let days = unix_secs / 86_400;
+        let minute_of_day = (unix_secs % 86_400) / 60;
+        self.matches_day(days)
+            && bit(self.hours, minute_of_day / 60)
+            && bit(self.minutes, minute_of_day % 60)
+    }
+
+    /// The first firing minute strictly after `unix_secs`, as Unix seconds.
+    pub fn next_after(&self, unix_secs: u64) -> Option<u64> {
+        let mut minute = unix_secs / 60 + 1;
+        let limit = minute + SEARCH_LIMIT_DAYS * 1440;
+        while minute < limit {
+            let days = minute / 1440;
+            if !self.matches_day(days) {
+                minute = (days + 1) * 1440;
+                continue;
+            }
+            let minute_of_day = minute % 1440;
+            if !bit(self.hours, minute_of_day / 60) {
+                minute = (minute / 60 + 1) * 60;
+                continue;
+            }
+            if bit(self.minutes, minute_of_day % 60) {
+                return Some(minute * 60);
+            }
+            minute += 1;
+        }
+        None
+    }
+
+    fn matches_day(&self, days: u64) -> bool {
+        let (_, month, day) = crate::time::civil_from_days(days as i64);
+        // 1970-01-01 was a Thursday.
+        let weekday = (days + 4) % 7;
+        if !bit(self.months, month as u64) {
+            return false;
+        }
+        let day_ok = bit(self.days, day as u64);
+        let weekday_ok = bit(self.weekdays, weekday);
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_ok || weekday_ok,
+            _ => day_ok && weekday_ok,
+        }
+    }
+}
+
+fn bit(set: u64, value: u64) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parse one field into a bitset of the values it selects.
+fn field(raw: &str, min: u64, max: u64, name: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid cron {name} field '{raw}' (allowed {min}-{max})");
+    let number = |text: &str| -> Result<u64, String> {
+        text.parse::<u64>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(invalid)
+    };
+    let mut set = 0u64;
+    for part in raw.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // `5/15` means from 5 to the end in steps of 15.
+                None if part.contains('/') => (number(range)?, max),
+                None => {
+                    let value = number(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-03-02T10:07:00Z, a Monday.
+    const MONDAY_1007: u64 = 1_772_446_020;
+
+    #[test]
+    fn steps_ranges_and_lists_select_the_expected_minutes() {
+        let every_five = CronExpr::parse("*/5 * * * *").unwrap();
+        assert!(!every_five.matches(MONDAY_1007));
+        assert_eq!(every_five.next_after(MONDAY_1007), Some(MONDAY_1007 + 180));
+
+        let weekday_mornings = CronExpr::parse("30 9-11 * * 1-5").unwrap();
+        assert_eq!(
+            weekday_mornings.next_after(MONDAY_1007),
+            Some(MONDAY_1007 + 23 * 60)
+        );
+
+        let sundays = CronExpr::parse("0 0 * * 7").unwrap();
+        assert_eq!(sundays, CronExpr::parse("@weekly").unwrap());
+        assert_eq!(
+            sundays.next_after(MONDAY_1007),
+            Some(MONDAY_1007 - (10 * 60 + 7) * 60 + 6 * 86_400)
+        );
+    }
+
+    #[test]
+    fn restricted_day_fields_fire_on_either() {
+        // The 1st of the month or any Monday.
+        let expr = CronExpr::parse("7 10 1 * 1").unwrap();
+        assert!(expr.matches(MONDAY_1007));
+        assert!(expr.matches(MONDAY_1007 - 86_400));
+        assert!(!expr.matches(MONDAY_1007 + 86_400));
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(CronExpr::parse(expr).is_err(), "{expr}");
+        }
+    }
+}
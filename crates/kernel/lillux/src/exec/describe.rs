@@ -0,0 +1,262 @@
+//! `lillux exec describe`: a machine-readable description of every exec
+//! subcommand, derived from the clap definitions so it cannot drift.
+
+use clap::builder::{ArgAction, ValueParser};
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+/// Bumped when the layout of the description itself changes.
+pub const DESCRIPTION_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DescribeFormat {
+    /// JSON Schema for each subcommand's arguments and output
+    JsonSchema,
+}
+
+/// Describe the subcommands of `command` (the `exec` command).
+pub fn describe(command: &clap::Command) -> Value {
+    let global: Vec<Value> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && arg.is_global_set())
+        .map(|arg| json!({ "name": flag_name(arg), "schema": arg_schema(arg) }))
+        .collect();
+    let commands: Vec<Value> = command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .map(|sub| {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for arg in sub.get_arguments() {
+                if arg.is_hide_set() || is_builtin(arg) {
+                    continue;
+                }
+                if arg.is_required_set() {
+                    required.push(Value::from(flag_name(arg)));
+                }
+                properties.insert(flag_name(arg), arg_schema(arg));
+            }
+            json!({
+                "name": sub.get_name(),
+                "description": sub.get_about().map(|about| about.to_string()),
+                "input": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                    "additionalProperties": false,
+                },
+                "output": output_schema(sub.get_name()),
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "description_version": DESCRIPTION_VERSION,
+        "tool": format!("lillux {}", command.get_name()),
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "global_arguments": global,
+        "commands": commands,
+    })
+}
+
+/// The long flag an argument is passed as, without dashes.
+fn flag_name(arg: &clap::Arg) -> String {
+    arg.get_long()
+        .map(str::to_string)
+        .unwrap_or_else(|| arg.get_id().to_string())
+}
+
+fn is_builtin(arg: &clap::Arg) -> bool {
+    matches!(arg.get_id().as_str(), "help" | "version")
+}
+
+fn arg_schema(arg: &clap::Arg) -> Value {
+    let mut schema = match arg.get_action() {
+        ArgAction::SetTrue | ArgAction::SetFalse => json!({ "type": "boolean" }),
+        _ => value_schema(arg),
+    };
+    if let Some(help) = arg.get_help() {
+        schema["description"] = help.to_string().into();
+    }
+    if let [default] = arg.get_default_values() {
+        if !matches!(arg.get_action(), ArgAction::SetTrue | ArgAction::SetFalse) {
+            let default = default.to_string_lossy();
+            schema["default"] = match schema["type"].as_str() {
+                Some("integer") => default.parse::<i64>().map(Value::from).unwrap_or_default(),
+                Some("number") => default.parse::<f64>().map(Value::from).unwrap_or_default(),
+                _ => Value::from(default.into_owned()),
+            };
+        }
+    }
+    if matches!(arg.get_action(), ArgAction::Append) {
+        schema = json!({ "type": "array", "items": schema });
+    }
+    schema
+}
+
+fn value_schema(arg: &clap::Arg) -> Value {
+    let choices: Vec<Value> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| Value::from(value.get_name()))
+        .collect();
+    if !choices.is_empty() {
+        return json!({ "type": "string", "enum": choices });
+    }
+    let id = arg.get_value_parser().type_id();
+    let parses = |parser: ValueParser| parser.type_id() == id;
+    if parses(clap::value_parser!(u32).into())
+        || parses(clap::value_parser!(u64).into())
+        || parses(clap::value_parser!(usize).into())
+    {
+        json!({ "type": "integer", "minimum": 0 })
+    } else if parses(clap::value_parser!(f64).into()) {
+        json!({ "type": "number" })
+    } else {
+        json!({ "type": "string" })
+    }
+}
+
+/// What a subcommand prints. Commands that stream print NDJSON or raw
+/// text and say so; the rest print one JSON document, and any failure
+/// prints an object with an `error` string.
+fn output_schema(command: &str) -> Value {
+    let error = json!({
+        "type": "object",
+        "properties": { "error": { "type": "string" } },
+        "required": ["error"],
+    });
+    let pid = json!({ "type": "integer", "minimum": 0 });
+    let status = json!({
+        "type": "object",
+        "properties": {
+            "pid": pid,
+            "alive": { "type": "boolean" },
+            "name": { "type": "string" },
+            "ppid": { "type": "integer" },
+            "cmdline": { "type": ["array", "null"], "items": { "type": "string" } },
+            "rss_bytes": { "type": ["integer", "null"] },
+            "cpu_percent": { "type": ["number", "null"] },
+            "uptime_ms": { "type": ["integer", "null"] },
+        },
+        "required": ["pid", "alive"],
+    });
+    let success = |extra: Value| {
+        let mut properties = json!({ "success": { "type": "boolean" }, "pid": pid });
+        if let (Some(properties), Some(extra)) = (properties.as_object_mut(), extra.as_object()) {
+            properties.extend(extra.clone());
+        }
+        json!({ "type": "object", "properties": properties, "required": ["success"] })
+    };
+    let document = match command {
+        "spawn" => success(json!({
+            "name": { "type": "string" },
+            "container": { "type": "string" },
+            "registry_error": { "type": "string" },
+        })),
+        "kill" => success(json!({
+            "method": { "enum": ["terminated", "killed", "already_dead"] },
+            "name": { "type": "string" },
+        })),
+        "send" => success(json!({ "name": { "type": "string" }, "bytes": { "type": "integer" } })),
+        "on-exit" => success(json!({ "watcher_pid": pid })),
+        "pipeline" => success(json!({
+            "pgid": pid,
+            "stages": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "pid": pid,
+                        "argv": { "type": "array", "items": { "type": "string" } },
+                        "exit_code": { "type": ["integer", "null"] },
+                    },
+                },
+            },
+        })),
+        "status" => json!({ "oneOf": [status, { "type": "array", "items": status }] }),
+        "list" => json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": ["string", "null"] },
+                    "pid": pid,
+                    "alive": { "type": "boolean" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "exit_status": { "type": ["string", "null"] },
+                },
+            },
+        }),
+        "exists" => json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" }, "exists": { "type": "boolean" }, "pid": pid },
+            "required": ["name", "exists"],
+        }),
+        "gc" => json!({
+            "type": "object",
+            "properties": {
+                "dead_entries": { "type": "array" },
+                "kept": { "type": "integer" },
+                "dry_run": { "type": "boolean" },
+            },
+        }),
+        "run" => json!({
+            "type": "object",
+            "properties": {
+                "success": { "type": "boolean" },
+                "stdout": { "type": "string" },
+                "stderr": { "type": "string" },
+                "exit_code": { "type": "integer" },
+                "timed_out": { "type": "boolean" },
+                "duration_ms": { "type": "number" },
+                "pid": pid,
+            },
+        }),
+        "tree" | "ports" | "describe" => json!({ "type": ["object", "array"] }),
+        "stream" | "sample" | "logs" | "attach" | "proxy" => {
+            return json!({ "stream": true, "description": "NDJSON or text written as it is produced" })
+        }
+        "serve" | "daemon" | "mcp" => {
+            return json!({ "stream": true, "description": "Newline-delimited JSON-RPC 2.0 messages" })
+        }
+        _ => json!({ "type": "object" }),
+    };
+    json!({ "stream": false, "schema": { "anyOf": [document, error] } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_visible_subcommand_is_described_with_typed_arguments() {
+        let command = super::super::command();
+        let description = describe(&command);
+        assert_eq!(description["global_arguments"][0]["name"], "registry");
+        let commands = description["commands"].as_array().unwrap();
+        assert_eq!(
+            commands.len(),
+            command
+                .get_subcommands()
+                .filter(|sub| !sub.is_hide_set())
+                .count()
+        );
+
+        let spawn = commands.iter().find(|c| c["name"] == "spawn").unwrap();
+        assert_eq!(spawn["input"]["required"], json!(["cmd"]));
+        assert_eq!(spawn["input"]["properties"]["arg"]["type"], "array");
+        assert_eq!(
+            spawn["input"]["properties"]["stdin-pipe"]["type"],
+            "boolean"
+        );
+
+        let kill = commands.iter().find(|c| c["name"] == "kill").unwrap();
+        assert_eq!(kill["input"]["properties"]["grace"]["type"], "number");
+        assert_eq!(kill["input"]["properties"]["pid"]["type"], "integer");
+
+        let on_exit = commands.iter().find(|c| c["name"] == "on-exit").unwrap();
+        assert!(on_exit["input"]["properties"].get("foreground").is_none());
+    }
+}
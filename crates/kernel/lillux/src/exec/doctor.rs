@@ -0,0 +1,344 @@
+//! `lillux exec doctor`: why a command would fail to spawn.
+//!
+//! Checks, in the order a spawn would trip over them: how `--cmd` resolves
+//! on `PATH` (the one from `--env PATH=...` when given, as spawn uses),
+//! whether the file is executable, the interpreter named by a `#!` line,
+//! whether a binary is built for this machine, shared libraries the
+//! dynamic loader cannot find (`ldd`, Linux), and whether `--log` can be
+//! created. Every failed check adds a line to `problems`.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+pub fn run(cmd: &str, envs: &[String], log: Option<&str>) -> Value {
+    let mut problems = Vec::new();
+    let search_path = envs
+        .iter()
+        .rev()
+        .find_map(|env| env.strip_prefix("PATH="))
+        .map(str::to_string)
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+    let matches = resolve(cmd, &search_path);
+    let mut report = json!({
+        "cmd": cmd,
+        "resolved": matches.first().map(|path| path.display().to_string()),
+    });
+    if !has_separator(cmd) {
+        report["path"] = search_path.clone().into();
+        // Later matches are shadowed by the first; listing them explains
+        // "but it works in my shell" when the shell's PATH differs.
+        report["path_matches"] = matches
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .into();
+    }
+    match matches.first() {
+        None if has_separator(cmd) => problems.push(format!("{cmd} does not exist")),
+        None => problems.push(format!("{cmd} was not found on PATH")),
+        Some(path) => inspect(path, &search_path, &mut report, &mut problems),
+    }
+    if let Some(log) = log {
+        report["log"] = check_log(Path::new(log), &mut problems);
+    }
+    report["ok"] = problems.is_empty().into();
+    report["problems"] = problems.into();
+    report
+}
+
+fn has_separator(cmd: &str) -> bool {
+    cmd.contains('/') || (cfg!(windows) && cmd.contains('\\'))
+}
+
+/// Every file `cmd` names on `search_path`, in lookup order; just `cmd`
+/// itself when it is a path.
+fn resolve(cmd: &str, search_path: &str) -> Vec<PathBuf> {
+    if has_separator(cmd) {
+        let path = PathBuf::from(cmd);
+        return if path.exists() {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+    let extensions: Vec<String> = if cfg!(windows) {
+        std::env::var("PATHEXT")
+            .unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .map(str::to_string)
+            .chain(std::iter::once(String::new()))
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+    std::env::split_paths(search_path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{cmd}{ext}")))
+        })
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Permissions, format, interpreter, and libraries of the resolved file.
+fn inspect(path: &Path, search_path: &str, report: &mut Value, problems: &mut Vec<String>) {
+    let shown = path.display().to_string();
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) => {
+            problems.push(format!("Failed to stat {shown}: {e}"));
+            return;
+        }
+    };
+    if meta.is_dir() {
+        problems.push(format!("{shown} is a directory"));
+        return;
+    }
+    let executable = is_executable(path);
+    report["permissions"] = permissions(&meta, executable);
+    if !executable {
+        problems.push(format!("{shown} is not executable by this user"));
+    }
+    let mut head = [0u8; 256];
+    let read = std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read(&mut file, &mut head))
+        .unwrap_or(0);
+    let head = &head[..read];
+    if let Some(line) = head.strip_prefix(b"#!") {
+        report["format"] = "script".into();
+        let line = String::from_utf8_lossy(line.split(|b| *b == b'\n').next().unwrap_or_default());
+        let mut words = line.split_whitespace();
+        let Some(interpreter) = words.next() else {
+            problems.push(format!("{shown} has an empty #! line"));
+            return;
+        };
+        // `#!/usr/bin/env python3` runs the first python3 on PATH.
+        let via_env = interpreter.ends_with("/env");
+        let program = match words.next() {
+            Some(program) if via_env => program,
+            _ => interpreter,
+        };
+        let found = if via_env {
+            resolve(program, search_path).into_iter().next()
+        } else {
+            Some(PathBuf::from(program)).filter(|path| path.exists())
+        };
+        report["interpreter"] = json!({
+            "shebang": line.trim(),
+            "resolved": found.as_ref().map(|path| path.display().to_string()),
+        });
+        match found {
+            None => problems.push(format!("Interpreter {program} named by #! was not found")),
+            Some(found) if !is_executable(&found) => {
+                problems.push(format!("Interpreter {} is not executable", found.display()))
+            }
+            Some(_) => {}
+        }
+        if line.ends_with('\r') {
+            problems.push(format!(
+                "{shown} has a CRLF #! line; the interpreter name ends in \\r"
+            ));
+        }
+        return;
+    }
+    let (format, arch) = binary_format(head);
+    report["format"] = format.into();
+    report["host_arch"] = std::env::consts::ARCH.into();
+    if let Some(arch) = arch {
+        report["arch"] = arch.into();
+        if arch != std::env::consts::ARCH {
+            problems.push(format!(
+                "{shown} is built for {arch}, this machine is {}",
+                std::env::consts::ARCH
+            ));
+        }
+    }
+    if format == "elf" {
+        match missing_libraries(path) {
+            Some(missing) => {
+                for library in &missing {
+                    problems.push(format!("Shared library {library} was not found"));
+                }
+                report["missing_libraries"] = missing.into();
+            }
+            None => report["missing_libraries"] = Value::Null,
+        }
+    }
+}
+
+/// The executable format and, where the header says, the architecture in
+/// `std::env::consts::ARCH` terms.
+fn binary_format(head: &[u8]) -> (&'static str, Option<&'static str>) {
+    if head.starts_with(b"\x7fELF") && head.len() >= 20 {
+        let little = head[5] == 1;
+        let machine = if little {
+            u16::from_le_bytes([head[18], head[19]])
+        } else {
+            u16::from_be_bytes([head[18], head[19]])
+        };
+        let arch = match machine {
+            0x03 => Some("x86"),
+            0x3e => Some("x86_64"),
+            0x28 => Some("arm"),
+            0xb7 => Some("aarch64"),
+            0xf3 => Some(if head[4] == 2 { "riscv64" } else { "riscv32" }),
+            0x15 => Some("powerpc64"),
+            0x16 => Some("s390x"),
+            _ => None,
+        };
+        return ("elf", arch);
+    }
+    if head.len() >= 8 {
+        let magic = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+        let cpu = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+        match magic {
+            0xfeedfacf | 0xfeedface => {
+                let arch = match cpu {
+                    0x0100_0007 => Some("x86_64"),
+                    0x0100_000c => Some("aarch64"),
+                    _ => None,
+                };
+                return ("mach-o", arch);
+            }
+            // Fat binaries carry several architectures; the loader picks one.
+            0xbebafeca => return ("mach-o-universal", None),
+            _ => {}
+        }
+    }
+    if head.starts_with(b"MZ") {
+        return ("pe", None);
+    }
+    ("unknown", None)
+}
+
+/// Libraries `ldd` reports as `not found`; `None` when `ldd` cannot say.
+#[cfg(target_os = "linux")]
+fn missing_libraries(path: &Path) -> Option<Vec<String>> {
+    let output = std::process::Command::new("ldd").arg(path).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() && !stdout.contains("not found") {
+        // Static binaries: "not a dynamic executable".
+        return Some(Vec::new());
+    }
+    Some(
+        stdout
+            .lines()
+            .filter(|line| line.contains("=> not found"))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn missing_libraries(_path: &Path) -> Option<Vec<String>> {
+    None
+}
+
+/// Whether `--log` exists or can be created, and is writable.
+fn check_log(log: &Path, problems: &mut Vec<String>) -> Value {
+    let shown = log.display().to_string();
+    let dir = match log.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let writable = if log.exists() {
+        is_writable(log)
+    } else if dir.is_dir() {
+        is_writable(&dir)
+    } else {
+        problems.push(format!("Log directory {} does not exist", dir.display()));
+        return json!({ "path": shown, "directory": dir.display().to_string(), "writable": false });
+    };
+    if !writable {
+        problems.push(format!("Log {shown} is not writable by this user"));
+    }
+    json!({ "path": shown, "directory": dir.display().to_string(), "writable": writable })
+}
+
+#[cfg(unix)]
+fn permissions(meta: &std::fs::Metadata, executable: bool) -> Value {
+    use std::os::unix::fs::MetadataExt;
+    json!({
+        "mode": format!("{:o}", meta.mode() & 0o7777),
+        "uid": meta.uid(),
+        "gid": meta.gid(),
+        "executable": executable,
+    })
+}
+
+#[cfg(not(unix))]
+fn permissions(meta: &std::fs::Metadata, executable: bool) -> Value {
+    json!({ "readonly": meta.permissions().readonly(), "executable": executable })
+}
+
+#[cfg(unix)]
+fn access(path: &Path, mode: i32) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    access(path, libc::X_OK)
+}
+
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    access(path, libc::W_OK)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| !meta.permissions().readonly())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_missing_interpreters_and_unwritable_log_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let script = tmp.path().join("tool");
+        std::fs::write(&script, "#!/nonexistent/python3\nprint()\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let envs = [format!("PATH={}", tmp.path().display())];
+
+        let report = run("tool", &envs, Some("/nonexistent/dir/tool.log"));
+        assert_eq!(report["resolved"], script.display().to_string());
+        assert_eq!(report["format"], "script");
+        assert_eq!(report["interpreter"]["resolved"], Value::Null);
+        assert_eq!(report["log"]["writable"], false);
+        assert_eq!(report["problems"].as_array().unwrap().len(), 2);
+
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        let log = tmp.path().join("tool.log");
+        let report = run("tool", &envs, log.to_str());
+        assert_eq!(report["ok"], true, "{report}");
+
+        let report = run("missing-tool", &envs, None);
+        assert_eq!(report["ok"], false);
+        assert_eq!(report["path_matches"], json!([]));
+    }
+
+    #[test]
+    fn elf_headers_name_their_architecture() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        assert_eq!(binary_format(&exe[..64]).1, Some(std::env::consts::ARCH));
+    }
+}
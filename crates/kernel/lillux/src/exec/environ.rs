@@ -0,0 +1,203 @@
+//! `lillux exec env`: capture the environment a process was or will be
+//! given, and compare two captures.
+//!
+//! Spawned processes start from an empty environment plus their `--env`
+//! pairs, which is rarely what the caller's shell has. A snapshot of a
+//! registered process (`--name`), a live one (`--pid`, Linux), the pairs a
+//! spawn would pass (`--env`), or this shell (no source) can be diffed
+//! against a known-good baseline to see exactly what differs.
+
+use std::collections::BTreeMap;
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::SysError;
+
+#[derive(Subcommand)]
+pub enum EnvAction {
+    /// Capture an environment as JSON
+    Snapshot {
+        /// The environment the registered process was spawned with
+        #[arg(long, conflicts_with_all = ["pid", "envs"])]
+        name: Option<String>,
+        /// The environment a live process started with (Linux)
+        #[arg(long, conflicts_with = "envs")]
+        pid: Option<u32>,
+        /// Exactly what `spawn --env` with these pairs would pass; repeatable
+        #[arg(long = "env")]
+        envs: Vec<String>,
+        /// Write the snapshot here instead of printing it
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Compare two snapshots: variables only in B are `added`
+    Diff { a: String, b: String },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Where the variables came from: `name:<name>`, `pid:<pid>`, `args`,
+    /// or `shell`.
+    pub source: String,
+    pub captured_at: String,
+    pub env: BTreeMap<String, String>,
+}
+
+pub fn run(registry: Option<&str>, action: EnvAction) -> Value {
+    match action {
+        EnvAction::Snapshot {
+            name,
+            pid,
+            envs,
+            out,
+        } => {
+            let snapshot = match capture(registry, name, pid, envs) {
+                Ok(snapshot) => snapshot,
+                Err(e) => return e.failed(),
+            };
+            let Some(out) = out else {
+                return serde_json::to_value(snapshot).unwrap_or_default();
+            };
+            let body = serde_json::to_string_pretty(&snapshot).unwrap_or_default();
+            match write_private(&out, &(body + "\n")) {
+                Ok(()) => json!({
+                    "success": true,
+                    "out": out,
+                    "source": snapshot.source,
+                    "variables": snapshot.env.len(),
+                }),
+                Err(e) => {
+                    json!({ "success": false, "error": format!("Failed to write {out}: {e}") })
+                }
+            }
+        }
+        EnvAction::Diff { a, b } => match (load(&a), load(&b)) {
+            (Ok(a), Ok(b)) => diff(&a, &b),
+            (Err(e), _) | (_, Err(e)) => json!({ "success": false, "error": e }),
+        },
+    }
+}
+
+fn capture(
+    registry: Option<&str>,
+    name: Option<String>,
+    pid: Option<u32>,
+    envs: Vec<String>,
+) -> Result<Snapshot, SysError> {
+    let (source, pairs) = if let Some(name) = name {
+        let entry = super::named_entry(registry, &name)?;
+        (format!("name:{name}"), entry.envs)
+    } else if let Some(pid) = pid {
+        (format!("pid:{pid}"), process_environ(pid)?)
+    } else if !envs.is_empty() {
+        ("args".to_string(), envs)
+    } else {
+        let shell = std::env::vars_os()
+            .map(|(k, v)| format!("{}={}", k.to_string_lossy(), v.to_string_lossy()))
+            .collect();
+        ("shell".to_string(), shell)
+    };
+    Ok(Snapshot {
+        source,
+        captured_at: crate::time::iso8601_now(),
+        env: to_map(&pairs),
+    })
+}
+
+/// Environments hold secrets: the snapshot is readable by its owner only.
+fn write_private(path: &str, body: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(body.as_bytes())
+}
+
+/// `KEY=VALUE` pairs as a map; a later pair for a key wins, as in spawn.
+fn to_map(pairs: &[String]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn process_environ(pid: u32) -> Result<Vec<String>, String> {
+    let raw = std::fs::read(format!("/proc/{pid}/environ"))
+        .map_err(|e| format!("Failed to read the environment of pid {pid}: {e}"))?;
+    Ok(raw
+        .split(|byte| *byte == 0)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| String::from_utf8_lossy(pair).into_owned())
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_environ(_pid: u32) -> Result<Vec<String>, String> {
+    Err("Reading another process's environment is only supported on Linux".to_string())
+}
+
+/// A snapshot file, or a bare `{"KEY": "VALUE"}` object for hand-written
+/// baselines.
+fn load(path: &str) -> Result<BTreeMap<String, String>, String> {
+    let raw = std::fs::read(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    if let Ok(snapshot) = serde_json::from_slice::<Snapshot>(&raw) {
+        return Ok(snapshot.env);
+    }
+    serde_json::from_slice(&raw).map_err(|e| format!("{path} is not an environment snapshot: {e}"))
+}
+
+pub fn diff(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>) -> Value {
+    let mut added = BTreeMap::new();
+    let mut removed = BTreeMap::new();
+    let mut changed = BTreeMap::new();
+    let mut unchanged = 0;
+    for (key, value) in b {
+        match a.get(key) {
+            None => {
+                added.insert(key, value);
+            }
+            Some(old) if old != value => {
+                changed.insert(key, json!({ "a": old, "b": value }));
+            }
+            Some(_) => unchanged += 1,
+        }
+    }
+    for (key, value) in a {
+        if !b.contains_key(key) {
+            removed.insert(key, value);
+        }
+    }
+    json!({
+        "identical": added.is_empty() && removed.is_empty() && changed.is_empty(),
+        "added": added,
+        "removed": removed,
+        "changed": changed,
+        "unchanged": unchanged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_variables() {
+        let a = to_map(&["PATH=/bin".into(), "HOME=/root".into(), "LANG=C".into()]);
+        let b = to_map(&["PATH=/usr/bin".into(), "LANG=C".into(), "TERM=xterm".into()]);
+        let report = diff(&a, &b);
+        assert_eq!(report["identical"], false);
+        assert_eq!(report["added"], json!({ "TERM": "xterm" }));
+        assert_eq!(report["removed"], json!({ "HOME": "/root" }));
+        assert_eq!(
+            report["changed"],
+            json!({ "PATH": { "a": "/bin", "b": "/usr/bin" } })
+        );
+        assert_eq!(report["unchanged"], 1);
+        assert_eq!(diff(&a, &a)["identical"], true);
+    }
+}
@@ -0,0 +1,86 @@
+//! Named FIFOs that feed a detached child's stdin after spawn
+//! (`spawn --stdin-fifo`, `lillux exec send`).
+
+use std::fs::File;
+
+use super::sys_error::{ErrorCode, SysError};
+
+/// Create the FIFO at `path` (or reuse an existing one) and open it as the
+/// child's stdin.
+///
+/// The FIFO is opened read-write so the open does not block waiting for a
+/// writer and the child never sees end-of-file between `send` calls.
+#[cfg(unix)]
+pub fn create(path: &str) -> Result<File, String> {
+    use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_fifo() => {}
+        Ok(_) => return Err(format!("{path} exists and is not a FIFO")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let c_path = std::ffi::CString::new(path)
+                .map_err(|_| format!("FIFO path contains a NUL byte: {path}"))?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(format!(
+                    "Failed to create FIFO {path}: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+        Err(e) => return Err(format!("Failed to inspect {path}: {e}")),
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|e| format!("Failed to open FIFO {path}: {e}"))
+}
+
+#[cfg(not(unix))]
+pub fn create(_path: &str) -> Result<File, String> {
+    Err("--stdin-fifo is not supported on this platform".to_string())
+}
+
+/// Write `data` to the FIFO at `path`. Fails instead of blocking when no
+/// process holds the read end.
+#[cfg(unix)]
+pub fn send(path: &str, data: &[u8]) -> Result<(), SysError> {
+    use std::io::Write;
+    use std::os::unix::fs::{FileTypeExt, OpenOptionsExt};
+
+    let meta = std::fs::symlink_metadata(path)
+        .map_err(|e| SysError::io(format!("Failed to open {path}"), "lstat", &e).path(path))?;
+    if !meta.file_type().is_fifo() {
+        return Err(
+            SysError::new(ErrorCode::InvalidArgument, format!("{path} is not a FIFO")).path(path),
+        );
+    }
+    let mut fifo = std::fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ENXIO) => {
+                SysError::new(ErrorCode::NotFound, format!("No process is reading {path}"))
+                    .path(path)
+            }
+            _ => SysError::io(format!("Failed to open {path}"), "open", &e).path(path),
+        })?;
+    // Writes to a full pipe would fail with EAGAIN; block for them instead.
+    let fd = std::os::fd::AsRawFd::as_raw_fd(&fifo);
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK);
+    }
+    fifo.write_all(data)
+        .map_err(|e| SysError::io(format!("Failed to write to {path}"), "write", &e).path(path))
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &str, _data: &[u8]) -> Result<(), SysError> {
+    Err(SysError::new(
+        ErrorCode::Unsupported,
+        "lillux exec send is not supported on this platform",
+    ))
+}
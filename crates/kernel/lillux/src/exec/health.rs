@@ -0,0 +1,434 @@
+//! Health checks for registered processes (`spawn --health-*`).
+//!
+//! The check is recorded with the process and evaluated by `exec daemon`,
+//! which runs one monitor thread per live checked process. After
+//! `retries` consecutive failures the process is unhealthy and the
+//! configured action runs: restart it, kill it, or run a hook. The latest
+//! state is written back to the registry for `status` and `list`.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{self, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::registry::{Registry, RegistryEntry};
+use super::serve::Server;
+
+/// How often the daemon looks for newly spawned checked processes.
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Grace given to an unhealthy process before SIGKILL.
+const KILL_GRACE: f64 = 3.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Probe {
+    /// Shell command; exit status 0 is healthy.
+    Cmd { command: String },
+    /// `http://` URL answering 2xx or 3xx.
+    Http { url: String },
+    /// `HOST:PORT` accepting a TCP connection.
+    Tcp { address: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthAction {
+    /// Only record the state
+    None,
+    /// Stop the process and spawn it again
+    Restart,
+    /// Stop the process
+    Kill,
+    /// Run `--health-hook`
+    Hook,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub probe: Probe,
+    pub interval_ms: u64,
+    pub timeout_ms: u64,
+    /// Consecutive failures before the process counts as unhealthy.
+    pub retries: u32,
+    pub action: HealthAction,
+    #[serde(default)]
+    pub hook: Option<String>,
+}
+
+/// The outcome of the checks so far.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthState {
+    /// `starting` until the first check, then `healthy` or `unhealthy`.
+    pub status: String,
+    pub failures: u32,
+    #[serde(default)]
+    pub checked_at: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// Times the process has been restarted by its health action.
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// The `--health-*` flags of `exec spawn`.
+#[derive(clap::Args, Debug)]
+pub struct HealthArgs {
+    /// Shell command whose exit status 0 means healthy; evaluated by
+    /// `exec daemon`
+    #[arg(long, conflicts_with_all = ["health_http", "health_tcp"])]
+    pub health_cmd: Option<String>,
+    /// `http://` URL that must answer with a 2xx or 3xx status
+    #[arg(long, conflicts_with = "health_tcp")]
+    pub health_http: Option<String>,
+    /// `HOST:PORT` that must accept a TCP connection
+    #[arg(long)]
+    pub health_tcp: Option<String>,
+    #[arg(long, default_value = "10s")]
+    pub health_interval: String,
+    #[arg(long, default_value = "5s")]
+    pub health_timeout: String,
+    /// Consecutive failures before the process is unhealthy
+    #[arg(long, default_value_t = 3)]
+    pub health_retries: u32,
+    /// What to do once the process is unhealthy
+    #[arg(long, value_enum, default_value_t = HealthAction::None)]
+    pub health_action: HealthAction,
+    /// Shell command run with `--health-action hook`; it receives
+    /// `LILLUX_HEALTH_NAME`, `LILLUX_HEALTH_PID`, and `LILLUX_HEALTH_ERROR`
+    #[arg(long, required_if_eq("health_action", "hook"))]
+    pub health_hook: Option<String>,
+}
+
+impl HealthArgs {
+    /// The configured check, or `None` without a `--health-*` probe.
+    pub fn into_check(self) -> Result<Option<HealthCheck>, String> {
+        let probe = match (self.health_cmd, self.health_http, self.health_tcp) {
+            (Some(command), _, _) => Probe::Cmd { command },
+            (_, Some(url), _) => {
+                parse_http_url(&url)?;
+                Probe::Http { url }
+            }
+            (_, _, Some(address)) => Probe::Tcp { address },
+            _ => return Ok(None),
+        };
+        let millis = |raw: &str| {
+            crate::time::parse_duration(raw).map(|duration| duration.as_millis() as u64)
+        };
+        Ok(Some(HealthCheck {
+            probe,
+            interval_ms: millis(&self.health_interval)?.max(1),
+            timeout_ms: millis(&self.health_timeout)?,
+            retries: self.health_retries.max(1),
+            action: self.health_action,
+            hook: self.health_hook,
+        }))
+    }
+}
+
+/// Monitor every live checked process until the daemon exits. Runs on its
+/// own thread; `server` spawns restarted processes so it reaps them.
+pub fn run(server: &Arc<Server>) {
+    let monitored: Arc<Mutex<HashSet<i64>>> = Arc::default();
+    loop {
+        let entries = Registry::locate(server.registry()).and_then(|r| r.entries());
+        for entry in entries.unwrap_or_default() {
+            if entry.health.is_none() || !entry.is_live() {
+                continue;
+            }
+            if !lock(&monitored).insert(entry.id) {
+                continue;
+            }
+            let server = Arc::clone(server);
+            let monitored = Arc::clone(&monitored);
+            std::thread::spawn(move || {
+                let id = entry.id;
+                monitor(&server, entry);
+                lock(&monitored).remove(&id);
+            });
+        }
+        std::thread::sleep(DISCOVERY_INTERVAL);
+    }
+}
+
+/// Check `entry` every interval until it exits or its action replaces it.
+fn monitor(server: &Arc<Server>, entry: RegistryEntry) {
+    let Some(check) = entry.health.clone() else {
+        return;
+    };
+    let Ok(registry) = Registry::locate(server.registry()) else {
+        return;
+    };
+    let mut state = entry.health_state.clone().unwrap_or_else(|| HealthState {
+        status: "starting".to_string(),
+        ..HealthState::default()
+    });
+    let _ = registry.update_health(entry.id, &state);
+    loop {
+        std::thread::sleep(Duration::from_millis(check.interval_ms));
+        if !registry
+            .find_pid(entry.pid)
+            .ok()
+            .flatten()
+            .is_some_and(|current| current.id == entry.id && current.is_live())
+        {
+            return;
+        }
+        let outcome = probe(&check.probe, Duration::from_millis(check.timeout_ms));
+        state.checked_at = Some(crate::time::iso8601_now());
+        match outcome {
+            Ok(()) => {
+                state.failures = 0;
+                state.status = "healthy".to_string();
+                state.last_error = None;
+            }
+            Err(e) => {
+                state.failures += 1;
+                state.last_error = Some(e);
+                if state.failures >= check.retries {
+                    state.status = "unhealthy".to_string();
+                }
+            }
+        }
+        let _ = registry.update_health(entry.id, &state);
+        // Act once per run of failures, not on every failing check.
+        if state.failures == check.retries && act(server, &registry, &entry, &check, &state) {
+            return;
+        }
+    }
+}
+
+/// Run the unhealthy action; `true` when the process is gone as a result.
+fn act(
+    server: &Arc<Server>,
+    registry: &Registry,
+    entry: &RegistryEntry,
+    check: &HealthCheck,
+    state: &HealthState,
+) -> bool {
+    match check.action {
+        HealthAction::None => false,
+        HealthAction::Hook => {
+            if let Some(hook) = &check.hook {
+                let mut command = shell(hook);
+                command
+                    .env(
+                        "LILLUX_HEALTH_NAME",
+                        entry.name.as_deref().unwrap_or_default(),
+                    )
+                    .env("LILLUX_HEALTH_PID", entry.pid.to_string())
+                    .env(
+                        "LILLUX_HEALTH_ERROR",
+                        state.last_error.as_deref().unwrap_or_default(),
+                    );
+                let _ = run_with_timeout(command, Duration::from_millis(check.timeout_ms));
+            }
+            false
+        }
+        HealthAction::Kill => {
+            super::kill_registered(server.registry(), Some(entry.pid), None, KILL_GRACE, true);
+            true
+        }
+        HealthAction::Restart => {
+            super::kill_registered(server.registry(), Some(entry.pid), None, KILL_GRACE, true);
+            let mut template = entry.clone();
+            template.id = 0;
+            template.exit_status = None;
+            template.ended_at = None;
+            template.health_state = None;
+            let result = server.spawn_entry(template, None, &Default::default());
+            let pid = result.get("pid").and_then(Value::as_u64);
+            if let Some(Ok(Some(respawned))) = pid.map(|pid| registry.find_pid(pid as u32)) {
+                let _ = registry.update_health(
+                    respawned.id,
+                    &HealthState {
+                        status: "starting".to_string(),
+                        restarts: state.restarts + 1,
+                        ..HealthState::default()
+                    },
+                );
+            }
+            true
+        }
+    }
+}
+
+/// One check; `Err` carries why it failed.
+pub fn probe(probe: &Probe, timeout: Duration) -> Result<(), String> {
+    match probe {
+        Probe::Cmd { command } => match run_with_timeout(shell(command), timeout)? {
+            0 => Ok(()),
+            code => Err(format!("Health command exited with {code}")),
+        },
+        Probe::Tcp { address } => connect(address, timeout).map(drop),
+        Probe::Http { url } => {
+            let (authority, path) = parse_http_url(url)?;
+            let mut stream = connect(&authority, timeout)?;
+            let _ = stream.set_read_timeout(Some(timeout));
+            let _ = stream.set_write_timeout(Some(timeout));
+            let host = authority
+                .rsplit_once(':')
+                .map_or(authority.as_str(), |(host, _)| host);
+            write!(
+                stream,
+                "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: lillux\r\nConnection: close\r\n\r\n"
+            )
+            .map_err(|e| format!("Failed to send request to {url}: {e}"))?;
+            let mut head = [0u8; 32];
+            let read = stream
+                .read(&mut head)
+                .map_err(|e| format!("No response from {url}: {e}"))?;
+            let status = String::from_utf8_lossy(&head[..read])
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| format!("Malformed HTTP response from {url}"))?;
+            match status {
+                200..=399 => Ok(()),
+                _ => Err(format!("{url} answered {status}")),
+            }
+        }
+    }
+}
+
+/// `host:port` (port defaulting to 80) and path of an `http://` URL.
+fn parse_http_url(url: &str) -> Result<(String, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("--health-http only supports http:// URLs: {url}"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(format!("--health-http URL has no host: {url}"));
+    }
+    let authority = match authority.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => authority.to_string(),
+        _ => format!("{authority}:80"),
+    };
+    Ok((authority, path.to_string()))
+}
+
+fn connect(address: &str, timeout: Duration) -> Result<TcpStream, String> {
+    let addrs = address
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve {address}: {e}"))?;
+    let mut last = format!("Cannot resolve {address}");
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = format!("Failed to connect to {address}: {e}"),
+        }
+    }
+    Err(last)
+}
+
+fn shell(command: &str) -> process::Command {
+    let (program, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("/bin/sh", "-c")
+    };
+    let mut shell = process::Command::new(program);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Run `command` with no stdio, killing it after `timeout`.
+fn run_with_timeout(mut command: process::Command, timeout: Duration) -> Result<i32, String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run health command: {e}"))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status.code().unwrap_or(-1)),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Health command timed out after {timeout:?}"));
+            }
+            Err(e) => return Err(format!("Failed to wait for health command: {e}")),
+        }
+    }
+}
+
+/// `status`/`list` view of an entry's health, if it has a check.
+pub fn describe(entry: &RegistryEntry) -> Option<Value> {
+    let check = entry.health.as_ref()?;
+    let state = entry.health_state.clone().unwrap_or_else(|| HealthState {
+        status: "starting".to_string(),
+        ..HealthState::default()
+    });
+    let mut health = serde_json::to_value(state).unwrap_or_default();
+    health["probe"] = json!(check.probe);
+    Some(health)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_report_success_and_failure() {
+        let timeout = Duration::from_secs(2);
+        let ok = Probe::Cmd {
+            command: "exit 0".to_string(),
+        };
+        assert_eq!(probe(&ok, timeout), Ok(()));
+        let failing = Probe::Cmd {
+            command: "exit 3".to_string(),
+        };
+        assert_eq!(
+            probe(&failing, timeout),
+            Err("Health command exited with 3".to_string())
+        );
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 256];
+            let _ = stream.read(&mut request);
+            stream
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n")
+                .unwrap();
+        });
+        let http = Probe::Http {
+            url: format!("http://{address}/healthz"),
+        };
+        assert_eq!(
+            probe(&http, timeout),
+            Err(format!("http://{address}/healthz answered 503"))
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn http_urls_default_to_port_80_and_root_path() {
+        assert_eq!(
+            parse_http_url("http://localhost").unwrap(),
+            ("localhost:80".to_string(), "/".to_string())
+        );
+        assert_eq!(
+            parse_http_url("http://127.0.0.1:8080/ready?x=1").unwrap(),
+            ("127.0.0.1:8080".to_string(), "/ready?x=1".to_string())
+        );
+        assert!(parse_http_url("https://example.com").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! launchd property lists for `lillux exec export launchd`.
+
+use super::registry::RegistryEntry;
+
+/// A LaunchAgent/LaunchDaemon plist that runs `entry`'s command with the
+/// same environment and log file. `keep_alive` has launchd restart the
+/// program whenever it exits.
+pub fn render_plist(entry: &RegistryEntry, label: &str, keep_alive: bool) -> String {
+    let mut plist = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+        "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n",
+    ));
+    plist.push_str(&key_string("Label", label));
+    plist.push_str("  <key>ProgramArguments</key>\n  <array>\n");
+    for word in std::iter::once(&entry.cmd).chain(&entry.args) {
+        plist.push_str(&format!("    <string>{}</string>\n", escape(word)));
+    }
+    plist.push_str("  </array>\n");
+    if !entry.envs.is_empty() {
+        plist.push_str("  <key>EnvironmentVariables</key>\n  <dict>\n");
+        for env in &entry.envs {
+            let (key, value) = env.split_once('=').unwrap_or((env, ""));
+            plist.push_str(&format!(
+                "    <key>{}</key>\n    <string>{}</string>\n",
+                escape(key),
+                escape(value)
+            ));
+        }
+        plist.push_str("  </dict>\n");
+    }
+    if let Some(log) = &entry.log {
+        plist.push_str(&key_string("StandardOutPath", log));
+        plist.push_str(&key_string("StandardErrorPath", log));
+    }
+    plist.push_str("  <key>RunAtLoad</key>\n  <true/>\n");
+    plist.push_str(&format!(
+        "  <key>KeepAlive</key>\n  <{}/>\n",
+        if keep_alive { "true" } else { "false" }
+    ));
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+fn key_string(key: &str, value: &str) -> String {
+    format!("  <key>{key}</key>\n  <string>{}</string>\n", escape(value))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plist_carries_arguments_environment_and_log() {
+        let entry = RegistryEntry {
+            id: 1,
+            pid: 42,
+            name: Some("web".to_string()),
+            cmd: "/usr/bin/server".to_string(),
+            args: vec!["--motd".to_string(), "a < b & c".to_string()],
+            log: Some("/tmp/web.log".to_string()),
+            envs: vec!["HOME=/srv/web".to_string()],
+            ..Default::default()
+        };
+        let plist = render_plist(&entry, "lillux.web", true);
+        assert!(plist.contains("<key>Label</key>\n  <string>lillux.web</string>\n"));
+        assert!(plist.contains("<string>a &lt; b &amp; c</string>\n"));
+        assert!(plist.contains("<key>HOME</key>\n    <string>/srv/web</string>\n"));
+        assert!(plist.contains("<key>StandardErrorPath</key>\n  <string>/tmp/web.log</string>\n"));
+        assert!(plist.contains("<key>KeepAlive</key>\n  <true/>\n"));
+    }
+}
@@ -0,0 +1,288 @@
+//! `lillux exec lock`: named advisory locks for serializing work across
+//! sessions.
+//!
+//! A lock is an [`ExclusiveFileLock`] on `locks/<name>` beside the
+//! registry, so the kernel releases it when its holder dies, however that
+//! happens. `with` holds it for the life of one command. `acquire` hands it
+//! to a detached holder (this binary re-executed with `--foreground`) that
+//! keeps it until `release` stops the holder. While held, `locks/<name>`
+//! records who holds it so `release` can find the holder.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::time::Duration;
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::registry::Registry;
+use super::sys_error::{ErrorCode, SysError};
+use crate::locks::{ExclusiveFileLock, LockTimeout};
+
+#[derive(Subcommand)]
+pub enum LockAction {
+    /// Take the lock and keep it, from a detached holder, until `release`
+    Acquire {
+        #[arg(long)]
+        name: String,
+        /// Give up after this long (e.g. `30s`); waits indefinitely when
+        /// omitted
+        #[arg(long)]
+        timeout: Option<String>,
+        /// Hold the lock in this process instead of detaching a holder
+        #[arg(long, hide = true)]
+        foreground: bool,
+    },
+    /// Release a lock taken with `acquire`
+    Release {
+        #[arg(long)]
+        name: String,
+    },
+    /// Run a command while holding the lock and exit with its status
+    With {
+        #[arg(long)]
+        name: String,
+        /// Give up after this long (e.g. `30s`); waits indefinitely when
+        /// omitted
+        #[arg(long)]
+        timeout: Option<String>,
+        /// The command and its arguments, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+}
+
+/// Contents of a lock file while it is held.
+#[derive(Debug, Serialize, Deserialize)]
+struct Holder {
+    pid: u32,
+    /// `acquire` or `with`.
+    mode: String,
+    acquired_at: String,
+}
+
+pub fn run(registry: Option<&str>, action: LockAction) -> Value {
+    match action {
+        LockAction::Acquire {
+            name,
+            timeout,
+            foreground,
+        } => {
+            let timeout = match timeout.as_deref().map(crate::time::parse_duration) {
+                Some(Ok(timeout)) => Some(timeout),
+                Some(Err(e)) => {
+                    return SysError::new(ErrorCode::InvalidArgument, e)
+                        .failed_with(json!({ "name": name }))
+                }
+                None => None,
+            };
+            if foreground {
+                hold(registry, &name, timeout);
+            }
+            launch(registry, &name, timeout)
+                .unwrap_or_else(|e| SysError::from(e).failed_with(json!({ "name": name })))
+        }
+        LockAction::Release { name } => {
+            release(registry, &name).unwrap_or_else(|e| e.failed_with(json!({ "name": name })))
+        }
+        LockAction::With {
+            name,
+            timeout,
+            command,
+        } => {
+            let locked = timeout
+                .as_deref()
+                .map(crate::time::parse_duration)
+                .transpose()
+                .map_err(|e| SysError::new(ErrorCode::InvalidArgument, e))
+                .and_then(|timeout| lock(registry, &name, timeout, "with"));
+            let _lock = match locked {
+                Ok(lock) => lock,
+                Err(e) => return e.failed_with(json!({ "name": name })),
+            };
+            match process::Command::new(&command[0])
+                .args(&command[1..])
+                .status()
+            {
+                Ok(status) => process::exit(super::pipeline::exit_code(status)),
+                Err(e) => json!({
+                    "success": false,
+                    "name": name,
+                    "error": format!("Failed to run {}: {e}", command[0]),
+                }),
+            }
+        }
+    }
+}
+
+/// `locks/<name>` in the registry's directory.
+fn lock_path(registry: Option<&str>, name: &str) -> Result<PathBuf, String> {
+    Registry::locate(registry)?.state_file("locks", name)
+}
+
+/// Take the lock named `name` for this process, waiting up to `timeout`
+/// (forever when `None`), and record this process as its holder.
+fn lock(
+    registry: Option<&str>,
+    name: &str,
+    timeout: Option<Duration>,
+    mode: &str,
+) -> Result<ExclusiveFileLock, SysError> {
+    let path = lock_path(registry, name)?;
+    let lock = match timeout {
+        None => ExclusiveFileLock::acquire(&path),
+        Some(timeout) => ExclusiveFileLock::acquire_with_timeout(&path, timeout),
+    }
+    .map_err(|e| {
+        let message = format!("Failed to take lock '{name}': {e}");
+        match e.is::<LockTimeout>() {
+            true => SysError::new(ErrorCode::Timeout, message),
+            false => SysError::from(message),
+        }
+    })?;
+    let record = Holder {
+        pid: process::id(),
+        mode: mode.to_string(),
+        acquired_at: crate::time::iso8601_now(),
+    };
+    lock.replace_target(&serde_json::to_vec(&record).unwrap_or_default())
+        .map_err(|e| format!("Failed to record the holder of lock '{name}': {e}"))?;
+    Ok(lock)
+}
+
+/// The recorded holder of a lock file, if it parses.
+fn holder(path: &Path) -> Option<Holder> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+/// Run as the detached holder: take the lock, report to the launcher on
+/// stdout, and keep it until killed.
+fn hold(registry: Option<&str>, name: &str, timeout: Option<Duration>) -> ! {
+    let report = |value: Value| {
+        let mut stdout = std::io::stdout();
+        let _ = writeln!(stdout, "{value}");
+        let _ = stdout.flush();
+    };
+    let _lock = match lock(registry, name, timeout, "acquire") {
+        Ok(lock) => lock,
+        Err(e) => {
+            report(e.failed_with(json!({ "name": name })));
+            process::exit(crate::output::EXIT_FAILED);
+        }
+    };
+    report(json!({ "success": true, "name": name, "pid": process::id() }));
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Start a detached holder for `name` and return its report.
+fn launch(registry: Option<&str>, name: &str, timeout: Option<Duration>) -> Result<Value, String> {
+    let registry = Registry::locate(registry)?;
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let mut command = process::Command::new(exe);
+    command
+        .arg("exec")
+        .arg("--registry")
+        .arg(registry.path())
+        .args(["lock", "acquire", "--name", name, "--foreground"])
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    if let Some(timeout) = timeout {
+        command.args(["--timeout", &format!("{}ms", timeout.as_millis())]);
+    }
+    detach(&mut command);
+    let mut holder = command
+        .spawn()
+        .map_err(|e| format!("Failed to start lock holder: {e}"))?;
+    let mut line = String::new();
+    if let Some(stdout) = holder.stdout.take() {
+        let _ = BufReader::new(stdout).read_line(&mut line);
+    }
+    let report: Value = serde_json::from_str(&line)
+        .map_err(|_| "Lock holder exited before reporting".to_string())?;
+    if report.get("error").is_some() {
+        // Reap it; it has already exited.
+        let _ = holder.wait();
+    }
+    Ok(report)
+}
+
+fn release(registry: Option<&str>, name: &str) -> Result<Value, SysError> {
+    let not_held = || SysError::new(ErrorCode::NotFound, format!("Lock '{name}' is not held"));
+    let path = lock_path(registry, name)?;
+    if !path.exists() {
+        return Err(not_held());
+    }
+    // Taking it without waiting means nobody had it; dropping it lets it go.
+    if ExclusiveFileLock::acquire_with_timeout(&path, Duration::ZERO).is_ok() {
+        return Err(not_held());
+    }
+    match holder(&path) {
+        Some(holder) if holder.mode == "acquire" => {
+            let method = super::kill_process(holder.pid, 3.0)?;
+            Ok(json!({ "success": true, "name": name, "pid": holder.pid, "method": method }))
+        }
+        Some(holder) => Err(format!(
+            "Lock '{name}' is held by `lock {}` (pid {}) and is released when its command exits",
+            holder.mode, holder.pid
+        )
+        .into()),
+        None => Err(format!("Lock '{name}' is held by an unknown process").into()),
+    }
+}
+
+#[cfg(unix)]
+pub(super) fn detach(command: &mut process::Command) {
+    use std::os::unix::process::CommandExt;
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+pub(super) fn detach(command: &mut process::Command) {
+    use std::os::windows::process::CommandExt;
+    command.creation_flags(0x00000200 | 0x00000008); // CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_held_lock_times_out_other_takers_until_dropped() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let registry = tmp.path().join("registry.db");
+        let registry = registry.to_str();
+        let wait = Some(Duration::from_millis(200));
+
+        let held = lock(registry, "build", None, "with").unwrap();
+        let Err(error) = lock(registry, "build", wait, "with") else {
+            panic!("a second holder took the lock");
+        };
+        assert_eq!(error.code, ErrorCode::Timeout);
+        assert!(
+            error
+                .message
+                .contains(&format!("holder pid: {}", process::id())),
+            "{error}"
+        );
+        assert!(release(registry, "build")
+            .unwrap_err()
+            .message
+            .contains("`lock with`"));
+
+        drop(held);
+        assert!(lock(registry, "build", wait, "with").is_ok());
+        assert!(lock(registry, "../build", wait, "with").is_err());
+    }
+}
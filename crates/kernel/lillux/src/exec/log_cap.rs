@@ -0,0 +1,259 @@
+//! `spawn --log-max-bytes N`: a hard cap on the size of a process's log.
+//!
+//! The child's stdout and stderr go into a pipe instead of the file, and a
+//! detached `lillux exec log-writer` copies the pipe into the log, keeping
+//! it under the cap. With `--log-overflow stop` (the default) the writer
+//! stops at the cap, appends a marker line, and discards the rest; with
+//! `ring` it drops the oldest half of the log instead, leaving a marker
+//! that counts what was dropped at the top. Either way the child never
+//! blocks on a full pipe. The writer exits once every holder of the pipe
+//! (the child and anything it started) has closed it.
+//!
+//! The writer appends, and reads the log's length afresh for each write,
+//! so `reload --rotate-log` truncating the file underneath it starts a
+//! fresh allowance.
+
+use std::fs::File;
+use std::io::{PipeWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::SysError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Keep the beginning of the log; drop output past the cap
+    Stop,
+    /// Keep the end of the log; drop its oldest half when full
+    Ring,
+}
+
+impl Overflow {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Overflow::Stop => "stop",
+            Overflow::Ring => "ring",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogCap {
+    pub max_bytes: u64,
+    pub overflow: Overflow,
+}
+
+/// The smallest cap accepted, so a marker line is a small part of the log.
+pub const MIN_BYTES: u64 = 1024;
+
+const CHUNK: usize = 64 * 1024;
+
+/// Create or empty `log` and start a detached `lillux exec log-writer`
+/// filling it from a new pipe. Returns the pipe's write end, for the
+/// child's stdout and stderr, and the writer's PID.
+pub fn start_writer(log: &str, cap: LogCap) -> Result<(PipeWriter, u32), SysError> {
+    super::open_log(log)?;
+    let (reader, writer) =
+        std::io::pipe().map_err(|e| SysError::io("Failed to create a log pipe", "pipe", &e))?;
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let args = [
+        "exec",
+        "log-writer",
+        "--path",
+        log,
+        "--max-bytes",
+        &cap.max_bytes.to_string(),
+        "--overflow",
+        cap.overflow.as_str(),
+    ]
+    .map(str::to_string);
+    let pid = super::spawn_detached(
+        &exe.to_string_lossy(),
+        &args,
+        super::DetachedLog::Null,
+        &[],
+        super::DetachedStdin::Pipe(reader),
+        false,
+        None,
+    )?;
+    Ok((writer, pid))
+}
+
+/// Copy `from` into the log at `path` until end of input, keeping the log
+/// within `cap`. The log must exist; it is appended to.
+pub fn write(path: &Path, cap: LogCap, mut from: impl Read) -> Result<(), String> {
+    let write_err = |e: std::io::Error| format!("Failed to write log {}: {e}", path.display());
+    let mut log = std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(write_err)?;
+    let mut dropped = 0u64;
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = match from.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Failed to read the process's output: {e}")),
+        };
+        let len = log.metadata().map_err(write_err)?.len();
+        dropped = match cap.overflow {
+            Overflow::Stop => stop(&mut log, len, &buf[..n], cap.max_bytes, dropped),
+            Overflow::Ring => ring(&mut log, len, &buf[..n], cap.max_bytes, dropped),
+        }
+        .map_err(write_err)?;
+    }
+}
+
+/// Write as much of `chunk` as fits, preferring a line boundary, and mark
+/// the cut. Output stops short of the cap by the marker's length, so the
+/// marker fits under it too. `dropped` counts bytes discarded since the
+/// marker; a log found empty again (rotated) starts over.
+fn stop(log: &mut File, len: u64, chunk: &[u8], max: u64, dropped: u64) -> std::io::Result<u64> {
+    let marker = format!("[lillux] log reached --log-max-bytes {max}; further output is dropped\n");
+    // The marker, and a newline ending a line cut short before it.
+    let reserve = marker.len() as u64 + 1;
+    if (dropped > 0 && len > 0) || len + reserve > max {
+        // Full, marker written already (or the log was filled by hand).
+        return Ok(dropped + chunk.len() as u64);
+    }
+    let room = (max - reserve - len) as usize;
+    if chunk.len() <= room {
+        log.write_all(chunk)?;
+        return Ok(0);
+    }
+    // Drop a line that does not fit whole, unless it is all there is.
+    let cut = match chunk[..room].iter().rposition(|&b| b == b'\n') {
+        Some(newline) => newline + 1,
+        None if len == 0 => room,
+        None => 0,
+    };
+    log.write_all(&chunk[..cut])?;
+    if cut > 0 && chunk[cut - 1] != b'\n' {
+        log.write_all(b"\n")?;
+    }
+    log.write_all(marker.as_bytes())?;
+    Ok((chunk.len() - cut) as u64)
+}
+
+/// Write `chunk`, first cutting the log down to its newest half (from a
+/// line boundary) under a marker when it would not fit. Returns the bytes
+/// dropped so far.
+fn ring(log: &mut File, len: u64, chunk: &[u8], max: u64, dropped: u64) -> std::io::Result<u64> {
+    if len + chunk.len() as u64 <= max {
+        log.write_all(chunk)?;
+        return Ok(dropped);
+    }
+    let keep = (max / 2) as usize;
+    let incoming = chunk.len();
+    let (kept, chunk) = if chunk.len() >= keep {
+        let tail = &chunk[chunk.len() - keep..];
+        (Vec::new(), from_line_start(tail))
+    } else {
+        let want = (keep - chunk.len()).min(len as usize);
+        let mut tail = vec![0u8; want];
+        log.seek(SeekFrom::Start(len - want as u64))?;
+        log.read_exact(&mut tail)?;
+        (from_line_start(&tail).to_vec(), chunk)
+    };
+    let content = len - marker_len(log)?;
+    let dropped = dropped + content - kept.len() as u64 + (incoming - chunk.len()) as u64;
+    log.set_len(0)?;
+    log.write_all(
+        format!("[lillux] {dropped} earlier bytes dropped to stay under --log-max-bytes {max}\n")
+            .as_bytes(),
+    )?;
+    log.write_all(&kept)?;
+    log.write_all(chunk)?;
+    Ok(dropped)
+}
+
+/// The length of the marker line heading `log` from an earlier cut, or 0.
+fn marker_len(log: &mut File) -> std::io::Result<u64> {
+    let mut head = Vec::new();
+    log.seek(SeekFrom::Start(0))?;
+    Read::by_ref(log).take(256).read_to_end(&mut head)?;
+    Ok(match head.iter().position(|&b| b == b'\n') {
+        Some(newline) if head.starts_with(b"[lillux] ") => newline as u64 + 1,
+        _ => 0,
+    })
+}
+
+/// `data` from just after its first newline, so a cut never leaves half a
+/// line behind; all of it when it has no newline.
+fn from_line_start(data: &[u8]) -> &[u8] {
+    match data.iter().position(|&b| b == b'\n') {
+        Some(newline) if newline + 1 < data.len() => &data[newline + 1..],
+        _ => data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capped(overflow: Overflow, max_bytes: u64, input: &str) -> String {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("web.log");
+        std::fs::write(&path, "").unwrap();
+        // One line per read, as a chatty child delivers them.
+        let reads = input
+            .split_inclusive('\n')
+            .map(|line| std::io::Cursor::new(line.as_bytes().to_vec()))
+            .fold(Box::new(std::io::empty()) as Box<dyn Read>, |all, line| {
+                Box::new(all.chain(line))
+            });
+        write(
+            &path,
+            LogCap {
+                max_bytes,
+                overflow,
+            },
+            reads,
+        )
+        .unwrap();
+        std::fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn stop_keeps_the_start_and_marks_the_cut() {
+        let input: String = (0..1000).map(|i| format!("line {i:03}\n")).collect();
+        let log = capped(Overflow::Stop, 1024, &input);
+        let (kept, marker) = log.trim_end().rsplit_once('\n').unwrap();
+        assert_eq!(
+            marker,
+            "[lillux] log reached --log-max-bytes 1024; further output is dropped"
+        );
+        // 106 whole 9-byte lines fit in 1024 bytes with the marker.
+        assert_eq!(kept.lines().count(), 106);
+        assert!(kept.ends_with("line 105"));
+        assert!(log.len() <= 1024, "{}", log.len());
+        assert_eq!(capped(Overflow::Stop, 100_000, &input), input);
+
+        // A line longer than the cap is cut, and the marker still fits.
+        let log = capped(Overflow::Stop, 1024, &"x".repeat(5000));
+        assert_eq!(log.len(), 1024);
+        assert!(log.ends_with("further output is dropped\n"));
+    }
+
+    #[test]
+    fn ring_keeps_the_end_under_a_marker() {
+        let input: String = (0..1000).map(|i| format!("line {i:03}\n")).collect();
+        let log = capped(Overflow::Ring, 1024, &input);
+        let (marker, rest) = log.split_once('\n').unwrap();
+        let dropped: u64 = marker
+            .strip_prefix("[lillux] ")
+            .and_then(|m| m.split(' ').next())
+            .and_then(|n| n.parse().ok())
+            .unwrap();
+        assert!(rest.ends_with("line 999\n"), "{log}");
+        assert!(log.len() <= 1024, "{log}");
+        assert!(rest.starts_with("line "));
+        assert_eq!(dropped + rest.len() as u64, input.len() as u64);
+    }
+}
@@ -0,0 +1,477 @@
+//! `lillux exec logs`: print and follow the log file of a registered process.
+//!
+//! `reload --rotate-log` keeps one archive beside the log, `<log>.1`, which
+//! `--log-compress` turns into `<log>.1.gz` or `<log>.1.zst` in a detached
+//! process. `logs --since` reads whichever form is there.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    /// Log lines as written
+    Plain,
+    /// One JSON event per line
+    Json,
+}
+
+/// How a rotated log is archived.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogCompression {
+    Gzip,
+    Zstd,
+}
+
+impl LogCompression {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+const CHUNK: u64 = 8192;
+
+/// The last `count` lines of `path`, and the file length they were read at.
+pub fn last_lines(path: &Path, count: usize) -> Result<(Vec<String>, u64), String> {
+    let open_err = |e: std::io::Error| format!("Failed to read log {}: {e}", path.display());
+    let mut file = File::open(path).map_err(open_err)?;
+    let len = file.metadata().map_err(open_err)?.len();
+    if count == 0 {
+        return Ok((Vec::new(), len));
+    }
+    // Read backwards until the window holds one newline more than needed,
+    // so the first kept line is known to be complete.
+    let mut pos = len;
+    let mut window = Vec::new();
+    while pos > 0 && window.iter().filter(|&&b| b == b'\n').count() <= count {
+        let step = CHUNK.min(pos);
+        pos -= step;
+        let mut chunk = vec![0; step as usize];
+        file.seek(SeekFrom::Start(pos))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .map_err(open_err)?;
+        chunk.extend_from_slice(&window);
+        window = chunk;
+    }
+    let mut lines: Vec<String> = String::from_utf8_lossy(&window)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    lines.drain(..lines.len().saturating_sub(count));
+    Ok((lines, len))
+}
+
+/// A log file read incrementally across rotation: a file renamed away and
+/// recreated is reopened by path, and a truncated one is reread from the
+/// start.
+pub struct Tail {
+    path: PathBuf,
+    file: File,
+    offset: u64,
+}
+
+impl Tail {
+    /// Start reading `path` at byte `offset`.
+    pub fn open(path: &Path, offset: u64) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Failed to read log {}: {e}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            offset,
+        })
+    }
+
+    /// Bytes appended since the last call, and whether the log was rotated
+    /// or truncated. Data left in a rotated-away file is drained first.
+    pub fn read_new(&mut self) -> Result<(Vec<u8>, bool), String> {
+        let mut data = self.drain()?;
+        let mut rotated = false;
+        if self.replaced() {
+            if let Ok(file) = File::open(&self.path) {
+                self.file = file;
+                self.offset = 0;
+                rotated = true;
+            }
+        } else if self
+            .file
+            .metadata()
+            .is_ok_and(|meta| meta.len() < self.offset)
+        {
+            self.offset = 0;
+            rotated = true;
+        }
+        if rotated {
+            data.extend(self.drain()?);
+        }
+        Ok((data, rotated))
+    }
+
+    fn drain(&mut self) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        self.file
+            .seek(SeekFrom::Start(self.offset))
+            .and_then(|_| self.file.read_to_end(&mut data))
+            .map_err(|e| format!("Failed to read log {}: {e}", self.path.display()))?;
+        self.offset += data.len() as u64;
+        Ok(data)
+    }
+
+    /// Whether the path now names a different file than the open handle.
+    /// A missing path (rotated away, not yet recreated) is not a switch.
+    #[cfg(unix)]
+    fn replaced(&self) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(&self.path), self.file.metadata()) {
+            (Ok(current), Ok(open)) => (current.dev(), current.ino()) != (open.dev(), open.ino()),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn replaced(&self) -> bool {
+        false
+    }
+}
+
+/// Write one log line in `format`.
+pub fn emit(out: &mut dyn Write, format: LogFormat, name: &str, line: &str) -> std::io::Result<()> {
+    match format {
+        LogFormat::Plain => writeln!(out, "{line}"),
+        LogFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "event": "line",
+                "name": name,
+                "line": line,
+                "timestamp": crate::time::iso8601_now(),
+            })
+        ),
+    }
+}
+
+/// ANSI colors for the prefixes of a multiplexed log, one per stage in turn.
+const COLORS: [&str; 6] = ["36", "33", "32", "35", "34", "31"];
+
+/// The `[label]` prefixes of a multiplexed pipeline log, to color on a
+/// terminal.
+pub struct Prefixes(Vec<String>);
+
+impl Prefixes {
+    pub fn new<'a>(labels: impl IntoIterator<Item = &'a str>) -> Self {
+        Self(
+            labels
+                .into_iter()
+                .map(|label| format!("[{label}]"))
+                .collect(),
+        )
+    }
+
+    /// `line` with its prefix colored, if it starts with one.
+    pub fn paint<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let known = self
+            .0
+            .iter()
+            .enumerate()
+            .find(|(_, prefix)| line.starts_with(prefix.as_str()));
+        match known {
+            Some((index, prefix)) => Cow::Owned(format!(
+                "\x1b[{}m{prefix}\x1b[0m{}",
+                COLORS[index % COLORS.len()],
+                &line[prefix.len()..]
+            )),
+            None => Cow::Borrowed(line),
+        }
+    }
+}
+
+/// Print lines appended to `tail` every `interval` until `alive` reports
+/// the process gone and the log is drained. JSON output also reports
+/// rotations and the final exit. `prefixes` colors plain lines.
+pub fn follow(
+    tail: &mut Tail,
+    name: &str,
+    format: LogFormat,
+    prefixes: Option<&Prefixes>,
+    interval: Duration,
+    mut alive: impl FnMut() -> bool,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let write_err = |e: std::io::Error| format!("Failed to write log line: {e}");
+    let event = |event: &str| {
+        serde_json::json!({
+            "event": event,
+            "name": name,
+            "timestamp": crate::time::iso8601_now(),
+        })
+    };
+    let paint = |line: &str| -> String {
+        match prefixes {
+            Some(prefixes) => prefixes.paint(line).into_owned(),
+            None => line.to_string(),
+        }
+    };
+    let mut partial = Vec::new();
+    loop {
+        // Sample liveness before reading so output written just before
+        // exit is still drained.
+        let running = alive();
+        let (data, rotated) = tail.read_new()?;
+        if rotated && matches!(format, LogFormat::Json) {
+            writeln!(out, "{}", event("rotated")).map_err(write_err)?;
+        }
+        partial.extend_from_slice(&data);
+        while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            emit(out, format, name, &paint(line.trim_end_matches('\r'))).map_err(write_err)?;
+        }
+        if !running {
+            if !partial.is_empty() {
+                emit(
+                    out,
+                    format,
+                    name,
+                    &paint(&String::from_utf8_lossy(&partial)),
+                )
+                .map_err(write_err)?;
+            }
+            if matches!(format, LogFormat::Json) {
+                writeln!(out, "{}", event("exited")).map_err(write_err)?;
+            }
+            return out.flush().map_err(write_err);
+        }
+        out.flush().map_err(write_err)?;
+        std::thread::sleep(interval);
+    }
+}
+
+/// Copy bytes appended to `tail` to `out` as they arrive, without waiting
+/// for whole lines, until `alive` reports the process gone and the log is
+/// drained.
+pub fn relay(
+    tail: &mut Tail,
+    interval: Duration,
+    mut alive: impl FnMut() -> bool,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let write_err = |e: std::io::Error| format!("Failed to relay output: {e}");
+    loop {
+        let running = alive();
+        let (data, _) = tail.read_new()?;
+        if !data.is_empty() {
+            out.write_all(&data)
+                .and_then(|_| out.flush())
+                .map_err(write_err)?;
+        }
+        if !running {
+            return Ok(());
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// `archive` compressed with `codec` beside it, e.g. `web.log.1.gz`.
+pub fn compressed_path(archive: &Path, codec: LogCompression) -> PathBuf {
+    PathBuf::from(format!("{}.{}", archive.display(), codec.extension()))
+}
+
+/// Compress `archive` into [`compressed_path`], written aside and renamed
+/// into place, then remove `archive` unless a newer rotation has already
+/// replaced it.
+pub fn compress(archive: &Path, codec: LogCompression) -> Result<PathBuf, String> {
+    let read_err = |e: std::io::Error| format!("Failed to read {}: {e}", archive.display());
+    let mut source = File::open(archive).map_err(read_err)?;
+    let target = compressed_path(archive, codec);
+    let partial = PathBuf::from(format!("{}.partial", target.display()));
+    let write_err = |e: std::io::Error| format!("Failed to write {}: {e}", partial.display());
+    let out = File::create(&partial).map_err(write_err)?;
+    match codec {
+        LogCompression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+            std::io::copy(&mut source, &mut encoder).map_err(read_err)?;
+            encoder
+                .finish()
+                .and_then(|out| out.sync_all())
+                .map_err(write_err)?;
+        }
+        LogCompression::Zstd => {
+            let mut encoder = zstd::Encoder::new(out, 0).map_err(write_err)?;
+            std::io::copy(&mut source, &mut encoder).map_err(read_err)?;
+            encoder
+                .finish()
+                .and_then(|out| out.sync_all())
+                .map_err(write_err)?;
+        }
+    }
+    std::fs::rename(&partial, &target)
+        .map_err(|e| format!("Failed to rename {}: {e}", partial.display()))?;
+    if std::fs::metadata(archive).is_ok_and(|current| same_file(&source, &current)) {
+        let _ = std::fs::remove_file(archive);
+    }
+    Ok(target)
+}
+
+#[cfg(unix)]
+fn same_file(open: &File, current: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    open.metadata()
+        .is_ok_and(|open| (open.dev(), open.ino()) == (current.dev(), current.ino()))
+}
+
+#[cfg(not(unix))]
+fn same_file(open: &File, current: &std::fs::Metadata) -> bool {
+    open.metadata().is_ok_and(|open| {
+        open.len() == current.len() && open.modified().ok() == current.modified().ok()
+    })
+}
+
+/// The newest archive of `log`, in whatever form rotation left it.
+fn archive_of(log: &Path) -> Option<(PathBuf, SystemTime)> {
+    let plain = PathBuf::from(format!("{}.1", log.display()));
+    [LogCompression::Gzip, LogCompression::Zstd]
+        .map(|codec| compressed_path(&plain, codec))
+        .into_iter()
+        .chain([plain])
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        // On a tie the plain archive, listed last, is the newer one.
+        .max_by_key(|(_, modified)| *modified)
+}
+
+fn read_all(path: &Path) -> Result<Vec<u8>, String> {
+    let read_err = |e: std::io::Error| format!("Failed to read log {}: {e}", path.display());
+    let mut file = File::open(path).map_err(read_err)?;
+    let mut data = Vec::new();
+    match LogCompression::of(path) {
+        Some(LogCompression::Gzip) => flate2::read::MultiGzDecoder::new(file)
+            .read_to_end(&mut data)
+            .map_err(read_err)?,
+        Some(LogCompression::Zstd) => zstd::Decoder::new(file)
+            .and_then(|mut decoder| decoder.read_to_end(&mut data))
+            .map_err(read_err)?,
+        None => file.read_to_end(&mut data).map_err(read_err)?,
+    };
+    Ok(data)
+}
+
+/// Every line of the files of `log` written to since `cutoff`, oldest
+/// first: its archive if rotated since then, then the live log. Lines
+/// carry no timestamps, so a file counts whole. Also the live log's
+/// length, to follow from.
+pub fn lines_since(log: &Path, cutoff: SystemTime) -> Result<(Vec<String>, u64), String> {
+    let recent = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= cutoff)
+    };
+    let mut lines = Vec::new();
+    if let Some((archive, _)) = archive_of(log).filter(|(_, rotated)| *rotated >= cutoff) {
+        let data = read_all(&archive)?;
+        lines.extend(String::from_utf8_lossy(&data).lines().map(str::to_string));
+    }
+    let data = read_all(log)?;
+    if recent(log) {
+        lines.extend(String::from_utf8_lossy(&data).lines().map(str::to_string));
+    }
+    Ok((lines, data.len() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_lines_spans_chunks_and_ignores_a_short_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("web.log");
+        let body: String = (0..3000).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(&path, &body).unwrap();
+
+        let (lines, offset) = last_lines(&path, 2000).unwrap();
+        assert_eq!(lines.len(), 2000);
+        assert_eq!(lines[0], "line 1000");
+        assert_eq!(lines[1999], "line 2999");
+        assert_eq!(offset, body.len() as u64);
+
+        std::fs::write(&path, "only\n").unwrap();
+        assert_eq!(last_lines(&path, 5).unwrap().0, vec!["only"]);
+    }
+
+    #[test]
+    fn tail_survives_truncation_and_replacement() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("web.log");
+        std::fs::write(&path, "old\n").unwrap();
+        let mut tail = Tail::open(&path, 4).unwrap();
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"new\n")
+            .unwrap();
+        assert_eq!(tail.read_new().unwrap(), (b"new\n".to_vec(), false));
+
+        std::fs::write(&path, "a\n").unwrap();
+        assert_eq!(tail.read_new().unwrap(), (b"a\n".to_vec(), true));
+
+        std::fs::rename(&path, tmp.path().join("web.log.1")).unwrap();
+        std::fs::write(&path, "fresh\n").unwrap();
+        assert_eq!(tail.read_new().unwrap(), (b"fresh\n".to_vec(), true));
+    }
+
+    #[test]
+    fn history_since_reads_a_compressed_archive_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("web.log");
+        let archive = tmp.path().join("web.log.1");
+        std::fs::write(&log, "live\n").unwrap();
+        let long_ago = SystemTime::now() - Duration::from_secs(3600);
+        let cutoff = SystemTime::now() - Duration::from_secs(60);
+
+        for codec in [LogCompression::Gzip, LogCompression::Zstd] {
+            std::fs::write(&archive, "one\ntwo\n").unwrap();
+            let compressed = compress(&archive, codec).unwrap();
+            assert_eq!(compressed, compressed_path(&archive, codec));
+            assert!(!archive.exists());
+            let (lines, offset) = lines_since(&log, cutoff).unwrap();
+            assert_eq!(lines, ["one", "two", "live"]);
+            assert_eq!(offset, 5);
+            std::fs::remove_file(compressed).unwrap();
+        }
+
+        std::fs::write(&archive, "old\n").unwrap();
+        File::options()
+            .write(true)
+            .open(&archive)
+            .and_then(|file| file.set_modified(long_ago))
+            .unwrap();
+        assert_eq!(lines_since(&log, cutoff).unwrap().0, ["live"]);
+    }
+}
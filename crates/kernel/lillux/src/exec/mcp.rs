@@ -0,0 +1,174 @@
+//! `lillux exec mcp`: the exec commands as Model Context Protocol tools.
+//!
+//! Speaks MCP's stdio transport (newline-delimited JSON-RPC 2.0) and maps
+//! each tool call onto the matching [`serve`](super::serve) method, so MCP
+//! clients and JSON-RPC clients share one implementation.
+
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use super::serve::Server;
+
+/// Protocol revisions this server implements, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// The advertised tools and their input schemas.
+pub fn tools() -> Value {
+    let target = json!({
+        "pid": { "type": "integer", "minimum": 1, "description": "Process ID" },
+        "name": { "type": "string", "description": "Registered process name" },
+    });
+    let mut kill_target = target.clone();
+    kill_target["grace"] = json!({
+        "type": "number",
+        "minimum": 0,
+        "default": 3.0,
+        "description": "Seconds between SIGTERM and SIGKILL",
+    });
+    json!([
+        {
+            "name": "spawn",
+            "description": "Start a detached process and record it in the registry.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "cmd": { "type": "string", "description": "Executable to run" },
+                    "args": { "type": "array", "items": { "type": "string" } },
+                    "envs": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "KEY=VALUE pairs; the environment starts empty",
+                    },
+                    "log": { "type": "string", "description": "File receiving stdout and stderr" },
+                    "name": { "type": "string", "description": "Unique name among live processes" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["cmd"],
+            },
+        },
+        {
+            "name": "kill",
+            "description": "Terminate a process by PID or registered name, escalating to SIGKILL after a grace period.",
+            "inputSchema": { "type": "object", "properties": kill_target },
+        },
+        {
+            "name": "status",
+            "description": "Report whether a process is alive, with its command line, memory, and CPU usage.",
+            "inputSchema": { "type": "object", "properties": target },
+        },
+        {
+            "name": "list",
+            "description": "List processes recorded by spawn, live or exited.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "tag": { "type": "string", "description": "Only entries carrying this tag" },
+                    "name_prefix": { "type": "string", "description": "Only names starting with this" },
+                },
+            },
+        },
+        {
+            "name": "logs",
+            "description": "Read the last lines of a registered process's log file.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Registered process name" },
+                    "lines": { "type": "integer", "minimum": 0, "default": 100 },
+                },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+/// The response to one MCP message; `None` for notifications.
+pub fn respond(server: &Arc<Server>, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": e.to_string() },
+            }))
+        }
+    };
+    let id = request.get("id").cloned()?;
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+    let result = match request.get("method").and_then(Value::as_str) {
+        Some("initialize") => {
+            let requested = params.get("protocolVersion").and_then(Value::as_str);
+            let version = PROTOCOL_VERSIONS
+                .iter()
+                .find(|version| Some(**version) == requested)
+                .unwrap_or(&PROTOCOL_VERSIONS[0]);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "lillux", "version": env!("CARGO_PKG_VERSION") },
+            })
+        }
+        Some("ping") => json!({}),
+        Some("tools/list") => json!({ "tools": tools() }),
+        Some("tools/call") => call_tool(server, &params),
+        Some(method) => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method '{method}'") },
+            }))
+        }
+        None => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32600, "message": "Request has no method" },
+            }))
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Run a tool. Failures are reported in the result with `isError` so the
+/// model sees them, as MCP prescribes for tool errors.
+fn call_tool(server: &Arc<Server>, params: &Value) -> Value {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let known = tools()
+        .as_array()
+        .is_some_and(|tools| tools.iter().any(|tool| tool["name"] == name));
+    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+    let (output, is_error) = if !known {
+        (json!({ "error": format!("Unknown tool '{name}'") }), true)
+    } else {
+        match server.call(name, arguments) {
+            Ok(result) => (result, false),
+            Err((_, message, data)) => (data.unwrap_or(json!({ "error": message })), true),
+        }
+    };
+    json!({
+        "content": [{ "type": "text", "text": output.to_string() }],
+        "isError": is_error,
+    })
+}
+
+/// Serve MCP on stdin/stdout until stdin closes.
+pub fn serve_stdio(registry: Option<String>) {
+    let server = Server::new(registry);
+    let mut stdout = std::io::stdout();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(&server, &line) {
+            let _ = writeln!(stdout, "{response}");
+            let _ = stdout.flush();
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! `lillux exec daemon --metrics`: Prometheus metrics over HTTP.
+//!
+//! `GET /metrics` renders the text exposition format from the registry
+//! (one series per live registered process) and the daemon's own call
+//! counters. Each scrape reads fresh values; nothing is cached.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::procinfo::ProcessInfo;
+use super::registry::{Registry, RegistryEntry};
+use super::serve::Server;
+
+type Series = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&RegistryEntry, &ProcessInfo) -> Option<f64>,
+);
+
+/// Per-process families: name, type, help, and how to read the value.
+const PROCESS_SERIES: &[Series] = &[
+    (
+        "lillux_exec_process_cpu_percent",
+        "gauge",
+        "Lifetime average CPU usage, percent of one core.",
+        |_, info| info.cpu_percent,
+    ),
+    (
+        "lillux_exec_process_resident_memory_bytes",
+        "gauge",
+        "Resident set size.",
+        |_, info| info.rss_bytes.map(|bytes| bytes as f64),
+    ),
+    (
+        "lillux_exec_process_uptime_seconds",
+        "gauge",
+        "Seconds since the process started.",
+        |_, info| info.uptime_ms.map(|ms| ms as f64 / 1000.0),
+    ),
+    (
+        "lillux_exec_process_restarts_total",
+        "counter",
+        "Restarts by the process's health action.",
+        |entry, _| {
+            Some(
+                entry
+                    .health_state
+                    .as_ref()
+                    .map_or(0, |state| state.restarts) as f64,
+            )
+        },
+    ),
+    (
+        "lillux_exec_process_healthy",
+        "gauge",
+        "1 when the last health checks passed, 0 when unhealthy.",
+        |entry, _| match entry.health_state.as_ref()?.status.as_str() {
+            "healthy" => Some(1.0),
+            "unhealthy" => Some(0.0),
+            _ => None,
+        },
+    ),
+];
+
+/// Bind `address` and answer scrapes on a background thread. Returns the
+/// bound address, which differs from `address` when it asks for port 0.
+pub fn serve(server: &Arc<Server>, address: &str) -> Result<SocketAddr, String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Failed to bind metrics on {address}: {e}"))?;
+    let bound = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to bind metrics on {address}: {e}"))?;
+    let server = Arc::clone(server);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let server = Arc::clone(&server);
+            std::thread::spawn(move || answer(&server, stream));
+        }
+    });
+    Ok(bound)
+}
+
+fn answer(server: &Arc<Server>, mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+    let mut request_line = String::new();
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Drain the headers so the client sees an orderly close.
+    let mut header = String::new();
+    while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    let path = path.map(|path| path.split('?').next().unwrap_or(path));
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render(server),
+        ),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "Not found; metrics are at /metrics\n".to_string(),
+        ),
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+/// The exposition text for one scrape.
+pub fn render(server: &Server) -> String {
+    let mut out = String::new();
+    let entries = Registry::locate(server.registry())
+        .and_then(|registry| registry.entries())
+        .unwrap_or_default();
+    let live: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| entry.live_info().map(|info| (entry, info)))
+        .collect();
+
+    family(
+        &mut out,
+        "lillux_exec_processes",
+        "gauge",
+        "Live registered processes.",
+    );
+    let _ = writeln!(out, "lillux_exec_processes {}", live.len());
+
+    for (name, kind, help, value) in PROCESS_SERIES {
+        family(&mut out, name, kind, help);
+        for (entry, info) in &live {
+            if let Some(value) = value(entry, info) {
+                let _ = writeln!(
+                    out,
+                    "{name}{{name=\"{}\",pid=\"{}\"}} {value}",
+                    escape(entry.name.as_deref().unwrap_or_default()),
+                    entry.pid
+                );
+            }
+        }
+    }
+
+    family(
+        &mut out,
+        "lillux_exec_daemon_calls_total",
+        "counter",
+        "JSON-RPC calls handled by this daemon, by method and outcome.",
+    );
+    for (method, outcome, count) in server.call_counts() {
+        let _ = writeln!(
+            out,
+            "lillux_exec_daemon_calls_total{{method=\"{}\",outcome=\"{outcome}\"}} {count}",
+            escape(&method)
+        );
+    }
+    family(
+        &mut out,
+        "lillux_exec_daemon_orphans_reaped_total",
+        "counter",
+        "Orphaned descendants adopted and reaped under --subreaper.",
+    );
+    let _ = writeln!(
+        out,
+        "lillux_exec_daemon_orphans_reaped_total {}",
+        server.orphans_reaped()
+    );
+    family(
+        &mut out,
+        "lillux_exec_daemon_spawns_rejected_total",
+        "counter",
+        "Spawns refused by --max-spawns-per-minute.",
+    );
+    let _ = writeln!(
+        out,
+        "lillux_exec_daemon_spawns_rejected_total {}",
+        server.spawns_rejected()
+    );
+    family(
+        &mut out,
+        "lillux_exec_daemon_uptime_seconds",
+        "gauge",
+        "Seconds since the daemon started.",
+    );
+    let _ = writeln!(
+        out,
+        "lillux_exec_daemon_uptime_seconds {}",
+        server.uptime().as_secs_f64()
+    );
+    out
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+}
+
+/// Escape a label value: backslash, double quote, and newline.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,154 @@
+//! `lillux exec on-exit`: run a hook once an arbitrary process exits.
+//!
+//! The target need not be our child, so its exit status is unobservable;
+//! the hook receives what is known instead (PID, name, command line, start
+//! and observed exit time) as `LILLUX_EXIT_*` environment variables. The
+//! target's start time is pinned when the watch is armed, so a recycled PID
+//! is treated as an exit rather than watched forever.
+
+use std::process;
+use std::time::Duration;
+
+use super::procinfo::{self, ProcessInfo};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What the watcher knows about the target at arm time.
+pub struct ExitHook {
+    pub pid: u32,
+    pub started_at_ms: Option<u64>,
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub envs: Vec<String>,
+    pub log: Option<String>,
+}
+
+/// Re-exec this binary as a detached watcher and return its PID.
+pub fn arm(hook: &ExitHook) -> Result<u32, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let mut args = vec![
+        "exec".to_string(),
+        "on-exit".to_string(),
+        "--pid".to_string(),
+        hook.pid.to_string(),
+        "--exec".to_string(),
+        hook.cmd.clone(),
+        "--foreground".to_string(),
+    ];
+    if let Some(started) = hook.started_at_ms {
+        args.extend(["--started-at-ms".to_string(), started.to_string()]);
+    }
+    for arg in &hook.args {
+        args.extend(["--arg".to_string(), arg.clone()]);
+    }
+    for env in &hook.envs {
+        args.extend(["--env".to_string(), env.clone()]);
+    }
+    if let Some(log) = &hook.log {
+        args.extend(["--log".to_string(), log.clone()]);
+    }
+    super::spawn_detached(
+        &exe.to_string_lossy(),
+        &args,
+        super::DetachedLog::Null,
+        &[],
+        super::DetachedStdin::Null,
+        false,
+        None,
+    )
+    .map_err(String::from)
+}
+
+/// Block until the target exits, then run the hook and return its exit code.
+pub fn watch_and_run(hook: &ExitHook) -> i32 {
+    let armed = procinfo::inspect(hook.pid).filter(|info| same_process(hook, info));
+    if armed.is_some() {
+        wait_for_exit(hook);
+    }
+    let observed_at_ms = crate::time::timestamp_millis().max(0) as u64;
+
+    let mut command = process::Command::new(&hook.cmd);
+    command.args(&hook.args).env_clear();
+    super::set_envs(&mut command, &hook.envs);
+    command
+        .env("LILLUX_EXIT_PID", hook.pid.to_string())
+        .env("LILLUX_EXIT_OBSERVED_AT_MS", observed_at_ms.to_string())
+        .env(
+            "LILLUX_EXIT_OBSERVED_AT",
+            crate::time::iso8601_from_unix_secs(observed_at_ms / 1000),
+        );
+    if let Some(started) = hook.started_at_ms {
+        command
+            .env("LILLUX_EXIT_STARTED_AT_MS", started.to_string())
+            .env(
+                "LILLUX_EXIT_UPTIME_MS",
+                observed_at_ms.saturating_sub(started).to_string(),
+            );
+    }
+    if let Some(info) = &armed {
+        if let Some(name) = &info.name {
+            command.env("LILLUX_EXIT_NAME", name);
+        }
+        if let Some(cmdline) = &info.cmdline {
+            command.env("LILLUX_EXIT_CMDLINE", cmdline.join(" "));
+        }
+    }
+    command.stdin(process::Stdio::null());
+    if let Err(e) = super::setup_log(&mut command, super::DetachedLog::file(hook.log.as_deref())) {
+        eprintln!("{e}");
+        return 125;
+    }
+    match command.status() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            eprintln!("Failed to run exit hook: {e}");
+            125
+        }
+    }
+}
+
+fn same_process(hook: &ExitHook, info: &ProcessInfo) -> bool {
+    match (hook.started_at_ms, info.start_time_ms) {
+        (Some(armed), Some(observed)) => armed.abs_diff(observed) <= 2000,
+        _ => true,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn wait_for_exit(hook: &ExitHook) {
+    use std::os::fd::AsRawFd as _;
+
+    // A pidfd pins the exact process; readiness means it has terminated.
+    let Ok(pidfd) = super::open_pidfd(hook.pid) else {
+        return poll_for_exit(hook);
+    };
+    if !procinfo::inspect(hook.pid).is_some_and(|info| same_process(hook, &info)) {
+        return;
+    }
+    loop {
+        let mut pollfd = libc::pollfd {
+            fd: pidfd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let result = unsafe { libc::poll(&mut pollfd, 1, -1) };
+        if result > 0 {
+            return;
+        }
+        if std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return poll_for_exit(hook);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wait_for_exit(hook: &ExitHook) {
+    poll_for_exit(hook)
+}
+
+fn poll_for_exit(hook: &ExitHook) {
+    while procinfo::inspect(hook.pid).is_some_and(|info| same_process(hook, &info)) {
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
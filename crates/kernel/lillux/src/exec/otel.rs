@@ -0,0 +1,364 @@
+//! OpenTelemetry tracing for exec operations.
+//!
+//! `spawn`, `kill`, `status`, and the daemon's calls run inside `tracing`
+//! spans. When `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` names an `http://` collector, finished
+//! spans are sent to it as OTLP/HTTP JSON. A W3C `TRACEPARENT` in our own
+//! environment makes our spans children of the caller's, and every
+//! spawned child receives a `TRACEPARENT` naming the span that started
+//! it, so a caller's trace and the processes it launches join up.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// How often buffered spans are sent while a long-lived process runs.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bound on one export; tracing must never hold up process control.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+static EXPORTER: OnceLock<Option<Arc<Exporter>>> = OnceLock::new();
+
+/// Install the exporter if one is configured. Spans still buffered when
+/// the returned guard drops are sent then.
+pub fn init() -> FlushGuard {
+    let exporter = EXPORTER.get_or_init(|| {
+        let exporter = Arc::new(Exporter::from_env()?);
+        let layer = OtlpLayer {
+            exporter: Arc::clone(&exporter),
+            parent: std::env::var("TRACEPARENT")
+                .ok()
+                .and_then(|raw| parse_traceparent(&raw)),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::set_global_default(subscriber).ok()?;
+        let periodic = Arc::clone(&exporter);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EXPORT_INTERVAL);
+            periodic.flush();
+        });
+        Some(exporter)
+    });
+    FlushGuard(exporter.clone())
+}
+
+pub struct FlushGuard(Option<Arc<Exporter>>);
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        if let Some(exporter) = &self.0 {
+            exporter.flush();
+        }
+    }
+}
+
+/// The `TRACEPARENT` to hand a child started now: the current span when
+/// spans are exported, else whatever context we were given ourselves.
+pub fn traceparent() -> Option<String> {
+    let current = tracing::dispatcher::get_default(|dispatch| {
+        let registry = dispatch.downcast_ref::<tracing_subscriber::Registry>()?;
+        let id = tracing::Span::current().id()?;
+        let span = registry.span(&id)?;
+        let extensions = span.extensions();
+        let data = extensions.get::<SpanData>()?;
+        Some(format!(
+            "00-{}-{}-01",
+            hex(&data.trace_id),
+            hex(&data.span_id)
+        ))
+    });
+    current.or_else(|| {
+        std::env::var("TRACEPARENT")
+            .ok()
+            .filter(|raw| parse_traceparent(raw).is_some())
+    })
+}
+
+/// Run `f` inside `span` and record the `pid` and `error` of its JSON
+/// result on it.
+pub fn in_span(span: tracing::Span, f: impl FnOnce() -> Value) -> Value {
+    let result = span.in_scope(f);
+    record(&span, &result);
+    result
+}
+
+/// Record the `pid` and `error` of `result` on `span`.
+pub fn record(span: &tracing::Span, result: &Value) {
+    if let Some(pid) = result.get("pid").and_then(Value::as_u64) {
+        span.record("pid", pid);
+    }
+    if let Some(error) = result.get("error").and_then(Value::as_str) {
+        span.record("error", error);
+    }
+}
+
+/// Trace and parent span ids from a version-00 W3C `traceparent`.
+fn parse_traceparent(raw: &str) -> Option<([u8; 16], [u8; 8])> {
+    let mut parts = raw.trim().split('-');
+    let (version, trace, span, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if version != "00" || flags.len() != 2 || parts.next().is_some() {
+        return None;
+    }
+    let trace: [u8; 16] = unhex(trace)?.try_into().ok()?;
+    let span: [u8; 8] = unhex(span)?.try_into().ok()?;
+    (trace != [0; 16] && span != [0; 8]).then_some((trace, span))
+}
+
+struct Exporter {
+    /// `host:port` of the collector.
+    authority: String,
+    path: String,
+    service: String,
+    spans: Mutex<Vec<Value>>,
+}
+
+impl Exporter {
+    fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if var("OTEL_SDK_DISABLED").is_some_and(|v| v.eq_ignore_ascii_case("true")) {
+            return None;
+        }
+        let endpoint = var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").or_else(|| {
+            var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .map(|base| format!("{}/v1/traces", base.trim_end_matches('/')))
+        })?;
+        let Some(rest) = endpoint.strip_prefix("http://") else {
+            eprintln!("lillux: OTLP endpoint {endpoint} is not http://; spans are not exported");
+            return None;
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/v1/traces"),
+        };
+        let authority = match authority.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => authority.to_string(),
+            _ => format!("{authority}:80"),
+        };
+        Some(Self {
+            authority,
+            path: path.to_string(),
+            service: var("OTEL_SERVICE_NAME").unwrap_or_else(|| "lillux".to_string()),
+            spans: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Send buffered spans; failures drop them.
+    fn flush(&self) {
+        let spans = std::mem::take(&mut *self.spans.lock().unwrap_or_else(|e| e.into_inner()));
+        if spans.is_empty() {
+            return;
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": { "attributes": [attribute("service.name", json!({ "stringValue": self.service }))] },
+                "scopeSpans": [{
+                    "scope": { "name": "lillux", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        })
+        .to_string();
+        let _ = self.post(&body);
+    }
+
+    fn post(&self, body: &str) -> std::io::Result<()> {
+        use std::net::ToSocketAddrs;
+        let addr = self
+            .authority
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::other("unresolvable OTLP endpoint"))?;
+        let mut stream = TcpStream::connect_timeout(&addr, EXPORT_TIMEOUT)?;
+        stream.set_write_timeout(Some(EXPORT_TIMEOUT))?;
+        stream.set_read_timeout(Some(EXPORT_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.path,
+            self.authority,
+            body.len()
+        )?;
+        // Wait for the status line so the collector has the request
+        // before a short-lived process exits.
+        let mut status = [0u8; 12];
+        let _ = stream.read(&mut status)?;
+        Ok(())
+    }
+}
+
+/// Per-span state kept in the registry's extensions.
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    start_ns: u128,
+    attributes: Vec<Value>,
+    error: Option<String>,
+}
+
+struct OtlpLayer {
+    exporter: Arc<Exporter>,
+    /// Context inherited through `TRACEPARENT`, parent of our root spans.
+    parent: Option<([u8; 16], [u8; 8])>,
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            extensions
+                .get::<SpanData>()
+                .map(|data| (data.trace_id, data.span_id))
+        });
+        let (trace_id, parent_span_id) = match parent.or(self.parent) {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => (rand::random(), None),
+        };
+        let mut data = SpanData {
+            trace_id,
+            span_id: rand::random(),
+            parent_span_id,
+            start_ns: now_ns(),
+            attributes: Vec::new(),
+            error: None,
+        };
+        attrs.record(&mut data);
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                values.record(data);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        let mut otlp = json!({
+            "traceId": hex(&data.trace_id),
+            "spanId": hex(&data.span_id),
+            "name": span.name(),
+            "kind": 1,
+            "startTimeUnixNano": data.start_ns.to_string(),
+            "endTimeUnixNano": now_ns().to_string(),
+            "attributes": data.attributes,
+            "status": match &data.error {
+                Some(message) => json!({ "code": 2, "message": message }),
+                None => json!({ "code": 0 }),
+            },
+        });
+        if let Some(parent) = data.parent_span_id {
+            otlp["parentSpanId"] = hex(&parent).into();
+        }
+        self.exporter
+            .spans
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(otlp);
+    }
+}
+
+/// Span fields become OTLP attributes; a field named `error` also marks
+/// the span failed.
+impl Visit for SpanData {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "error" {
+            self.error = Some(value.to_string());
+        }
+        self.attributes
+            .push(attribute(field.name(), json!({ "stringValue": value })));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.attributes.push(attribute(
+            field.name(),
+            json!({ "intValue": value.to_string() }),
+        ));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.attributes.push(attribute(
+            field.name(),
+            json!({ "intValue": value.to_string() }),
+        ));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.attributes
+            .push(attribute(field.name(), json!({ "doubleValue": value })));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.attributes
+            .push(attribute(field.name(), json!({ "boolValue": value })));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+fn attribute(key: &str, value: Value) -> Value {
+    json!({ "key": key, "value": value })
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceparent_parsing_follows_w3c_version_00() {
+        let (trace, span) =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(hex(&trace), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex(&span), "00f067aa0ba902b7");
+        for invalid in [
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert!(parse_traceparent(invalid).is_none(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! `lillux exec pipeline`: run `a | b | c` detached as one unit.
+//!
+//! A detached supervisor (this binary re-executed with `--foreground`)
+//! starts the stages as its own children inside its new session, so the
+//! supervisor and every stage share one process group and killing the
+//! pipeline signals that group. The supervisor records the pipeline in the
+//! registry, hands the stage PIDs back to the launcher on stdout, then
+//! reaps each stage and records its exit code.
+//!
+//! With `--multiplex`, every stage's stderr (and the last stage's stdout)
+//! passes through the supervisor instead of going to `--log` directly.
+//! Each line lands in `--log` prefixed with the stage's label, `[grep]`,
+//! and unprefixed in the stage's own file beside it, `web.grep.log`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{self, Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::registry::{PipelineStage, Registry, RegistryEntry};
+use super::rusage::ResourceUsage;
+
+pub struct Pipeline {
+    pub stages: Vec<Vec<String>>,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub log: Option<String>,
+    pub envs: Vec<String>,
+    /// Interleave the stages' output in `log` behind `[label]` prefixes.
+    pub multiplex: bool,
+}
+
+/// Parse one `--stage`: a non-empty JSON array of strings.
+pub fn parse_stage(raw: &str) -> Result<Vec<String>, String> {
+    let argv: Vec<String> = serde_json::from_str(raw)
+        .map_err(|e| format!("--stage must be a JSON array of strings ({e}): {raw}"))?;
+    if argv.is_empty() {
+        return Err("--stage must name a command".to_string());
+    }
+    Ok(argv)
+}
+
+/// Start a detached supervisor for `pipeline` and return its report: the
+/// supervisor PID (also the pipeline's process group) and stage PIDs.
+#[cfg(unix)]
+pub fn launch(pipeline: &Pipeline, registry: &Registry) -> Result<serde_json::Value, String> {
+    use std::os::unix::process::CommandExt;
+
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let mut args = vec![
+        "exec".to_string(),
+        "--registry".to_string(),
+        registry.path().to_string_lossy().into_owned(),
+        "pipeline".to_string(),
+        "--foreground".to_string(),
+    ];
+    for stage in &pipeline.stages {
+        args.extend([
+            "--stage".to_string(),
+            serde_json::to_string(stage).unwrap_or_default(),
+        ]);
+    }
+    if let Some(name) = &pipeline.name {
+        args.extend(["--name".to_string(), name.clone()]);
+    }
+    for tag in &pipeline.tags {
+        args.extend(["--tag".to_string(), tag.clone()]);
+    }
+    for env in &pipeline.envs {
+        args.extend(["--env".to_string(), env.clone()]);
+    }
+    if let Some(log) = &pipeline.log {
+        args.extend(["--log".to_string(), log.clone()]);
+    }
+    if pipeline.multiplex {
+        args.push("--multiplex".to_string());
+    }
+    let mut command = process::Command::new(exe);
+    command
+        .args(&args)
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let mut supervisor = command
+        .spawn()
+        .map_err(|e| format!("Failed to start pipeline supervisor: {e}"))?;
+    let mut line = String::new();
+    if let Some(stdout) = supervisor.stdout.take() {
+        let _ = BufReader::new(stdout).read_line(&mut line);
+    }
+    let report: serde_json::Value = serde_json::from_str(&line)
+        .map_err(|_| "Pipeline supervisor exited before reporting its stages".to_string())?;
+    match report.get("error").and_then(|e| e.as_str()) {
+        Some(error) => Err(error.to_string()),
+        None => Ok(report),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn launch(_pipeline: &Pipeline, _registry: &Registry) -> Result<serde_json::Value, String> {
+    Err("lillux exec pipeline is not supported on this platform".to_string())
+}
+
+/// Run `pipeline` in this process, reporting to stdout once every stage
+/// has started. Returns the last stage's exit code.
+pub fn supervise(pipeline: &Pipeline, registry: &Registry) -> i32 {
+    let (mut children, relays) = match start_stages(pipeline) {
+        Ok(started) => started,
+        Err(e) => {
+            report(&serde_json::json!({ "success": false, "error": e }));
+            return 1;
+        }
+    };
+    let supervisor = process::id();
+    let mut entry = RegistryEntry {
+        pid: supervisor,
+        name: pipeline.name.clone(),
+        tags: pipeline.tags.clone(),
+        cmd: pipeline.stages[0][0].clone(),
+        args: display_args(&pipeline.stages),
+        log: pipeline.log.clone(),
+        started_at_ms: super::procinfo::inspect(supervisor).and_then(|info| info.start_time_ms),
+        spawned_at: crate::time::iso8601_now(),
+        stages: children
+            .iter()
+            .zip(&pipeline.stages)
+            .zip(stage_logs(pipeline))
+            .map(|((child, argv), own)| PipelineStage {
+                pid: child.id(),
+                argv: argv.clone(),
+                exit_code: None,
+                rusage: None,
+                label: own.as_ref().map(|(label, _)| label.clone()),
+                log: own.map(|(_, log)| log),
+            })
+            .collect(),
+        envs: pipeline.envs.clone(),
+        pgid: Some(supervisor),
+        ..Default::default()
+    };
+    let mut result = serde_json::json!({
+        "success": true,
+        "pid": supervisor,
+        "pgid": supervisor,
+        "stages": entry.stages,
+    });
+    if let Some(name) = &entry.name {
+        result["name"] = name.clone().into();
+    }
+    match registry.record(&entry) {
+        Ok(id) => entry.id = id,
+        Err(e) => result["registry_error"] = e.into(),
+    }
+    report(&result);
+
+    let mut last = 0;
+    for (index, child) in children.iter_mut().enumerate() {
+        let (code, usage) = reap(child);
+        last = code;
+        entry.stages[index].exit_code = Some(last);
+        entry.stages[index].rusage = usage;
+        if entry.id != 0 {
+            let _ = registry.update_stages(entry.id, &entry.stages);
+        }
+    }
+    for relay in relays {
+        let _ = relay.join();
+    }
+    if entry.id != 0 {
+        let _ = registry.mark_ended(entry.id, "exited");
+    }
+    last
+}
+
+/// Spawn every stage, wiring each stdout to the next stdin, and start the
+/// threads relaying output into a multiplexed log. On failure the stages
+/// already started are killed.
+fn start_stages(pipeline: &Pipeline) -> Result<(Vec<Child>, Vec<JoinHandle<()>>), String> {
+    let log = match &pipeline.log {
+        Some(path) => Some(super::open_log(path)?),
+        None => None,
+    };
+    let multiplexed = match (&log, pipeline.multiplex) {
+        (Some(file), true) => Some(Arc::new(Mutex::new(
+            file.try_clone()
+                .map_err(|e| format!("Failed to clone log fd: {e}"))?,
+        ))),
+        _ => None,
+    };
+    let own_logs = stage_logs(pipeline)
+        .into_iter()
+        .map(|own| match own {
+            Some((label, path)) => Ok(Some((label, Arc::new(Mutex::new(super::open_log(&path)?))))),
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let sink = || -> Result<Stdio, String> {
+        match &log {
+            Some(_) if multiplexed.is_some() => Ok(Stdio::piped()),
+            Some(file) => file
+                .try_clone()
+                .map(Stdio::from)
+                .map_err(|e| format!("Failed to clone log fd: {e}")),
+            None => Ok(Stdio::null()),
+        }
+    };
+    let mut relays = Vec::new();
+    let mut children: Vec<Child> = Vec::new();
+    let mut previous = None;
+    for (index, argv) in pipeline.stages.iter().enumerate() {
+        let mut command = process::Command::new(&argv[0]);
+        command.args(&argv[1..]).env_clear();
+        super::set_envs(&mut command, &pipeline.envs);
+        command.stdin(previous.take().map_or_else(Stdio::null, Stdio::from));
+        let last = index + 1 == pipeline.stages.len();
+        let spawned = sink().and_then(|stderr| {
+            let stdout = if last { sink()? } else { Stdio::piped() };
+            command
+                .stdout(stdout)
+                .stderr(stderr)
+                .spawn()
+                .map_err(|e| format!("Failed to spawn stage {} ({}): {e}", index + 1, argv[0]))
+        });
+        match spawned {
+            Ok(mut child) => {
+                if let (Some(combined), Some((label, own))) = (&multiplexed, &own_logs[index]) {
+                    let streams: [Option<Box<dyn Read + Send>>; 2] = [
+                        child.stderr.take().map(|s| Box::new(s) as _),
+                        if last {
+                            child.stdout.take().map(|s| Box::new(s) as _)
+                        } else {
+                            None
+                        },
+                    ];
+                    for from in streams.into_iter().flatten() {
+                        let (combined, own) = (Arc::clone(combined), Arc::clone(own));
+                        let prefix = format!("[{label}] ");
+                        relays.push(thread::spawn(move || relay(from, &prefix, &combined, &own)));
+                    }
+                }
+                previous = child.stdout.take();
+                children.push(child);
+            }
+            Err(e) => {
+                for child in &mut children {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok((children, relays))
+}
+
+/// Copy `from` line by line into the combined log behind `prefix`, and as
+/// is into the stage's own file. A line is written in one call, so lines
+/// from different stages never tear.
+fn relay(from: impl Read, prefix: &str, combined: &Mutex<File>, own: &Mutex<File>) {
+    let mut from = BufReader::new(from);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match from.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if line.last() != Some(&b'\n') {
+            line.push(b'\n');
+        }
+        let mut prefixed = Vec::with_capacity(prefix.len() + line.len());
+        prefixed.extend_from_slice(prefix.as_bytes());
+        prefixed.extend_from_slice(&line);
+        let _ = combined
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(&prefixed);
+        let _ = own
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(&line);
+    }
+}
+
+/// Each stage's label and own log file when `pipeline` is multiplexed.
+fn stage_logs(pipeline: &Pipeline) -> Vec<Option<(String, String)>> {
+    match (&pipeline.log, pipeline.multiplex) {
+        (Some(log), true) => labels(&pipeline.stages)
+            .into_iter()
+            .map(|label| {
+                let own = stage_log(log, &label);
+                Some((label, own))
+            })
+            .collect(),
+        _ => vec![None; pipeline.stages.len()],
+    }
+}
+
+/// A prefix per stage: the command's file name, with the stage's position
+/// appended when another stage runs the same command (`cat-1`, `cat-3`).
+pub fn labels(stages: &[Vec<String>]) -> Vec<String> {
+    let names: Vec<String> = stages
+        .iter()
+        .map(|argv| {
+            Path::new(&argv[0]).file_name().map_or_else(
+                || argv[0].clone(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            if names.iter().filter(|other| *other == name).count() > 1 {
+                format!("{name}-{}", index + 1)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+/// The stage's own file beside the combined `log`: `web.log` and `grep`
+/// give `web.grep.log`.
+pub fn stage_log(log: &str, label: &str) -> String {
+    let path = Path::new(log);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => path
+            .with_file_name(format!(
+                "{}.{label}.{}",
+                stem.to_string_lossy(),
+                extension.to_string_lossy()
+            ))
+            .to_string_lossy()
+            .into_owned(),
+        _ => format!("{log}.{label}"),
+    }
+}
+
+/// `a x | b y` as the registry's `args`: every stage after the first
+/// command, separated by `|`.
+fn display_args(stages: &[Vec<String>]) -> Vec<String> {
+    let mut args = stages[0][1..].to_vec();
+    for stage in &stages[1..] {
+        args.push("|".to_string());
+        args.extend(stage.iter().cloned());
+    }
+    args
+}
+
+/// Wait for a stage; its exit code and, on Unix, what it used.
+#[cfg(unix)]
+fn reap(child: &mut Child) -> (i32, Option<ResourceUsage>) {
+    match super::rusage::wait(child.id()) {
+        Some((code, usage)) => (code.unwrap_or(-1), Some(usage)),
+        None => (-1, None),
+    }
+}
+
+#[cfg(not(unix))]
+fn reap(child: &mut Child) -> (i32, Option<ResourceUsage>) {
+    (child.wait().map(exit_code).unwrap_or(-1), None)
+}
+
+pub(super) fn exit_code(status: process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+fn report(value: &serde_json::Value) {
+    let mut stdout = std::io::stdout();
+    let _ = writeln!(stdout, "{value}");
+    let _ = stdout.flush();
+}
@@ -0,0 +1,303 @@
+//! `lillux exec port alloc`: free TCP ports for servers about to start.
+//!
+//! Ports are found by binding them. Without `--hold` they are released
+//! as soon as they are printed. With `--hold NAME` a detached holder (this
+//! binary re-executed with `--foreground`) keeps them bound so nothing
+//! else can take them, and `spawn --name NAME --claim-ports` stops the
+//! holder right before starting the server with the ports in its
+//! environment. A reservation nobody claims is dropped after `--ttl`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::time::Duration;
+
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::registry::Registry;
+use super::sys_error::{ErrorCode, SysError};
+
+/// Environment variable listing claimed ports, comma-separated.
+pub const PORTS_ENV: &str = "LILLUX_PORTS";
+
+#[derive(Subcommand)]
+pub enum PortAction {
+    /// Find free TCP ports, optionally keeping them bound for a later spawn
+    Alloc {
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        /// Only ports in `LOW-HIGH`, e.g. `30000-40000`; any port the
+        /// system assigns otherwise
+        #[arg(long)]
+        range: Option<String>,
+        /// Keep the ports bound until `spawn --name NAME --claim-ports`
+        #[arg(long, value_name = "NAME")]
+        hold: Option<String>,
+        /// Drop an unclaimed reservation after this long
+        #[arg(long, default_value = "5m", requires = "hold")]
+        ttl: String,
+        /// Hold the ports in this process instead of detaching a holder
+        #[arg(long, hide = true, requires = "hold")]
+        foreground: bool,
+    },
+}
+
+/// A held reservation, as recorded in `ports/<name>`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Reservation {
+    pid: u32,
+    ports: Vec<u16>,
+    expires_at: String,
+}
+
+pub fn run(registry: Option<&str>, action: PortAction) -> Value {
+    let PortAction::Alloc {
+        count,
+        range,
+        hold,
+        ttl,
+        foreground,
+    } = action;
+    let range = match range.as_deref().map(parse_range).transpose() {
+        Ok(range) => range,
+        Err(e) => return e.failed(),
+    };
+    let Some(name) = hold else {
+        return match bind_free(count, range) {
+            Ok(listeners) => json!({ "success": true, "ports": ports_of(&listeners) }),
+            Err(e) => e.failed(),
+        };
+    };
+    let ttl = match crate::time::parse_duration(&ttl) {
+        Ok(ttl) => ttl,
+        Err(e) => {
+            return SysError::new(ErrorCode::InvalidArgument, e)
+                .failed_with(json!({ "name": name }))
+        }
+    };
+    if foreground {
+        hold_ports(registry, &name, count, range, ttl);
+    }
+    launch(registry, &name, count, range, ttl)
+        .unwrap_or_else(|e| e.failed_with(json!({ "name": name })))
+}
+
+/// Stop the holder of `name`'s reservation and return its ports, which
+/// are free for the caller to hand to the process it starts next.
+pub fn claim(registry: Option<&str>, name: &str) -> Result<Vec<u16>, SysError> {
+    let path = reservation_path(registry, name)?;
+    let reservation = read_reservation(&path).ok_or_else(|| {
+        SysError::new(
+            ErrorCode::NotFound,
+            format!("No ports are reserved for '{name}'"),
+        )
+    })?;
+    let _ = std::fs::remove_file(&path);
+    if !super::is_alive(reservation.pid) {
+        return Err(SysError::new(
+            ErrorCode::NotFound,
+            format!("The port reservation for '{name}' has expired"),
+        ));
+    }
+    super::kill_process(reservation.pid, 1.0)?;
+    Ok(reservation.ports)
+}
+
+/// `LILLUX_PORTS=a,b,c` and `PORT=a` for a process given `ports`.
+pub fn envs(ports: &[u16]) -> Vec<String> {
+    let list: Vec<String> = ports.iter().map(u16::to_string).collect();
+    let mut envs = vec![format!("{PORTS_ENV}={}", list.join(","))];
+    if let Some(first) = list.first() {
+        envs.push(format!("PORT={first}"));
+    }
+    envs
+}
+
+fn reservation_path(registry: Option<&str>, name: &str) -> Result<PathBuf, String> {
+    Registry::locate(registry)?.state_file("ports", name)
+}
+
+fn read_reservation(path: &Path) -> Option<Reservation> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+fn parse_range(raw: &str) -> Result<(u16, u16), SysError> {
+    let invalid = || {
+        SysError::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid port range '{raw}': expected LOW-HIGH, e.g. 30000-40000"),
+        )
+    };
+    let (low, high) = raw.split_once('-').ok_or_else(invalid)?;
+    let low: u16 = low.trim().parse().map_err(|_| invalid())?;
+    let high: u16 = high.trim().parse().map_err(|_| invalid())?;
+    if low == 0 || low > high {
+        return Err(invalid());
+    }
+    Ok((low, high))
+}
+
+/// Bind `count` distinct free ports on all interfaces. In a range the
+/// search starts at a random port so concurrent callers rarely collide.
+fn bind_free(count: usize, range: Option<(u16, u16)>) -> Result<Vec<TcpListener>, SysError> {
+    if count == 0 {
+        return Err(SysError::new(
+            ErrorCode::InvalidArgument,
+            "--count must be at least 1",
+        ));
+    }
+    let bind = |port: u16| TcpListener::bind((Ipv4Addr::UNSPECIFIED, port));
+    let Some((low, high)) = range else {
+        return (0..count)
+            .map(|_| bind(0).map_err(|e| SysError::io("Failed to bind a free port", "bind", &e)))
+            .collect();
+    };
+    let size = u32::from(high - low) + 1;
+    let start = rand::random::<u32>() % size;
+    let listeners: Vec<TcpListener> = (0..size)
+        .map(|offset| low + ((start + offset) % size) as u16)
+        .filter_map(|port| bind(port).ok())
+        .take(count)
+        .collect();
+    if listeners.len() < count {
+        return Err(format!(
+            "Only {} of {count} ports in {low}-{high} are free",
+            listeners.len()
+        )
+        .into());
+    }
+    Ok(listeners)
+}
+
+fn ports_of(listeners: &[TcpListener]) -> Vec<u16> {
+    listeners
+        .iter()
+        .filter_map(|listener| listener.local_addr().ok())
+        .map(|addr| addr.port())
+        .collect()
+}
+
+/// Run as the detached holder: bind the ports, record and report them,
+/// then keep them until killed by a claim or until `ttl` passes.
+fn hold_ports(
+    registry: Option<&str>,
+    name: &str,
+    count: usize,
+    range: Option<(u16, u16)>,
+    ttl: Duration,
+) -> ! {
+    let report = |value: Value| {
+        let mut stdout = std::io::stdout();
+        let _ = writeln!(stdout, "{value}");
+        let _ = stdout.flush();
+    };
+    let held = (|| -> Result<_, SysError> {
+        let path = reservation_path(registry, name)?;
+        if read_reservation(&path).is_some_and(|held| super::is_alive(held.pid)) {
+            return Err(SysError::new(
+                ErrorCode::AlreadyExists,
+                format!("Ports are already reserved for '{name}'"),
+            ));
+        }
+        let listeners = bind_free(count, range)?;
+        let expires = crate::time::timestamp_millis() as u64 / 1000 + ttl.as_secs();
+        let reservation = Reservation {
+            pid: process::id(),
+            ports: ports_of(&listeners),
+            expires_at: crate::time::iso8601_from_unix_secs(expires),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_vec(&reservation).unwrap_or_default())
+            .map_err(|e| format!("Failed to record reservation {}: {e}", path.display()))?;
+        Ok((path, listeners, reservation))
+    })();
+    let (path, _listeners, reservation) = match held {
+        Ok(held) => held,
+        Err(e) => {
+            report(e.failed_with(json!({ "name": name })));
+            process::exit(crate::output::EXIT_FAILED);
+        }
+    };
+    report(json!({
+        "success": true,
+        "name": name,
+        "ports": reservation.ports,
+        "pid": reservation.pid,
+        "expires_at": reservation.expires_at,
+    }));
+    std::thread::sleep(ttl);
+    // Unclaimed: drop the record unless a new reservation replaced it.
+    let ours = read_reservation(&path).is_some_and(|current| current.pid == reservation.pid);
+    if ours {
+        let _ = std::fs::remove_file(&path);
+    }
+    process::exit(crate::output::EXIT_OK);
+}
+
+/// Start a detached holder for `name` and return its report.
+fn launch(
+    registry: Option<&str>,
+    name: &str,
+    count: usize,
+    range: Option<(u16, u16)>,
+    ttl: Duration,
+) -> Result<Value, SysError> {
+    let registry = Registry::locate(registry)?;
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let mut command = process::Command::new(exe);
+    command
+        .arg("exec")
+        .arg("--registry")
+        .arg(registry.path())
+        .args(["port", "alloc", "--hold", name, "--foreground"])
+        .args(["--count", &count.to_string()])
+        .args(["--ttl", &format!("{}ms", ttl.as_millis())])
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    if let Some((low, high)) = range {
+        command.args(["--range", &format!("{low}-{high}")]);
+    }
+    super::lock::detach(&mut command);
+    let mut holder = command
+        .spawn()
+        .map_err(|e| format!("Failed to start port holder: {e}"))?;
+    let mut line = String::new();
+    if let Some(stdout) = holder.stdout.take() {
+        let _ = BufReader::new(stdout).read_line(&mut line);
+    }
+    let report: Value = serde_json::from_str(&line)
+        .map_err(|_| "Port holder exited before reporting".to_string())?;
+    if report.get("error").is_some() {
+        let _ = holder.wait();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranged_allocation_binds_distinct_ports_inside_the_range() {
+        let listeners = bind_free(3, Some((30000, 40000))).unwrap();
+        let mut ports = ports_of(&listeners);
+        assert!(ports.iter().all(|port| (30000..=40000).contains(port)));
+        ports.sort();
+        ports.dedup();
+        assert_eq!(ports.len(), 3);
+
+        let taken = ports[0];
+        assert!(bind_free(1, Some((taken, taken))).is_err());
+        assert!(parse_range("40000-30000").is_err());
+        assert_eq!(envs(&[8080, 8081]), ["LILLUX_PORTS=8080,8081", "PORT=8080"]);
+    }
+}
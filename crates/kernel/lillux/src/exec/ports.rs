@@ -0,0 +1,204 @@
+//! Listening sockets owned by a process, for `lillux exec ports`.
+//!
+//! Linux matches the socket inodes in `/proc/<pid>/fd` against the
+//! process's own network namespace tables (`/proc/<pid>/net/*`). Other
+//! Unix platforms ask `lsof`. TCP sockets are reported only in `LISTEN`;
+//! UDP sockets are reported whenever they are bound to a local port.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ListeningSocket {
+    pub pid: u32,
+    pub protocol: &'static str,
+    pub address: String,
+    pub port: u16,
+}
+
+/// Listening sockets of `pids`, sorted and de-duplicated (a socket shared
+/// across a fork is reported once per owning PID).
+#[cfg(target_os = "linux")]
+pub fn listening(pids: &[u32]) -> Result<Vec<ListeningSocket>, String> {
+    use std::collections::HashMap;
+
+    let mut sockets = Vec::new();
+    for &pid in pids {
+        let inodes = socket_inodes(pid);
+        if inodes.is_empty() {
+            continue;
+        }
+        let mut by_inode: HashMap<u64, (&'static str, String, u16)> = HashMap::new();
+        for (file, protocol) in [
+            ("tcp", "tcp"),
+            ("tcp6", "tcp6"),
+            ("udp", "udp"),
+            ("udp6", "udp6"),
+        ] {
+            let Ok(table) = std::fs::read_to_string(format!("/proc/{pid}/net/{file}")) else {
+                continue;
+            };
+            for (inode, address, port) in parse_net_table(&table, protocol.starts_with("tcp")) {
+                by_inode.insert(inode, (protocol, address, port));
+            }
+        }
+        for inode in inodes {
+            if let Some((protocol, address, port)) = by_inode.get(&inode) {
+                sockets.push(ListeningSocket {
+                    pid,
+                    protocol,
+                    address: address.clone(),
+                    port: *port,
+                });
+            }
+        }
+    }
+    sockets.sort();
+    sockets.dedup();
+    Ok(sockets)
+}
+
+#[cfg(target_os = "linux")]
+fn socket_inodes(pid: u32) -> Vec<u64> {
+    let Ok(fds) = std::fs::read_dir(format!("/proc/{pid}/fd")) else {
+        return Vec::new();
+    };
+    fds.filter_map(Result::ok)
+        .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+        .filter_map(|target| {
+            target
+                .to_str()?
+                .strip_prefix("socket:[")?
+                .strip_suffix(']')?
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+/// Parse a `/proc/net/{tcp,udp}[6]` table into `(inode, address, port)`
+/// for listening TCP (`st == 0A`) or bound UDP rows.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_net_table(table: &str, tcp: bool) -> Vec<(u64, String, u16)> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (address, port) = fields.get(1)?.split_once(':')?;
+            let state = fields.get(3)?;
+            let inode = fields.get(9)?.parse::<u64>().ok()?;
+            let port = u16::from_str_radix(port, 16).ok()?;
+            if (tcp && *state != "0A") || port == 0 || inode == 0 {
+                return None;
+            }
+            Some((inode, decode_address(address)?, port))
+        })
+        .collect()
+}
+
+/// `/proc/net` addresses are hex words in host (little-endian) order.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn decode_address(hex: &str) -> Option<String> {
+    let words: Vec<u32> = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16).ok())
+        .collect::<Option<_>>()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    match bytes.len() {
+        4 => Some(std::net::Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            Some(std::net::Ipv6Addr::from(octets).to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn listening(pids: &[u32]) -> Result<Vec<ListeningSocket>, String> {
+    if pids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let list: Vec<String> = pids.iter().map(u32::to_string).collect();
+    let output = std::process::Command::new("lsof")
+        .args(["-nP", "-a", "-p", &list.join(","), "-i", "-FpPnT"])
+        .output()
+        .map_err(|e| format!("Failed to run lsof: {e}"))?;
+    let mut sockets = Vec::new();
+    let mut pid = 0u32;
+    let mut protocol = String::new();
+    let mut name: Option<String> = None;
+    let mut listening_state = false;
+    let mut flush = |pid: u32, protocol: &str, name: Option<String>, listen: bool| {
+        let Some(name) = name else { return };
+        let tcp = protocol.eq_ignore_ascii_case("tcp");
+        if (tcp && !listen) || name.contains("->") {
+            return;
+        }
+        let Some((address, port)) = name.rsplit_once(':') else {
+            return;
+        };
+        let Ok(port) = port.parse::<u16>() else {
+            return;
+        };
+        let ipv6 = address.starts_with('[');
+        sockets.push(ListeningSocket {
+            pid,
+            protocol: match (tcp, ipv6) {
+                (true, false) => "tcp",
+                (true, true) => "tcp6",
+                (false, false) => "udp",
+                (false, true) => "udp6",
+            },
+            address: address.trim_matches(['[', ']']).to_string(),
+            port,
+        });
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (tag, value) = line.split_at(1.min(line.len()));
+        match tag {
+            "p" | "f" => {
+                flush(pid, &protocol, name.take(), listening_state);
+                listening_state = false;
+                if tag == "p" {
+                    pid = value.parse().unwrap_or(0);
+                }
+            }
+            "P" => protocol = value.to_string(),
+            "n" => name = Some(value.to_string()),
+            "T" => listening_state |= value == "ST=LISTEN",
+            _ => {}
+        }
+    }
+    flush(pid, &protocol, name.take(), listening_state);
+    sockets.sort();
+    sockets.dedup();
+    Ok(sockets)
+}
+
+#[cfg(not(unix))]
+pub fn listening(_pids: &[u32]) -> Result<Vec<ListeningSocket>, String> {
+    Err("Socket inspection is not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_table_keeps_listening_tcp_and_bound_udp() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 111 1
+   1: 0100007F:A1B2 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 222 1";
+        assert_eq!(
+            parse_net_table(tcp, true),
+            vec![(111, "127.0.0.1".to_string(), 8080)]
+        );
+
+        let udp6 = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:0035 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 333 2";
+        assert_eq!(
+            parse_net_table(udp6, false),
+            vec![(333, "::1".to_string(), 53)]
+        );
+    }
+}
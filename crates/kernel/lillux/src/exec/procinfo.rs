@@ -0,0 +1,720 @@
+//! Read-only process inspection for `lillux exec status`.
+//!
+//! Linux reads `/proc` directly. Other Unix platforms fall back to a
+//! single `ps` invocation, which cannot report the working directory
+//! and only yields a whitespace-split command line. Windows asks a query
+//! handle for the image name, CPU and start times, and the working set;
+//! the rest would mean reading the process's memory. Fields a platform
+//! cannot observe are `None` rather than guessed.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Point-in-time facts about a live process.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub name: Option<String>,
+    /// Scheduler state, as named by [`state_name`]: `running`,
+    /// `sleeping`, `stopped`, `zombie`, ...
+    pub state: Option<&'static str>,
+    pub cmdline: Option<Vec<String>>,
+    pub cwd: Option<String>,
+    pub rss_bytes: Option<u64>,
+    /// Lifetime average CPU usage (user + system time over elapsed wall
+    /// time), matching `ps`'s `%cpu`. May exceed 100 on multi-core hosts.
+    pub cpu_percent: Option<f64>,
+    /// Process start as Unix milliseconds.
+    pub start_time_ms: Option<u64>,
+    pub uptime_ms: Option<u64>,
+}
+
+impl ProcessInfo {
+    /// Whether it has exited and only waits for its parent to collect the
+    /// exit status.
+    pub fn is_defunct(&self) -> bool {
+        matches!(self.state, Some("zombie" | "dead"))
+    }
+
+    /// JSON shape used by the CLI: `ProcessInfo` plus `alive` (false for
+    /// a zombie) and an ISO-8601 rendering of the start time.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.insert(
+                "alive".to_string(),
+                serde_json::Value::Bool(!self.is_defunct()),
+            );
+            object.insert(
+                "start_time".to_string(),
+                self.start_time_ms
+                    .map(|ms| crate::time::iso8601_from_unix_secs(ms / 1000).into())
+                    .unwrap_or(serde_json::Value::Null),
+            );
+        }
+        value
+    }
+}
+
+/// One row of a process-table snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessSummary {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    pub state: &'static str,
+}
+
+/// Direct and transitive children of `root` as nested JSON
+/// (`{pid, name, state, children}`), or `None` if `root` is absent.
+pub fn descendant_tree(root: u32) -> Result<Option<serde_json::Value>, String> {
+    let table = snapshot()?;
+    let Some(node) = table.iter().find(|p| p.pid == root) else {
+        return Ok(None);
+    };
+    let mut children: std::collections::HashMap<u32, Vec<&ProcessSummary>> =
+        std::collections::HashMap::new();
+    for process in &table {
+        if process.pid != process.ppid {
+            children.entry(process.ppid).or_default().push(process);
+        }
+    }
+    fn build(
+        node: &ProcessSummary,
+        children: &std::collections::HashMap<u32, Vec<&ProcessSummary>>,
+    ) -> serde_json::Value {
+        let kids: Vec<serde_json::Value> = children
+            .get(&node.pid)
+            .map(|kids| kids.iter().map(|kid| build(kid, children)).collect())
+            .unwrap_or_default();
+        serde_json::json!({
+            "pid": node.pid,
+            "name": node.name,
+            "state": node.state,
+            "children": kids,
+        })
+    }
+    Ok(Some(build(node, &children)))
+}
+
+/// Map a `/proc` or `ps` state letter to a stable name.
+pub fn state_name(code: char) -> &'static str {
+    match code {
+        'R' => "running",
+        'S' => "sleeping",
+        'D' | 'U' => "disk_sleep",
+        'T' | 't' => "stopped",
+        'Z' => "zombie",
+        'X' | 'x' => "dead",
+        'I' => "idle",
+        _ => "unknown",
+    }
+}
+
+/// The scheduler state of `pid` (see [`state_name`]), if it exists.
+#[cfg(target_os = "linux")]
+pub fn state(pid: u32) -> Option<&'static str> {
+    linux::read_stat(pid).map(|stat| state_name(stat.state))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn state(pid: u32) -> Option<&'static str> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "state=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let code = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .chars()
+        .next()?;
+    Some(state_name(code))
+}
+
+#[cfg(not(unix))]
+pub fn state(_pid: u32) -> Option<&'static str> {
+    None
+}
+
+/// Every process visible to the caller, ordered by PID.
+#[cfg(target_os = "linux")]
+pub fn snapshot() -> Result<Vec<ProcessSummary>, String> {
+    let mut table: Vec<ProcessSummary> = std::fs::read_dir("/proc")
+        .map_err(|e| format!("Failed to read /proc: {e}"))?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = linux::read_stat(pid)?;
+            Some(ProcessSummary {
+                pid,
+                ppid: stat.ppid,
+                name: stat.comm,
+                state: state_name(stat.state),
+            })
+        })
+        .collect();
+    table.sort_by_key(|p| p.pid);
+    Ok(table)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn snapshot() -> Result<Vec<ProcessSummary>, String> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,state=,comm="])
+        .output()
+        .map_err(|e| format!("Failed to run ps: {e}"))?;
+    let mut table: Vec<ProcessSummary> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let state = state_name(fields.next()?.chars().next()?);
+            let name = fields.collect::<Vec<_>>().join(" ");
+            Some(ProcessSummary {
+                pid,
+                ppid,
+                name,
+                state,
+            })
+        })
+        .collect();
+    table.sort_by_key(|p| p.pid);
+    Ok(table)
+}
+
+#[cfg(not(unix))]
+pub fn snapshot() -> Result<Vec<ProcessSummary>, String> {
+    Err("Process table enumeration is not supported on this platform".to_string())
+}
+
+/// The members of process group `pgid`, ordered by PID.
+#[cfg(target_os = "linux")]
+pub fn group(pgid: u32) -> Result<Vec<ProcessSummary>, String> {
+    let mut members: Vec<ProcessSummary> = std::fs::read_dir("/proc")
+        .map_err(|e| format!("Failed to read /proc: {e}"))?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = linux::read_stat(pid).filter(|stat| stat.pgid == pgid)?;
+            Some(ProcessSummary {
+                pid,
+                ppid: stat.ppid,
+                name: stat.comm,
+                state: state_name(stat.state),
+            })
+        })
+        .collect();
+    members.sort_by_key(|p| p.pid);
+    Ok(members)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn group(pgid: u32) -> Result<Vec<ProcessSummary>, String> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,pgid=,state=,comm="])
+        .output()
+        .map_err(|e| format!("Failed to run ps: {e}"))?;
+    let mut members: Vec<ProcessSummary> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            if fields.next()?.parse::<u32>().ok()? != pgid {
+                return None;
+            }
+            let state = state_name(fields.next()?.chars().next()?);
+            let name = fields.collect::<Vec<_>>().join(" ");
+            Some(ProcessSummary {
+                pid,
+                ppid,
+                name,
+                state,
+            })
+        })
+        .collect();
+    members.sort_by_key(|p| p.pid);
+    Ok(members)
+}
+
+#[cfg(not(unix))]
+pub fn group(_pgid: u32) -> Result<Vec<ProcessSummary>, String> {
+    Err("Process groups are not supported on this platform".to_string())
+}
+
+/// Open descriptor usage of a process.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FdUsage {
+    pub count: usize,
+    /// Soft `RLIMIT_NOFILE` of the process, when observable.
+    pub soft_limit: Option<u64>,
+    /// Per-descriptor targets, only collected on request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<OpenFile>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenFile {
+    pub fd: String,
+    pub target: String,
+}
+
+/// Count (and with `detail`, list) the open descriptors of `pid`.
+#[cfg(target_os = "linux")]
+pub fn fd_usage(pid: u32, detail: bool) -> Result<FdUsage, String> {
+    let dir = format!("/proc/{pid}/fd");
+    let mut files: Vec<OpenFile> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read {dir}: {e}"))?
+        .filter_map(Result::ok)
+        .map(|entry| OpenFile {
+            fd: entry.file_name().to_string_lossy().into_owned(),
+            target: std::fs::read_link(entry.path())
+                .map(|target| target.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+        .collect();
+    files.sort_by_key(|file| file.fd.parse::<u64>().unwrap_or(u64::MAX));
+    let soft_limit = std::fs::read_to_string(format!("/proc/{pid}/limits"))
+        .ok()
+        .and_then(|limits| {
+            limits
+                .lines()
+                .find_map(|line| line.strip_prefix("Max open files"))
+                .and_then(|rest| rest.split_whitespace().next()?.parse().ok())
+        });
+    Ok(FdUsage {
+        count: files.len(),
+        soft_limit,
+        files: detail.then_some(files),
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn fd_usage(pid: u32, detail: bool) -> Result<FdUsage, String> {
+    let output = std::process::Command::new("lsof")
+        .args(["-nP", "-p", &pid.to_string(), "-Ffn"])
+        .output()
+        .map_err(|e| format!("Failed to run lsof: {e}"))?;
+    let mut files: Vec<OpenFile> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(fd) = line.strip_prefix('f') {
+            files.push(OpenFile {
+                fd: fd.to_string(),
+                target: String::new(),
+            });
+        } else if let (Some(name), Some(last)) = (line.strip_prefix('n'), files.last_mut()) {
+            last.target = name.to_string();
+        }
+    }
+    // lsof also lists cwd, text, and memory-mapped entries; keep numeric fds.
+    files.retain(|file| file.fd.parse::<u64>().is_ok());
+    Ok(FdUsage {
+        count: files.len(),
+        soft_limit: None,
+        files: detail.then_some(files),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn fd_usage(_pid: u32, _detail: bool) -> Result<FdUsage, String> {
+    Err("Descriptor inspection is not supported on this platform".to_string())
+}
+
+/// Cumulative resource counters of a process at one instant. Rates (CPU
+/// percentage) come from differencing two samples.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceSample {
+    pub timestamp_ms: u64,
+    /// User + system CPU time consumed so far.
+    pub cpu_time_ms: Option<u64>,
+    pub rss_bytes: Option<u64>,
+    pub threads: Option<u64>,
+    /// Bytes read from / written to storage (`/proc/<pid>/io`), which is
+    /// only readable for processes the caller may ptrace.
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn resource_sample(pid: u32) -> Option<ResourceSample> {
+    let stat = linux::read_stat(pid)?;
+    let io = std::fs::read_to_string(format!("/proc/{pid}/io")).ok();
+    let io_field = |key: &str| {
+        io.as_deref()?
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some(ResourceSample {
+        timestamp_ms: now_ms(),
+        cpu_time_ms: Some((stat.utime + stat.stime) * 1000 / linux::clock_ticks()),
+        rss_bytes: Some(stat.rss_pages * linux::page_size()),
+        threads: Some(stat.threads),
+        read_bytes: io_field("read_bytes:"),
+        write_bytes: io_field("write_bytes:"),
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn resource_sample(pid: u32) -> Option<ResourceSample> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=,time=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    let rss_bytes = fields.next()?.parse::<u64>().ok().map(|kb| kb * 1024);
+    // `time` is `[dd-][hh:]mm:ss[.cc]`; drop the fraction.
+    let cpu_time_ms = fields
+        .next()
+        .and_then(|raw| parse_ps_etime(raw.split('.').next()?))
+        .map(|secs| secs * 1000);
+    Some(ResourceSample {
+        timestamp_ms: now_ms(),
+        cpu_time_ms,
+        rss_bytes,
+        threads: None,
+        read_bytes: None,
+        write_bytes: None,
+    })
+}
+
+#[cfg(windows)]
+pub fn resource_sample(pid: u32) -> Option<ResourceSample> {
+    if !super::lib_is_alive(pid) {
+        return None;
+    }
+    let counters = windows::counters(pid).unwrap_or_default();
+    Some(ResourceSample {
+        timestamp_ms: now_ms(),
+        cpu_time_ms: counters.cpu_time_ms,
+        rss_bytes: counters.rss_bytes,
+        threads: None,
+        read_bytes: None,
+        write_bytes: None,
+    })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Inspect `pid`. Returns `None` when the process does not exist.
+#[cfg(target_os = "linux")]
+pub fn inspect(pid: u32) -> Option<ProcessInfo> {
+    let stat = linux::read_stat(pid)?;
+    let now = now_ms();
+    let start_time_ms =
+        linux::boot_time_ms().map(|boot| boot + stat.start_ticks * 1000 / linux::clock_ticks());
+    let uptime_ms = start_time_ms.map(|start| now.saturating_sub(start));
+    let cpu_percent = uptime_ms.filter(|ms| *ms > 0).map(|ms| {
+        let cpu_ms = (stat.utime + stat.stime) * 1000 / linux::clock_ticks();
+        round_percent(cpu_ms as f64 * 100.0 / ms as f64)
+    });
+    Some(ProcessInfo {
+        pid,
+        ppid: Some(stat.ppid),
+        name: Some(stat.comm),
+        state: Some(state_name(stat.state)),
+        cmdline: linux::read_cmdline(pid),
+        cwd: std::fs::read_link(format!("/proc/{pid}/cwd"))
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned()),
+        rss_bytes: Some(stat.rss_pages * linux::page_size()),
+        cpu_percent,
+        start_time_ms,
+        uptime_ms,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn inspect(pid: u32) -> Option<ProcessInfo> {
+    let output = std::process::Command::new("ps")
+        .args([
+            "-o",
+            "ppid=,state=,rss=,%cpu=,etime=,comm=",
+            "-p",
+            &pid.to_string(),
+        ])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let line = text.lines().find(|line| !line.trim().is_empty())?;
+    let mut fields = line.split_whitespace();
+    let ppid = fields.next().and_then(|v| v.parse().ok());
+    let state = fields.next().and_then(|v| v.chars().next()).map(state_name);
+    let rss_bytes = fields
+        .next()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(|kb| kb * 1024);
+    let cpu_percent = fields.next().and_then(|v| v.parse().ok());
+    let uptime_ms = fields
+        .next()
+        .and_then(parse_ps_etime)
+        .map(|secs| secs * 1000);
+    let name = fields.collect::<Vec<_>>().join(" ");
+    let cmdline = std::process::Command::new("ps")
+        .args(["-o", "command=", "-p", &pid.to_string()])
+        .output()
+        .ok()
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect()
+        });
+    Some(ProcessInfo {
+        pid,
+        ppid,
+        name: (!name.is_empty()).then_some(name),
+        state,
+        cmdline,
+        cwd: None,
+        rss_bytes,
+        cpu_percent,
+        start_time_ms: uptime_ms.map(|ms| now_ms().saturating_sub(ms)),
+        uptime_ms,
+    })
+}
+
+/// A process this user may not query is still reported, with only its
+/// PID.
+#[cfg(windows)]
+pub fn inspect(pid: u32) -> Option<ProcessInfo> {
+    if !super::lib_is_alive(pid) {
+        return None;
+    }
+    let counters = windows::counters(pid).unwrap_or_default();
+    let uptime_ms = counters
+        .start_time_ms
+        .map(|start| now_ms().saturating_sub(start));
+    let cpu_percent = counters
+        .cpu_time_ms
+        .zip(uptime_ms.filter(|ms| *ms > 0))
+        .map(|(cpu_ms, ms)| round_percent(cpu_ms as f64 * 100.0 / ms as f64));
+    Some(ProcessInfo {
+        pid,
+        name: counters.name,
+        rss_bytes: counters.rss_bytes,
+        cpu_percent,
+        start_time_ms: counters.start_time_ms,
+        uptime_ms,
+        ..ProcessInfo::default()
+    })
+}
+
+/// Parse `ps` elapsed time (`[[dd-]hh:]mm:ss`) into seconds.
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+pub(super) fn parse_ps_etime(raw: &str) -> Option<u64> {
+    let (days, clock) = match raw.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, raw),
+    };
+    let mut secs = 0u64;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(days * 86_400 + secs)
+}
+
+#[cfg_attr(all(unix, not(target_os = "linux")), allow(dead_code))]
+fn round_percent(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::sync::OnceLock;
+
+    pub(super) struct Stat {
+        pub comm: String,
+        pub state: char,
+        pub threads: u64,
+        pub ppid: u32,
+        pub pgid: u32,
+        pub utime: u64,
+        pub stime: u64,
+        pub start_ticks: u64,
+        pub rss_pages: u64,
+    }
+
+    /// Parse `/proc/<pid>/stat`. The command name is parenthesised and may
+    /// itself contain spaces or `)`, so fields are split after the last `)`.
+    pub(super) fn read_stat(pid: u32) -> Option<Stat> {
+        let raw = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        let open = raw.find('(')?;
+        let close = raw.rfind(')')?;
+        let comm = raw.get(open + 1..close)?.to_string();
+        let fields: Vec<&str> = raw[close + 1..].split_whitespace().collect();
+        let field = |index: usize| fields.get(index).and_then(|v| v.parse::<u64>().ok());
+        Some(Stat {
+            comm,
+            state: fields.first()?.chars().next()?,
+            ppid: field(1)? as u32,
+            pgid: field(2)? as u32,
+            utime: field(11)?,
+            stime: field(12)?,
+            threads: field(17)?,
+            start_ticks: field(19)?,
+            rss_pages: field(21)?,
+        })
+    }
+
+    pub(super) fn read_cmdline(pid: u32) -> Option<Vec<String>> {
+        let raw = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+        Some(
+            raw.split(|byte| *byte == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect(),
+        )
+    }
+
+    pub(super) fn clock_ticks() -> u64 {
+        static TICKS: OnceLock<u64> = OnceLock::new();
+        *TICKS.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+            ticks if ticks > 0 => ticks as u64,
+            _ => 100,
+        })
+    }
+
+    pub(super) fn page_size() -> u64 {
+        static PAGE: OnceLock<u64> = OnceLock::new();
+        *PAGE.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as u64,
+            _ => 4096,
+        })
+    }
+
+    /// Boot time as Unix milliseconds, from the `btime` line of `/proc/stat`.
+    pub(super) fn boot_time_ms() -> Option<u64> {
+        static BOOT: OnceLock<Option<u64>> = OnceLock::new();
+        *BOOT.get_or_init(|| {
+            let raw = std::fs::read_to_string("/proc/stat").ok()?;
+            raw.lines()
+                .find_map(|line| line.strip_prefix("btime "))
+                .and_then(|secs| secs.trim().parse::<u64>().ok())
+                .map(|secs| secs * 1000)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ps_etime_accepts_every_documented_shape() {
+        assert_eq!(parse_ps_etime("05"), Some(5));
+        assert_eq!(parse_ps_etime("01:05"), Some(65));
+        assert_eq!(parse_ps_etime("02:01:05"), Some(7265));
+        assert_eq!(parse_ps_etime("3-02:01:05"), Some(3 * 86_400 + 7265));
+        assert_eq!(parse_ps_etime("x:05"), None);
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, HANDLE};
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ,
+    };
+
+    /// Milliseconds from 1601-01-01, where a `FILETIME` counts from, to
+    /// the Unix epoch.
+    const UNIX_EPOCH_MS: u64 = 11_644_473_600_000;
+
+    #[derive(Default)]
+    pub(super) struct Counters {
+        pub name: Option<String>,
+        pub start_time_ms: Option<u64>,
+        /// User + kernel CPU time consumed so far.
+        pub cpu_time_ms: Option<u64>,
+        /// The working set.
+        pub rss_bytes: Option<u64>,
+    }
+
+    struct Process(HANDLE);
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// What a query handle on `pid` reports, or `None` if this user may
+    /// not open one.
+    pub(super) fn counters(pid: u32) -> Option<Counters> {
+        // The memory counters also need PROCESS_VM_READ; without it the
+        // times and name are still there.
+        let process = [
+            PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ,
+            PROCESS_QUERY_LIMITED_INFORMATION,
+        ]
+        .into_iter()
+        .map(|access| unsafe { OpenProcess(access, 0, pid) })
+        .find(|handle| !handle.is_null())
+        .map(Process)?;
+        let times = times(&process);
+        Some(Counters {
+            name: image_name(&process),
+            start_time_ms: times.map(|(start, _)| start),
+            cpu_time_ms: times.map(|(_, cpu)| cpu),
+            rss_bytes: working_set(&process),
+        })
+    }
+
+    /// Start as Unix milliseconds, and CPU time in milliseconds.
+    fn times(process: &Process) -> Option<(u64, u64)> {
+        let zero = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut created, mut exited, mut kernel, mut user) = (zero, zero, zero, zero);
+        let ok = unsafe {
+            GetProcessTimes(process.0, &mut created, &mut exited, &mut kernel, &mut user)
+        };
+        // FILETIMEs count 100ns intervals.
+        (ok != 0).then(|| {
+            (
+                (ticks(created) / 10_000).saturating_sub(UNIX_EPOCH_MS),
+                (ticks(kernel) + ticks(user)) / 10_000,
+            )
+        })
+    }
+
+    fn ticks(time: FILETIME) -> u64 {
+        (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime)
+    }
+
+    fn working_set(process: &Process) -> Option<u64> {
+        let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+        counters.cb = size;
+        let ok = unsafe { K32GetProcessMemoryInfo(process.0, &mut counters, size) };
+        (ok != 0).then_some(counters.WorkingSetSize as u64)
+    }
+
+    /// The file name of the executable, as `/proc/<pid>/comm` would give.
+    fn image_name(process: &Process) -> Option<String> {
+        let mut path = [0u16; 1024];
+        let mut len = path.len() as u32;
+        let ok = unsafe {
+            QueryFullProcessImageNameW(process.0, PROCESS_NAME_WIN32, path.as_mut_ptr(), &mut len)
+        };
+        if ok == 0 {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&path[..len as usize]);
+        path.rsplit('\\').next().map(str::to_string)
+    }
+}
@@ -0,0 +1,257 @@
+//! `lillux exec proxy --cmd ...`: run a command in the foreground in
+//! lillux's place, e.g. as a container `ENTRYPOINT` shim.
+//!
+//! The child inherits stdin, the environment (plus `--env`), and, unless
+//! `--log` asks for a copy, stdout and stderr. SIGINT, SIGTERM, and SIGHUP
+//! sent to lillux are passed on to the child; one the terminal raised has
+//! already reached it, since it shares lillux's process group. On Windows
+//! the child shares the console and gets Ctrl+C and Ctrl+Break itself;
+//! lillux only outlives them. The child's exit status, or `128 + signal`,
+//! becomes lillux's.
+//!
+//! Running as PID 1, lillux also reaps the orphans the kernel hands it, so
+//! they do not pile up as zombies in the container.
+
+use std::io::{Read, Write};
+use std::process::{self, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// What `--timeout` exits with, as for `exec stream`.
+pub const TIMED_OUT: i32 = 124;
+
+/// How long the child has between SIGTERM and SIGKILL once `--timeout`
+/// expires.
+#[cfg(unix)]
+const KILL_GRACE: Duration = Duration::from_secs(3);
+
+pub struct Proxy {
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub envs: Vec<String>,
+    pub log: Option<String>,
+    pub max_open_files: Option<u64>,
+    pub timeout: Option<Duration>,
+}
+
+/// Run `proxy`'s command to completion; its exit status.
+pub fn run(proxy: &Proxy) -> Result<i32, String> {
+    let mut command = process::Command::new(&proxy.cmd);
+    command.args(&proxy.args);
+    super::set_envs(&mut command, &proxy.envs);
+    if let Some(dir) = &proxy.cwd {
+        command.current_dir(dir);
+    }
+    let limits = super::SubprocessLimits {
+        max_open_files: proxy.max_open_files,
+        ..Default::default()
+    };
+    super::configure_subprocess_limits(&mut command, Some(&limits))?;
+    let log = match &proxy.log {
+        Some(path) => Some(Arc::new(Mutex::new(super::open_log(path)?))),
+        None => None,
+    };
+    if log.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut child =
+        signals::spawn(&mut command).map_err(|e| format!("Failed to spawn {}: {e}", proxy.cmd))?;
+    let copies: Vec<JoinHandle<()>> = log
+        .map(|log| {
+            let stdout = child.stdout.take().map(|from| {
+                let log = Arc::clone(&log);
+                thread::spawn(move || tee(from, std::io::stdout(), &log))
+            });
+            let stderr = child
+                .stderr
+                .take()
+                .map(|from| thread::spawn(move || tee(from, std::io::stderr(), &log)));
+            stdout.into_iter().chain(stderr).collect()
+        })
+        .unwrap_or_default();
+
+    let (code, timed_out) = signals::wait(&mut child, proxy.timeout);
+    for copy in copies {
+        let _ = copy.join();
+    }
+    Ok(if timed_out { TIMED_OUT } else { code })
+}
+
+/// Copy the child's `from` to our `to` and into the log, chunk by chunk.
+fn tee(mut from: impl Read, mut to: impl Write, log: &Mutex<std::fs::File>) {
+    let mut buf = [0u8; 8192];
+    loop {
+        match from.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let _ = to.write_all(&buf[..n]);
+                let _ = to.flush();
+                let mut log = log.lock().unwrap_or_else(|error| error.into_inner());
+                let _ = log.write_all(&buf[..n]);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::process::{self, Child};
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Signals passed on to the child.
+    const FORWARDED: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+    /// The child's PID while it runs; 0 before and after.
+    static CHILD: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn forward(
+        signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        _context: *mut libc::c_void,
+    ) {
+        // The terminal signals the whole foreground process group, child
+        // included; passing it on would deliver it twice.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if unsafe { (*info).si_code } == libc::SI_KERNEL {
+            return;
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let _ = info;
+        let pid = CHILD.load(Ordering::SeqCst);
+        if pid > 0 {
+            unsafe { libc::kill(pid, signal) };
+        }
+    }
+
+    fn mask(how: libc::c_int) {
+        unsafe {
+            let mut set: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for signal in FORWARDED {
+                libc::sigaddset(&mut set, signal);
+            }
+            libc::pthread_sigmask(how, &set, std::ptr::null_mut());
+        }
+    }
+
+    /// Spawn `command` with the forwarding handlers installed. The
+    /// signals are held back until the child's PID is known, so none is
+    /// lost or kills lillux in between; the child unblocks them before
+    /// exec, which restores their default dispositions.
+    pub fn spawn(command: &mut process::Command) -> std::io::Result<Child> {
+        use std::os::unix::process::CommandExt;
+        mask(libc::SIG_BLOCK);
+        unsafe {
+            command.pre_exec(|| {
+                mask(libc::SIG_UNBLOCK);
+                Ok(())
+            });
+        }
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = forward as *const () as libc::sighandler_t;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            for signal in FORWARDED {
+                libc::sigaction(signal, &action, std::ptr::null_mut());
+            }
+        }
+        let child = command.spawn();
+        if let Ok(child) = &child {
+            CHILD.store(child.id() as i32, Ordering::SeqCst);
+        }
+        mask(libc::SIG_UNBLOCK);
+        child
+    }
+
+    /// Wait for the child, reaping any other child along the way (orphans
+    /// adopted as PID 1); its exit code and whether `timeout` expired.
+    pub fn wait(child: &mut Child, timeout: Option<Duration>) -> (i32, bool) {
+        let pid = child.id() as i32;
+        let timed_out = Arc::new(AtomicBool::new(false));
+        if let Some(timeout) = timeout {
+            let timed_out = Arc::clone(&timed_out);
+            std::thread::spawn(move || {
+                std::thread::sleep(timeout);
+                for (signal, pause) in [
+                    (libc::SIGTERM, super::KILL_GRACE),
+                    (libc::SIGKILL, Duration::ZERO),
+                ] {
+                    let pid = CHILD.load(Ordering::SeqCst);
+                    if pid <= 0 {
+                        return;
+                    }
+                    timed_out.store(true, Ordering::SeqCst);
+                    unsafe { libc::kill(pid, signal) };
+                    std::thread::sleep(pause);
+                }
+            });
+        }
+        let mut status = 0;
+        let code = loop {
+            let waited = unsafe { libc::waitpid(-1, &mut status, 0) };
+            if waited == pid {
+                break if libc::WIFSIGNALED(status) {
+                    128 + libc::WTERMSIG(status)
+                } else {
+                    libc::WEXITSTATUS(status)
+                };
+            }
+            if waited < 0 && std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                break -1;
+            }
+        };
+        CHILD.store(0, Ordering::SeqCst);
+        (code, timed_out.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(windows)]
+mod signals {
+    use std::process::{self, Child};
+    use std::time::{Duration, Instant};
+
+    use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows_sys::Win32::System::Console::{
+        SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT,
+    };
+
+    const POLL: Duration = Duration::from_millis(50);
+
+    /// The child got the event from the console too; stay up to report
+    /// how it exits.
+    unsafe extern "system" fn outlive(event: u32) -> BOOL {
+        if event == CTRL_C_EVENT || event == CTRL_BREAK_EVENT {
+            TRUE
+        } else {
+            FALSE
+        }
+    }
+
+    pub fn spawn(command: &mut process::Command) -> std::io::Result<Child> {
+        unsafe { SetConsoleCtrlHandler(Some(outlive), TRUE) };
+        command.spawn()
+    }
+
+    pub fn wait(child: &mut Child, timeout: Option<Duration>) -> (i32, bool) {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => return (status.code().unwrap_or(-1), false),
+                Ok(None) => {}
+                Err(_) => return (-1, false),
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return (-1, true);
+            }
+            std::thread::sleep(POLL);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! `lillux exec ps`: the process table as the same JSON on every platform.
+//!
+//! Linux reads `/proc`. Other Unix platforms make two `ps` calls, one for
+//! the fixed columns and one for command lines, which come back split on
+//! whitespace. Windows asks PowerShell for `Win32_Process`, which has no
+//! CPU percentage or owner without a per-process query. As in `status`,
+//! fields a platform cannot observe are `null`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::sys_error::{ErrorCode, SysError};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PsEntry {
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub name: Option<String>,
+    pub cmdline: Option<Vec<String>>,
+    pub user: Option<String>,
+    pub uid: Option<u32>,
+    pub rss_bytes: Option<u64>,
+    /// Lifetime average, as in [`ProcessInfo`](super::ProcessInfo).
+    pub cpu_percent: Option<f64>,
+    /// Process start as Unix milliseconds.
+    pub start_time_ms: Option<u64>,
+    pub start_time: Option<String>,
+}
+
+/// Every visible process whose name contains `filter` (ignoring case) and
+/// that `user` (a name or numeric uid) owns, ordered by PID.
+pub fn run(filter: Option<&str>, user: Option<&str>) -> Value {
+    if user.is_some() && !cfg!(unix) {
+        return SysError::new(
+            ErrorCode::Unsupported,
+            "--user is not supported on this platform",
+        )
+        .failed();
+    }
+    let filter = filter.map(str::to_lowercase);
+    match list() {
+        Ok(entries) => json!(entries
+            .into_iter()
+            .filter(|entry| matches(entry, filter.as_deref(), user))
+            .collect::<Vec<_>>()),
+        Err(e) => json!({ "error": e }),
+    }
+}
+
+fn matches(entry: &PsEntry, filter: Option<&str>, user: Option<&str>) -> bool {
+    let named = filter.is_none_or(|filter| {
+        entry
+            .name
+            .as_deref()
+            .is_some_and(|name| name.to_lowercase().contains(filter))
+    });
+    let owned = user.is_none_or(|user| {
+        entry.user.as_deref() == Some(user) || entry.uid.is_some_and(|uid| uid.to_string() == user)
+    });
+    named && owned
+}
+
+/// The process table, ordered by PID.
+pub fn list() -> Result<Vec<PsEntry>, String> {
+    let mut entries = table()?;
+    #[cfg(unix)]
+    {
+        let mut names = HashMap::new();
+        for entry in &mut entries {
+            if let Some(uid) = entry.uid {
+                entry.user = names.entry(uid).or_insert_with(|| user_name(uid)).clone();
+            }
+        }
+    }
+    for entry in &mut entries {
+        entry.start_time = entry
+            .start_time_ms
+            .map(|ms| crate::time::iso8601_from_unix_secs(ms / 1000));
+    }
+    entries.sort_by_key(|entry| entry.pid);
+    Ok(entries)
+}
+
+#[cfg(target_os = "linux")]
+fn table() -> Result<Vec<PsEntry>, String> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::read_dir("/proc")
+        .map_err(|e| format!("Failed to read /proc: {e}"))?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            let info = super::procinfo::inspect(pid)?;
+            Some(PsEntry {
+                pid,
+                ppid: info.ppid,
+                name: info.name,
+                cmdline: info.cmdline,
+                user: None,
+                uid: entry.metadata().ok().map(|meta| meta.uid()),
+                rss_bytes: info.rss_bytes,
+                cpu_percent: info.cpu_percent,
+                start_time_ms: info.start_time_ms,
+                start_time: None,
+            })
+        })
+        .collect())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn table() -> Result<Vec<PsEntry>, String> {
+    let ps = |columns: &str| {
+        std::process::Command::new("ps")
+            .args(["-A", "-o", columns])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .map_err(|e| format!("Failed to run ps: {e}"))
+    };
+    let commands: HashMap<u32, Vec<String>> = ps("pid=,args=")?
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let pid = words.next()?.parse().ok()?;
+            Some((pid, words.map(str::to_string).collect()))
+        })
+        .collect();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    Ok(ps("pid=,ppid=,uid=,rss=,%cpu=,etime=,comm=")?
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next().and_then(|v| v.parse().ok());
+            let uid = fields.next().and_then(|v| v.parse().ok());
+            let rss_bytes = fields
+                .next()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|kb| kb * 1024);
+            let cpu_percent = fields.next().and_then(|v| v.parse().ok());
+            let uptime_ms = fields
+                .next()
+                .and_then(super::procinfo::parse_ps_etime)
+                .map(|secs| secs * 1000);
+            let name = fields.collect::<Vec<_>>().join(" ");
+            Some(PsEntry {
+                pid,
+                ppid,
+                name: (!name.is_empty()).then_some(name),
+                cmdline: commands.get(&pid).cloned(),
+                user: None,
+                uid,
+                rss_bytes,
+                cpu_percent,
+                start_time_ms: uptime_ms.map(|ms| now.saturating_sub(ms)),
+                start_time: None,
+            })
+        })
+        .collect())
+}
+
+#[cfg(windows)]
+fn table() -> Result<Vec<PsEntry>, String> {
+    const SCRIPT: &str = "Get-CimInstance Win32_Process | ForEach-Object { \
+        [pscustomobject]@{ pid = $_.ProcessId; ppid = $_.ParentProcessId; name = $_.Name; \
+        cmdline = $_.CommandLine; rss = $_.WorkingSetSize; \
+        start = if ($_.CreationDate) { ([DateTimeOffset]$_.CreationDate).ToUnixTimeMilliseconds() } else { $null } } \
+        } | ConvertTo-Json -Compress";
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .output()
+        .map_err(|e| format!("Failed to run powershell: {e}"))?;
+    let rows: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unexpected Win32_Process listing: {e}"))?;
+    // ConvertTo-Json writes a lone process as an object, not a list.
+    let rows = match rows {
+        Value::Array(rows) => rows,
+        row => vec![row],
+    };
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            Some(PsEntry {
+                pid: row["pid"].as_u64()? as u32,
+                ppid: row["ppid"].as_u64().map(|ppid| ppid as u32),
+                name: row["name"].as_str().map(str::to_string),
+                cmdline: row["cmdline"]
+                    .as_str()
+                    .map(|line| line.split_whitespace().map(str::to_string).collect()),
+                rss_bytes: row["rss"].as_u64(),
+                start_time_ms: row["start"].as_u64(),
+                ..PsEntry::default()
+            })
+        })
+        .collect())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn table() -> Result<Vec<PsEntry>, String> {
+    Err("Process table enumeration is not supported on this platform".to_string())
+}
+
+/// The login name of `uid`, from the password database.
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let mut record: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut found = std::ptr::null_mut();
+    let status = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut record,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut found,
+        )
+    };
+    if status != 0 || found.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(record.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
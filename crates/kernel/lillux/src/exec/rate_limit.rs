@@ -0,0 +1,182 @@
+//! `daemon --max-spawns-per-minute N`: a token bucket in front of every
+//! spawn the server makes, so a client stuck in a loop cannot fork-bomb the
+//! host through it.
+//!
+//! The bucket holds up to N tokens, starts full, and refills at N per
+//! minute; each spawn takes one. When it is empty, `--on-spawn-limit
+//! reject` (the default) fails the spawn at once with `retry_after_ms`,
+//! and `queue` holds the request until a token comes back. At most N
+//! requests wait at a time; any more are rejected, so a queue cannot grow
+//! without bound either. Schedules and health-check restarts go through
+//! the same bucket.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnLimit {
+    /// Fail the spawn with `retry_after_ms`
+    Reject,
+    /// Hold the spawn until the bucket refills
+    Queue,
+}
+
+pub struct SpawnLimit {
+    per_minute: u32,
+    on_limit: OnLimit,
+    bucket: Mutex<Bucket>,
+}
+
+/// Tokens left as of `at`, as a fraction so slow refills add up.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    at: Instant,
+    waiting: u32,
+    rejected: u64,
+}
+
+impl SpawnLimit {
+    pub fn new(per_minute: u32, on_limit: OnLimit) -> Self {
+        Self {
+            per_minute,
+            on_limit,
+            bucket: Mutex::new(Bucket {
+                tokens: per_minute as f64,
+                at: Instant::now(),
+                waiting: 0,
+                rejected: 0,
+            }),
+        }
+    }
+
+    /// Take a token for one spawn, waiting for it under `queue`. Returns
+    /// how long the spawn was held, or the failed result to return instead.
+    pub fn acquire(&self) -> Result<Duration, Value> {
+        let started = Instant::now();
+        let mut queued = false;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                match bucket.take(self.per_minute, Instant::now()) {
+                    Ok(()) => {
+                        if queued {
+                            bucket.waiting -= 1;
+                        }
+                        return Ok(started.elapsed());
+                    }
+                    Err(wait) if queued => wait,
+                    Err(wait) => {
+                        if self.on_limit == OnLimit::Reject || bucket.waiting >= self.per_minute {
+                            bucket.rejected += 1;
+                            return Err(self.rejected(wait));
+                        }
+                        bucket.waiting += 1;
+                        queued = true;
+                        wait
+                    }
+                }
+            };
+            // Another waiter may take the token first; the loop re-checks.
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Spawns turned away since the server started.
+    pub fn rejected_count(&self) -> u64 {
+        self.bucket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .rejected
+    }
+
+    fn rejected(&self, wait: Duration) -> Value {
+        let retry_after_ms = wait.as_millis().max(1) as u64;
+        json!({
+            "success": false,
+            "error": format!(
+                "Spawn rate limit of {} per minute reached; retry in {retry_after_ms}ms",
+                self.per_minute
+            ),
+            "retry_after_ms": retry_after_ms,
+        })
+    }
+}
+
+impl Bucket {
+    /// Refill up to `now` and take a token, or say how long until one.
+    fn take(&mut self, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        let rate = per_minute as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(per_minute as f64);
+        self.at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `take`, with the wait in whole seconds.
+    fn take(bucket: &mut Bucket, now: Instant) -> Result<(), u64> {
+        bucket
+            .take(3, now)
+            .map_err(|wait| wait.as_secs_f64().round() as u64)
+    }
+
+    #[test]
+    fn the_bucket_allows_a_burst_then_refills_at_the_rate() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            tokens: 3.0,
+            at: start,
+            waiting: 0,
+            rejected: 0,
+        };
+        for _ in 0..3 {
+            assert_eq!(take(&mut bucket, start), Ok(()));
+        }
+        // Three a minute: one token every 20 seconds.
+        assert_eq!(take(&mut bucket, start), Err(20));
+        let later = start + Duration::from_secs(15);
+        assert_eq!(take(&mut bucket, later), Err(5));
+        assert_eq!(take(&mut bucket, later + Duration::from_secs(6)), Ok(()));
+        // An idle hour refills to the burst size, no further.
+        let idle = later + Duration::from_secs(3600);
+        for _ in 0..3 {
+            assert_eq!(take(&mut bucket, idle), Ok(()));
+        }
+        assert!(take(&mut bucket, idle).is_err());
+    }
+
+    #[test]
+    fn reject_reports_when_to_retry() {
+        let limit = SpawnLimit::new(1, OnLimit::Reject);
+        assert!(limit.acquire().is_ok());
+        let rejected = limit.acquire().unwrap_err();
+        assert_eq!(rejected["success"], false);
+        let retry = rejected["retry_after_ms"].as_u64().unwrap();
+        assert!(retry > 59_000 && retry <= 60_000, "{retry}");
+        assert_eq!(limit.rejected_count(), 1);
+    }
+
+    #[test]
+    fn queue_holds_a_spawn_until_a_token_comes_back() {
+        // Sixty a minute: one token a second once the burst is spent.
+        let limit = SpawnLimit::new(60, OnLimit::Queue);
+        for _ in 0..60 {
+            assert!(limit.acquire().unwrap() < Duration::from_millis(100));
+        }
+        let queued = limit.acquire().unwrap();
+        assert!(queued >= Duration::from_millis(900), "{queued:?}");
+        assert_eq!(limit.rejected_count(), 0);
+    }
+}
@@ -0,0 +1,797 @@
+//! Durable record of processes started by `lillux exec spawn`.
+//!
+//! The registry is a SQLite database so concurrent `lillux` invocations
+//! share one consistent view. Each row pins its process by start time, so
+//! a recycled PID is reported dead instead of being mistaken for the
+//! original child. Rows keep the exit outcome once it is observed.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+
+use super::audit::{self, AuditRecord};
+use super::container::Container;
+use super::cores::CoreDumps;
+use super::health::{HealthCheck, HealthState};
+use super::log_cap::LogCap;
+use super::procinfo;
+use super::rusage::ResourceUsage;
+use super::sandbox::Sandbox;
+use super::schedule::{Overlap, ScheduleEntry};
+use super::sys_error::{ErrorCode, SysError};
+
+/// Environment override for the registry database path.
+pub const REGISTRY_PATH_ENV: &str = "LILLUX_EXEC_REGISTRY";
+
+/// Allowed drift between a recorded and an observed start time. Linux
+/// start times are derived identically on both reads; `ps` elapsed time
+/// only has one-second resolution.
+const START_TIME_TOLERANCE_MS: u64 = 2000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS processes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pid INTEGER NOT NULL,
+    name TEXT,
+    tags TEXT NOT NULL DEFAULT '[]',
+    cmd TEXT NOT NULL,
+    args TEXT NOT NULL DEFAULT '[]',
+    cmdline_hash TEXT NOT NULL,
+    log TEXT,
+    started_at_ms INTEGER,
+    spawned_at TEXT NOT NULL,
+    exit_status TEXT,
+    ended_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_processes_name ON processes(name);
+CREATE INDEX IF NOT EXISTS idx_processes_pid ON processes(pid);
+";
+
+/// Changes to the first schema, applied in order. `PRAGMA user_version`
+/// records how many a database has already run.
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE processes ADD COLUMN stdin_fifo TEXT;",
+    "ALTER TABLE processes ADD COLUMN stages TEXT;",
+    "ALTER TABLE processes ADD COLUMN envs TEXT NOT NULL DEFAULT '[]';
+     ALTER TABLE processes ADD COLUMN sd_notify INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE processes ADD COLUMN container TEXT;",
+    "CREATE TABLE schedules (
+         name TEXT PRIMARY KEY,
+         cron TEXT NOT NULL,
+         overlap TEXT NOT NULL,
+         template TEXT NOT NULL,
+         created_at TEXT NOT NULL,
+         last_fire_at TEXT,
+         last_result TEXT,
+         pending INTEGER NOT NULL DEFAULT 0
+     );",
+    "ALTER TABLE processes ADD COLUMN health TEXT;
+     ALTER TABLE processes ADD COLUMN health_state TEXT;",
+    "CREATE TABLE audit (
+         seq INTEGER PRIMARY KEY AUTOINCREMENT,
+         at_ms INTEGER NOT NULL,
+         operation TEXT NOT NULL,
+         trace_id TEXT,
+         args TEXT NOT NULL,
+         outcome TEXT NOT NULL,
+         prev_hash TEXT NOT NULL,
+         hash TEXT NOT NULL
+     );
+     CREATE INDEX idx_audit_at ON audit(at_ms);
+     CREATE TRIGGER audit_no_update BEFORE UPDATE ON audit
+     BEGIN SELECT RAISE(ABORT, 'the audit log is append-only'); END;
+     CREATE TRIGGER audit_no_delete BEFORE DELETE ON audit
+     BEGIN SELECT RAISE(ABORT, 'the audit log is append-only'); END;",
+    "ALTER TABLE processes ADD COLUMN reload_signal TEXT;",
+    "ALTER TABLE processes ADD COLUMN core_dumps TEXT;",
+    "ALTER TABLE processes ADD COLUMN sandbox TEXT;",
+    "ALTER TABLE processes ADD COLUMN pgid INTEGER;",
+    "ALTER TABLE processes ADD COLUMN log_cap TEXT;",
+];
+
+const SCHEDULE_COLUMNS: &str =
+    "name, cron, overlap, template, created_at, last_fire_at, last_result, pending";
+
+const COLUMNS: &str =
+    "id, pid, name, tags, cmd, args, cmdline_hash, log, started_at_ms, spawned_at, exit_status, ended_at, stdin_fifo, stages, envs, sd_notify, container, health, health_state, reload_signal, core_dumps, sandbox, pgid, log_cap";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// Row id; assigned by [`Registry::record`].
+    #[serde(default)]
+    pub id: i64,
+    pub pid: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub log: Option<String>,
+    /// Process start as Unix milliseconds, observed right after spawn.
+    #[serde(default)]
+    pub started_at_ms: Option<u64>,
+    pub spawned_at: String,
+    /// How the process ended, once observed: `terminated` or `killed` by
+    /// `lillux exec kill`, or `exited` when found gone.
+    #[serde(default)]
+    pub exit_status: Option<String>,
+    #[serde(default)]
+    pub ended_at: Option<String>,
+    /// FIFO connected to the child's stdin (`spawn --stdin-fifo`).
+    #[serde(default)]
+    pub stdin_fifo: Option<String>,
+    /// Stages of a `lillux exec pipeline`, whose `pid` is the supervisor.
+    /// Empty for plain spawns.
+    #[serde(default)]
+    pub stages: Vec<PipelineStage>,
+    /// `KEY=VALUE` environment the command was started with.
+    #[serde(default)]
+    pub envs: Vec<String>,
+    /// Whether the child was handed systemd's `NOTIFY_SOCKET`.
+    #[serde(default)]
+    pub sd_notify: bool,
+    /// The container the command runs in (`spawn --backend`); `pid` is
+    /// then the host PID of the container's init process.
+    #[serde(default)]
+    pub container: Option<Container>,
+    /// Check evaluated by `exec daemon` (`spawn --health-*`).
+    #[serde(default)]
+    pub health: Option<HealthCheck>,
+    /// Latest outcome of `health`, written by the daemon.
+    #[serde(default)]
+    pub health_state: Option<HealthState>,
+    /// Signal `lillux exec reload` sends (`spawn --reload-signal`), e.g.
+    /// `SIGUSR1`; `SIGHUP` when unset.
+    #[serde(default)]
+    pub reload_signal: Option<String>,
+    /// Where crash dumps are collected (`spawn --core-dumps`), and those
+    /// collected so far.
+    #[serde(default)]
+    pub core_dumps: Option<CoreDumps>,
+    /// The isolation preset it runs under (`spawn --sandbox`).
+    #[serde(default)]
+    pub sandbox: Option<Sandbox>,
+    /// The process group (and session) it led at spawn. `kill` and
+    /// `status` cover every member unless asked for `--self-only`; `None`
+    /// where lillux did not start the process itself, and on Windows.
+    #[serde(default)]
+    pub pgid: Option<u32>,
+    /// The cap on its log's size (`spawn --log-max-bytes`).
+    #[serde(default)]
+    pub log_cap: Option<LogCap>,
+}
+
+/// One command of a pipeline and, once it has been reaped, its exit code
+/// and what it used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStage {
+    pub pid: u32,
+    pub argv: Vec<String>,
+    /// Exit code, or `128 + signal` when killed by a signal.
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub rusage: Option<ResourceUsage>,
+    /// The `[label]` prefixing this stage's lines in a multiplexed log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The stage's own copy of its output, beside a multiplexed log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+}
+
+impl RegistryEntry {
+    /// SHA-256 of the JSON `[cmd, args...]` vector, for matching respawns
+    /// of the same command line without comparing argument text.
+    pub fn cmdline_hash(&self) -> String {
+        let argv: Vec<&str> = std::iter::once(self.cmd.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect();
+        crate::cas::sha256_hex(serde_json::to_string(&argv).unwrap_or_default().as_bytes())
+    }
+
+    /// Live process info, or `None` once the process has exited (zombies
+    /// included) or its PID now belongs to a different process.
+    pub fn live_info(&self) -> Option<procinfo::ProcessInfo> {
+        if self.exit_status.is_some() {
+            return None;
+        }
+        self.process().filter(|info| !info.is_defunct())
+    }
+
+    /// The process as the OS still holds it, possibly a zombie nobody has
+    /// reaped; `None` once it is gone or its PID is another process's.
+    pub fn process(&self) -> Option<procinfo::ProcessInfo> {
+        let info = procinfo::inspect(self.pid)?;
+        match (self.started_at_ms, info.start_time_ms) {
+            (Some(recorded), Some(observed))
+                if recorded.abs_diff(observed) > START_TIME_TOLERANCE_MS =>
+            {
+                None
+            }
+            _ => Some(info),
+        }
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.live_info().is_some()
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let json_list = |index: usize| -> rusqlite::Result<Vec<String>> {
+            let raw: String = row.get(index)?;
+            serde_json::from_str(&raw).map_err(|error| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    index,
+                    rusqlite::types::Type::Text,
+                    Box::new(error),
+                )
+            })
+        };
+        let json_value = |index: usize| -> rusqlite::Result<Option<String>> { row.get(index) };
+        let parse = |index: usize, error: serde_json::Error| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(error),
+            )
+        };
+        Ok(Self {
+            id: row.get(0)?,
+            pid: row.get(1)?,
+            name: row.get(2)?,
+            tags: json_list(3)?,
+            cmd: row.get(4)?,
+            args: json_list(5)?,
+            log: row.get(7)?,
+            started_at_ms: row.get::<_, Option<i64>>(8)?.map(|ms| ms as u64),
+            spawned_at: row.get(9)?,
+            exit_status: row.get(10)?,
+            ended_at: row.get(11)?,
+            stdin_fifo: row.get(12)?,
+            stages: match json_value(13)? {
+                Some(raw) => serde_json::from_str(&raw).map_err(|e| parse(13, e))?,
+                None => Vec::new(),
+            },
+            envs: json_list(14)?,
+            sd_notify: row.get(15)?,
+            container: match json_value(16)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(16, e))?),
+                None => None,
+            },
+            health: match json_value(17)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(17, e))?),
+                None => None,
+            },
+            health_state: match json_value(18)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(18, e))?),
+                None => None,
+            },
+            reload_signal: row.get(19)?,
+            core_dumps: match json_value(20)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(20, e))?),
+                None => None,
+            },
+            sandbox: match json_value(21)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(21, e))?),
+                None => None,
+            },
+            pgid: row.get(22)?,
+            log_cap: match json_value(23)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| parse(23, e))?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// Outcome of [`Registry::gc`].
+#[derive(Debug, Default, Serialize)]
+pub struct GcReport {
+    /// Rows whose process has exited or whose PID was recycled.
+    pub dead_entries: Vec<RegistryEntry>,
+    pub kept: usize,
+}
+
+pub struct Registry {
+    path: PathBuf,
+}
+
+impl Registry {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `explicit` (the `--registry` flag), else `$LILLUX_EXEC_REGISTRY`,
+    /// else `registry.db` in the per-user state directory
+    /// (`$XDG_STATE_HOME/lillux/exec` or `~/.local/state/lillux/exec`).
+    pub fn locate(explicit: Option<&str>) -> Result<Self, String> {
+        if let Some(path) = explicit.filter(|p| !p.is_empty()) {
+            return Ok(Self::open(path));
+        }
+        if let Some(path) = std::env::var_os(REGISTRY_PATH_ENV).filter(|v| !v.is_empty()) {
+            return Ok(Self::open(path));
+        }
+        let state = std::env::var_os("XDG_STATE_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .filter(|v| !v.is_empty())
+                    .map(|home| PathBuf::from(home).join(".local").join("state"))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Cannot locate the exec registry: pass --registry or set {REGISTRY_PATH_ENV}"
+                )
+            })?;
+        Ok(Self::open(
+            state.join("lillux").join("exec").join("registry.db"),
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `<dir>/<name>` beside the database, for state kept in plain files
+    /// (`exec lock`, `exec port alloc`). `name` must be a plain file name.
+    pub fn state_file(&self, dir: &str, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "Invalid name '{name}': use letters, digits, '-', '_', and '.'"
+            ));
+        }
+        let parent = self
+            .path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        Ok(parent.join(dir).join(name))
+    }
+
+    /// Open (creating if needed) the database. The parent directory is
+    /// created private: rows carry full command lines.
+    fn connect(&self) -> Result<Connection, String> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            let mut builder = std::fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder.create(parent).map_err(|e| {
+                format!(
+                    "Failed to create registry directory {}: {e}",
+                    parent.display()
+                )
+            })?;
+        }
+        let conn = Connection::open(&self.path)
+            .map_err(|e| format!("Failed to open registry {}: {e}", self.path.display()))?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .and_then(|_| conn.execute_batch("PRAGMA journal_mode=WAL;"))
+            .and_then(|_| conn.execute_batch(SCHEMA))
+            .and_then(|_| migrate(&conn))
+            .map_err(|e| format!("Failed to initialise registry {}: {e}", self.path.display()))?;
+        Ok(conn)
+    }
+
+    /// Record a spawn and return its row id.
+    pub fn record(&self, entry: &RegistryEntry) -> Result<i64, String> {
+        insert(&self.connect()?, entry)
+    }
+
+    /// [`record`](Self::record) `entry` unless a live process other than
+    /// it already holds its name, which is returned instead. The check and
+    /// the insert share one write lock, so of two spawns racing for a name
+    /// only one is recorded.
+    pub fn record_named(
+        &self,
+        entry: &RegistryEntry,
+    ) -> Result<Result<i64, RegistryEntry>, String> {
+        let conn = self.connect()?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)
+            .map_err(|e| format!("Failed to lock registry: {e}"))?;
+        let holder = {
+            let mut statement = tx
+                .prepare(&format!(
+                    "SELECT {COLUMNS} FROM processes WHERE name = ?1 ORDER BY id DESC"
+                ))
+                .map_err(|e| format!("Failed to query registry: {e}"))?;
+            let named: Vec<RegistryEntry> = statement
+                .query_map(params![entry.name], RegistryEntry::from_row)
+                .and_then(Iterator::collect)
+                .map_err(|e| format!("Failed to read registry: {e}"))?;
+            named
+                .into_iter()
+                .find(|named| named.pid != entry.pid && named.is_live())
+        };
+        if let Some(holder) = holder {
+            return Ok(Err(holder));
+        }
+        let id = insert(&tx, entry)?;
+        tx.commit()
+            .map_err(|e| format!("Failed to write registry entry: {e}"))?;
+        Ok(Ok(id))
+    }
+
+    /// All entries, oldest spawn first.
+    pub fn entries(&self) -> Result<Vec<RegistryEntry>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let conn = self.connect()?;
+        let mut statement = conn
+            .prepare(&format!("SELECT {COLUMNS} FROM processes ORDER BY id"))
+            .map_err(|e| format!("Failed to query registry: {e}"))?;
+        let rows = statement
+            .query_map([], RegistryEntry::from_row)
+            .and_then(Iterator::collect)
+            .map_err(|e| format!("Failed to read registry: {e}"));
+        rows
+    }
+
+    /// The live entry registered under `name`, if any.
+    pub fn find_live(&self, name: &str) -> Result<Option<RegistryEntry>, String> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .find(|entry| entry.name.as_deref() == Some(name) && entry.is_live()))
+    }
+
+    /// The newest entry registered under `name`, live or not.
+    pub fn find_latest(&self, name: &str) -> Result<Option<RegistryEntry>, String> {
+        self.newest("name = ?1", name)
+    }
+
+    /// The newest entry for `pid`, if any.
+    pub fn find_pid(&self, pid: u32) -> Result<Option<RegistryEntry>, String> {
+        self.newest("pid = ?1", pid)
+    }
+
+    fn newest(
+        &self,
+        filter: &str,
+        value: impl rusqlite::ToSql,
+    ) -> Result<Option<RegistryEntry>, String> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let conn = self.connect()?;
+        conn.query_row(
+            &format!("SELECT {COLUMNS} FROM processes WHERE {filter} ORDER BY id DESC LIMIT 1"),
+            params![value],
+            RegistryEntry::from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to query registry: {e}"))
+    }
+
+    /// Replace the recorded stages of a pipeline, e.g. as exit codes arrive.
+    pub fn update_stages(&self, id: i64, stages: &[PipelineStage]) -> Result<(), String> {
+        let conn = self.connect()?;
+        conn.execute(
+            "UPDATE processes SET stages = ?2 WHERE id = ?1",
+            params![id, stages_json(stages)],
+        )
+        .map_err(|e| format!("Failed to update registry entry: {e}"))?;
+        Ok(())
+    }
+
+    /// Store the latest health check outcome of a process.
+    pub fn update_health(&self, id: i64, state: &HealthState) -> Result<(), String> {
+        let conn = self.connect()?;
+        conn.execute(
+            "UPDATE processes SET health_state = ?2 WHERE id = ?1",
+            params![id, serde_json::to_string(state).unwrap_or_default()],
+        )
+        .map_err(|e| format!("Failed to update registry entry: {e}"))?;
+        Ok(())
+    }
+
+    /// Store the crash dumps collected for a process.
+    pub fn update_core_dumps(&self, id: i64, cores: &CoreDumps) -> Result<(), String> {
+        let conn = self.connect()?;
+        conn.execute(
+            "UPDATE processes SET core_dumps = ?2 WHERE id = ?1",
+            params![id, serde_json::to_string(cores).unwrap_or_default()],
+        )
+        .map_err(|e| format!("Failed to update registry entry: {e}"))?;
+        Ok(())
+    }
+
+    /// Record how a process ended. Earlier outcomes are never overwritten.
+    pub fn mark_ended(&self, id: i64, exit_status: &str) -> Result<(), String> {
+        let conn = self.connect()?;
+        conn.execute(
+            "UPDATE processes SET exit_status = ?2, ended_at = ?3
+             WHERE id = ?1 AND exit_status IS NULL",
+            params![id, exit_status, crate::time::iso8601_now()],
+        )
+        .map_err(|e| format!("Failed to update registry entry: {e}"))?;
+        Ok(())
+    }
+
+    /// Delete rows whose process is gone. With `dry_run` nothing is deleted
+    /// and the report lists what would be.
+    pub fn gc(&self, dry_run: bool) -> Result<GcReport, String> {
+        let mut report = GcReport::default();
+        for entry in self.entries()? {
+            if entry.is_live() {
+                report.kept += 1;
+            } else {
+                report.dead_entries.push(entry);
+            }
+        }
+        if !dry_run && !report.dead_entries.is_empty() {
+            let conn = self.connect()?;
+            for entry in &report.dead_entries {
+                conn.execute("DELETE FROM processes WHERE id = ?1", params![entry.id])
+                    .map_err(|e| format!("Failed to delete registry entry: {e}"))?;
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl ScheduleEntry {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let invalid = |index: usize, error: Box<dyn std::error::Error + Send + Sync>| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, error)
+        };
+        let overlap: String = row.get(2)?;
+        let template: String = row.get(3)?;
+        Ok(Self {
+            name: row.get(0)?,
+            cron: row.get(1)?,
+            overlap: <Overlap as clap::ValueEnum>::from_str(&overlap, false)
+                .map_err(|error| invalid(2, error.into()))?,
+            template: serde_json::from_str(&template).map_err(|e| invalid(3, e.into()))?,
+            created_at: row.get(4)?,
+            last_fire_at: row.get(5)?,
+            last_result: match row.get::<_, Option<String>>(6)? {
+                Some(raw) => Some(serde_json::from_str(&raw).map_err(|e| invalid(6, e.into()))?),
+                None => None,
+            },
+            pending: row.get(7)?,
+        })
+    }
+}
+
+impl Registry {
+    /// Add a schedule; fails if one with the same name exists.
+    pub fn add_schedule(&self, schedule: &ScheduleEntry) -> Result<(), SysError> {
+        let conn = self.connect()?;
+        conn.execute(
+            &format!(
+                "INSERT INTO schedules ({SCHEDULE_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, NULL, NULL, 0)"
+            ),
+            params![
+                schedule.name,
+                schedule.cron,
+                schedule.overlap.as_str(),
+                serde_json::to_string(&schedule.template).unwrap_or_default(),
+                schedule.created_at,
+            ],
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(error, _)
+                if error.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                SysError::new(
+                    ErrorCode::AlreadyExists,
+                    format!("A schedule named '{}' already exists", schedule.name),
+                )
+            }
+            e => format!("Failed to write schedule: {e}").into(),
+        })?;
+        Ok(())
+    }
+
+    /// All schedules, by name.
+    pub fn schedules(&self) -> Result<Vec<ScheduleEntry>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let conn = self.connect()?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {SCHEDULE_COLUMNS} FROM schedules ORDER BY name"
+            ))
+            .map_err(|e| format!("Failed to query schedules: {e}"))?;
+        let rows = statement
+            .query_map([], ScheduleEntry::from_row)
+            .and_then(Iterator::collect)
+            .map_err(|e| format!("Failed to read schedules: {e}"));
+        rows
+    }
+
+    /// Delete a schedule; `false` if none had that name.
+    pub fn remove_schedule(&self, name: &str) -> Result<bool, String> {
+        if !self.path.exists() {
+            return Ok(false);
+        }
+        let conn = self.connect()?;
+        let removed = conn
+            .execute("DELETE FROM schedules WHERE name = ?1", params![name])
+            .map_err(|e| format!("Failed to delete schedule: {e}"))?;
+        Ok(removed > 0)
+    }
+
+    /// Record the outcome of a firing and whether a run is still queued.
+    pub fn record_fire(
+        &self,
+        name: &str,
+        result: &serde_json::Value,
+        pending: bool,
+    ) -> Result<(), String> {
+        let conn = self.connect()?;
+        conn.execute(
+            "UPDATE schedules SET last_fire_at = ?2, last_result = ?3, pending = ?4 WHERE name = ?1",
+            params![name, crate::time::iso8601_now(), result.to_string(), pending],
+        )
+        .map_err(|e| format!("Failed to update schedule: {e}"))?;
+        Ok(())
+    }
+}
+
+impl Registry {
+    /// Append one record to the audit log, chained to the current head.
+    pub fn append_audit(
+        &self,
+        operation: &str,
+        trace_id: Option<&str>,
+        args: &serde_json::Value,
+        outcome: &serde_json::Value,
+    ) -> Result<(), String> {
+        let mut conn = self.connect()?;
+        let failed = |e: rusqlite::Error| format!("Failed to write audit record: {e}");
+        // Immediate, so concurrent writers cannot both extend the same head.
+        let tx = conn
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+            .map_err(failed)?;
+        let head: Option<(i64, String)> = tx
+            .query_row(
+                "SELECT seq, hash FROM audit ORDER BY seq DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(failed)?;
+        let (seq, prev_hash) = match head {
+            Some((seq, hash)) => (seq + 1, hash),
+            None => (1, audit::GENESIS_HASH.to_string()),
+        };
+        let at_ms = crate::time::timestamp_millis();
+        let (args, outcome) = (args.to_string(), outcome.to_string());
+        let hash = audit::chain_hash(&prev_hash, seq, at_ms, operation, trace_id, &args, &outcome);
+        tx.execute(
+            "INSERT INTO audit (seq, at_ms, operation, trace_id, args, outcome, prev_hash, hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![seq, at_ms, operation, trace_id, args, outcome, prev_hash, hash],
+        )
+        .map_err(failed)?;
+        tx.commit().map_err(failed)
+    }
+
+    /// Audit records at or after `since_ms` (Unix milliseconds), oldest
+    /// first.
+    pub fn audit_records(&self, since_ms: i64) -> Result<Vec<AuditRecord>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let conn = self.connect()?;
+        let mut statement = conn
+            .prepare(
+                "SELECT seq, at_ms, operation, trace_id, args, outcome, prev_hash, hash
+                 FROM audit WHERE at_ms >= ?1 ORDER BY seq",
+            )
+            .map_err(|e| format!("Failed to query audit log: {e}"))?;
+        let rows = statement
+            .query_map(params![since_ms], |row| {
+                let json = |index: usize| -> rusqlite::Result<serde_json::Value> {
+                    let raw: String = row.get(index)?;
+                    serde_json::from_str(&raw).map_err(|error| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            index,
+                            rusqlite::types::Type::Text,
+                            Box::new(error),
+                        )
+                    })
+                };
+                let at_ms: i64 = row.get(1)?;
+                Ok(AuditRecord {
+                    seq: row.get(0)?,
+                    at: crate::time::iso8601_from_unix_secs(at_ms.max(0) as u64 / 1000),
+                    at_ms,
+                    operation: row.get(2)?,
+                    trace_id: row.get(3)?,
+                    args: json(4)?,
+                    outcome: json(5)?,
+                    prev_hash: row.get(6)?,
+                    hash: row.get(7)?,
+                })
+            })
+            .and_then(Iterator::collect)
+            .map_err(|e| format!("Failed to read audit log: {e}"));
+        rows
+    }
+}
+
+/// Insert `entry` as a new row; returns its id.
+fn insert(conn: &Connection, entry: &RegistryEntry) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO processes (pid, name, tags, cmd, args, cmdline_hash, log, started_at_ms, spawned_at, stdin_fifo, stages, envs, sd_notify, container, health, reload_signal, core_dumps, sandbox, pgid, log_cap)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+        params![
+            entry.pid,
+            entry.name,
+            serde_json::to_string(&entry.tags).unwrap_or_default(),
+            entry.cmd,
+            serde_json::to_string(&entry.args).unwrap_or_default(),
+            entry.cmdline_hash(),
+            entry.log,
+            entry.started_at_ms.map(|ms| ms as i64),
+            entry.spawned_at,
+            entry.stdin_fifo,
+            stages_json(&entry.stages),
+            serde_json::to_string(&entry.envs).unwrap_or_default(),
+            entry.sd_notify,
+            entry
+                .container
+                .as_ref()
+                .map(|container| serde_json::to_string(container).unwrap_or_default()),
+            entry
+                .health
+                .as_ref()
+                .map(|health| serde_json::to_string(health).unwrap_or_default()),
+            entry.reload_signal,
+            entry
+                .core_dumps
+                .as_ref()
+                .map(|cores| serde_json::to_string(cores).unwrap_or_default()),
+            entry
+                .sandbox
+                .as_ref()
+                .map(|sandbox| serde_json::to_string(sandbox).unwrap_or_default()),
+            entry.pgid,
+            entry
+                .log_cap
+                .map(|cap| serde_json::to_string(&cap).unwrap_or_default()),
+        ],
+    )
+    .map_err(|e| format!("Failed to write registry entry: {e}"))?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn stages_json(stages: &[PipelineStage]) -> Option<String> {
+    (!stages.is_empty()).then(|| serde_json::to_string(stages).unwrap_or_default())
+}
+
+/// Apply the migrations a database has not run yet. Each runs under the
+/// write lock and re-reads `user_version` once it holds it, so two
+/// processes upgrading the same registry apply each step once.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version = |conn: &Connection| -> rusqlite::Result<usize> {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+    };
+    if version(conn)? >= MIGRATIONS.len() {
+        return Ok(());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate() {
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        if version(&tx)? <= index {
+            tx.execute_batch(&format!("{migration} PRAGMA user_version = {};", index + 1))?;
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! `lillux exec reload`: signal a registered process to reload, optionally
+//! rotating its log first, and report whether it survived.
+//!
+//! The signal is the one recorded with `spawn --reload-signal`, `SIGHUP`
+//! by default. `--rotate-log` copies the log to `<log>.1` (written aside
+//! and renamed into place, so the archive is never partial) and truncates
+//! the live file. Spawned processes write their log in append mode, so
+//! output after the truncation lands at the start of the fresh file
+//! whether or not the process reopens it. Bytes written between the copy
+//! and the truncation are lost, as with logrotate's `copytruncate`.
+//! `--log-compress` then hands the archive to a detached `lillux` that
+//! compresses it, so the reload does not wait on it.
+
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use super::logs::{self, LogCompression};
+use super::registry::RegistryEntry;
+use super::sys_error::{ErrorCode, SysError};
+
+/// Signals a process can be asked to reload with.
+const SIGNALS: &[&str] = &[
+    "SIGHUP", "SIGUSR1", "SIGUSR2", "SIGINT", "SIGQUIT", "SIGTERM", "SIGWINCH", "SIGCONT",
+];
+
+const DEFAULT_SIGNAL: &str = "SIGHUP";
+
+/// `HUP`, `hup`, or `SIGHUP` as `SIGHUP`.
+pub fn normalize_signal(raw: &str) -> Result<String, String> {
+    let upper = raw.trim().to_ascii_uppercase();
+    let name = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{upper}")
+    };
+    if SIGNALS.contains(&name.as_str()) {
+        Ok(name)
+    } else {
+        Err(format!(
+            "Unsupported reload signal '{raw}': expected one of {}",
+            SIGNALS.join(", ")
+        ))
+    }
+}
+
+pub fn run(
+    registry: Option<&str>,
+    name: &str,
+    signal: Option<&str>,
+    rotate_log: bool,
+    compress: Option<LogCompression>,
+    settle: &str,
+) -> Value {
+    reload(registry, name, signal, rotate_log, compress, settle).unwrap_or_else(|e| {
+        let mut failed = e.failed();
+        failed["name"] = name.into();
+        failed
+    })
+}
+
+fn reload(
+    registry: Option<&str>,
+    name: &str,
+    signal: Option<&str>,
+    rotate_log: bool,
+    compress: Option<LogCompression>,
+    settle: &str,
+) -> Result<Value, SysError> {
+    let settle = crate::time::parse_duration(settle)?;
+    let entry = super::named_entry(registry, name)?;
+    if !entry.is_live() {
+        return Err(SysError::new(
+            ErrorCode::NotFound,
+            format!("No live process named '{name}'"),
+        ));
+    }
+    if entry.container.is_some() {
+        return Err(format!("'{name}' runs in a container; reload it through its runtime").into());
+    }
+    let signal = match signal.or(entry.reload_signal.as_deref()) {
+        Some(raw) => normalize_signal(raw)?,
+        None => DEFAULT_SIGNAL.to_string(),
+    };
+    let rotated = match (rotate_log, entry.log.as_deref()) {
+        (true, Some(log)) => Some(rotate(Path::new(log))?),
+        (true, None) => return Err(format!("'{name}' was spawned without --log").into()),
+        (false, _) => None,
+    };
+    send(&entry, &signal)?;
+    std::thread::sleep(settle);
+    let mut result = json!({
+        "success": true,
+        "name": name,
+        "pid": entry.pid,
+        "signal": signal,
+        "survived": entry.is_live(),
+    });
+    if let Some(rotated) = rotated {
+        result["rotated_log"] = rotated.display().to_string().into();
+        if let Some(codec) = compress {
+            let compressor = spawn_compressor(&rotated, codec)?;
+            result["rotated_log"] = logs::compressed_path(&rotated, codec)
+                .display()
+                .to_string()
+                .into();
+            result["compressor_pid"] = compressor.into();
+        }
+    }
+    Ok(result)
+}
+
+/// Start `lillux exec compress-log` on `archive` in the background,
+/// clearing out the previous compressed archive first; its PID.
+fn spawn_compressor(archive: &Path, codec: LogCompression) -> Result<u32, SysError> {
+    for stale in [LogCompression::Gzip, LogCompression::Zstd] {
+        let _ = std::fs::remove_file(logs::compressed_path(archive, stale));
+    }
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the lillux executable: {e}"))?;
+    let args = [
+        "exec",
+        "compress-log",
+        "--path",
+        &archive.display().to_string(),
+        "--codec",
+        codec.as_str(),
+    ]
+    .map(str::to_string);
+    super::spawn_detached(
+        &exe.to_string_lossy(),
+        &args,
+        super::DetachedLog::Null,
+        &[],
+        super::DetachedStdin::Null,
+        false,
+        None,
+    )
+}
+
+/// Copy `log` to `<log>.1`, replacing the previous archive, and truncate
+/// `log` in place. Returns the archive's path.
+fn rotate(log: &Path) -> Result<PathBuf, String> {
+    let archive = PathBuf::from(format!("{}.1", log.display()));
+    let partial = PathBuf::from(format!("{}.1.partial", log.display()));
+    std::fs::copy(log, &partial).map_err(|e| format!("Failed to copy {}: {e}", log.display()))?;
+    std::fs::rename(&partial, &archive)
+        .map_err(|e| format!("Failed to rename {}: {e}", partial.display()))?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(log)
+        .and_then(|file| file.set_len(0))
+        .map_err(|e| format!("Failed to truncate {}: {e}", log.display()))?;
+    Ok(archive)
+}
+
+/// Send `signal` to `entry`'s process, or to every stage of a pipeline.
+#[cfg(unix)]
+fn send(entry: &RegistryEntry, signal: &str) -> Result<(), SysError> {
+    let number = match signal {
+        "SIGHUP" => libc::SIGHUP,
+        "SIGUSR1" => libc::SIGUSR1,
+        "SIGUSR2" => libc::SIGUSR2,
+        "SIGINT" => libc::SIGINT,
+        "SIGQUIT" => libc::SIGQUIT,
+        "SIGTERM" => libc::SIGTERM,
+        "SIGWINCH" => libc::SIGWINCH,
+        _ => libc::SIGCONT,
+    };
+    // A pipeline's supervisor leads the process group of its stages.
+    let target = if entry.stages.is_empty() {
+        entry.pid as i32
+    } else {
+        -(entry.pid as i32)
+    };
+    if unsafe { libc::kill(target, number) } != 0 {
+        return Err(SysError::last(format!("{signal} failed"), "kill").pid(target));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_entry: &RegistryEntry, _signal: &str) -> Result<(), SysError> {
+    Err(SysError::new(
+        ErrorCode::Unsupported,
+        "lillux exec reload is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_normalize_and_logs_rotate_into_a_fresh_file() {
+        assert_eq!(normalize_signal("hup").unwrap(), "SIGHUP");
+        assert_eq!(normalize_signal("SIGUSR1").unwrap(), "SIGUSR1");
+        assert!(normalize_signal("KILL").is_err());
+
+        let tmp = tempfile::tempdir().unwrap();
+        let log = tmp.path().join("web.log");
+        std::fs::write(&log, "before\n").unwrap();
+        let archive = rotate(&log).unwrap();
+        assert_eq!(std::fs::read_to_string(archive).unwrap(), "before\n");
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "");
+    }
+}
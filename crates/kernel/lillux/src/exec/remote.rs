@@ -0,0 +1,241 @@
+//! `lillux exec --host user@box ...`: the same command on another machine.
+//!
+//! The command line, less `--host` and `--output`, is run over ssh as
+//! `lillux --output <format> exec ...` on the host, with the local
+//! `--output` format, so what the remote lillux writes needs no
+//! translation: stdout, stderr, and stdin pass straight through (streams
+//! and `batch` included) and its exit status becomes ours. Paths such as
+//! `--registry` and `--log` are the host's; local config files play no
+//! part. ssh runs in batch mode, so a host that needs a password fails
+//! instead of prompting, and a host starting with `-` is refused rather
+//! than read by ssh as an option such as `-oProxyCommand`.
+
+use std::ffi::OsString;
+use std::process::Command;
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use super::sys_error::{ErrorCode, SysError};
+use crate::output::{self, OutputFormat};
+
+/// The lillux to run on the host (default `lillux`, found on its `PATH`).
+pub const REMOTE_BIN_ENV: &str = "LILLUX_REMOTE_BIN";
+
+/// The ssh command, split on whitespace (default `ssh`), e.g.
+/// `ssh -p 2222 -i ~/.ssh/fleet`.
+pub const SSH_ENV: &str = "LILLUX_SSH";
+
+/// What ssh exits with when it, not the remote command, failed.
+const SSH_FAILED: i32 = 255;
+
+/// Run the command line `args` (program name first), as parsed by
+/// `command`, on `host`. Returns the exit status.
+pub fn run(host: &str, format: OutputFormat, command: &clap::Command, args: &[OsString]) -> i32 {
+    if let Err(e) = check_host(host) {
+        return output::emit(e.failed_with(json!({ "host": host })));
+    }
+    let words = match forwarded(command, args) {
+        Ok(words) => words,
+        Err(e) => return output::emit(json!({ "host": host, "error": e })),
+    };
+    let bin = std::env::var(REMOTE_BIN_ENV)
+        .ok()
+        .filter(|bin| !bin.is_empty())
+        .unwrap_or_else(|| "lillux".to_string());
+    let format = format
+        .to_possible_value()
+        .map_or_else(|| "json".to_string(), |value| value.get_name().to_string());
+    let remote = std::iter::once(bin)
+        .chain(["--output".to_string(), format])
+        .chain(words)
+        .map(|word| quote(&word))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let ssh = std::env::var(SSH_ENV)
+        .ok()
+        .filter(|ssh| !ssh.trim().is_empty())
+        .unwrap_or_else(|| "ssh".to_string());
+    let mut ssh = ssh.split_whitespace();
+    let program = ssh.next().unwrap_or("ssh");
+    let status = Command::new(program)
+        .args(ssh)
+        .args(["-o", "BatchMode=yes", "--", host, &remote])
+        .status();
+    match status.map(|status| status.code()) {
+        Ok(Some(SSH_FAILED)) => output::emit(unreachable(host, "ssh exited 255")),
+        Ok(Some(code)) => code,
+        Ok(None) => output::emit(unreachable(host, "ssh was killed by a signal")),
+        Err(e) => output::emit(unreachable(host, &format!("Failed to run {program}: {e}"))),
+    }
+}
+
+/// Refuse a host ssh would take for an option.
+fn check_host(host: &str) -> Result<(), SysError> {
+    if host.starts_with('-') {
+        return Err(SysError::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid --host {host:?}: a host cannot start with '-'"),
+        ));
+    }
+    Ok(())
+}
+
+fn unreachable(host: &str, detail: &str) -> Value {
+    json!({ "host": host, "error": format!("Could not run lillux on {host}: {detail}") })
+}
+
+/// The words after the program name, without `--host` and `--output`.
+/// They are walked the way clap parses them against `command`, so the
+/// value of an option (a child's `--arg --output`, say) is kept whatever
+/// it looks like; everything after a `--` is the child's as given.
+fn forwarded(command: &clap::Command, args: &[OsString]) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // The subcommands entered so far; an option is looked up from the
+    // innermost out, which also finds globals declared further up.
+    let mut path = vec![command];
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = utf8(arg)?;
+        if arg == "--" {
+            words.push(arg);
+            for rest in args.by_ref() {
+                words.push(rest.to_string_lossy().into_owned());
+            }
+            break;
+        }
+        let Some(long) = arg.strip_prefix("--") else {
+            if let Some(sub) = path.last().and_then(|cmd| cmd.find_subcommand(&arg)) {
+                path.push(sub);
+            }
+            words.push(arg);
+            continue;
+        };
+        let (name, inline) = match long.split_once('=') {
+            Some((name, _)) => (name, true),
+            None => (long, false),
+        };
+        let local = name == "host" || name == "output";
+        let takes_value = path
+            .iter()
+            .rev()
+            .find_map(|cmd| cmd.get_arguments().find(|a| a.get_long() == Some(name)))
+            .is_some_and(|a| a.get_action().takes_values());
+        let value = match (takes_value && !inline).then(|| args.next()).flatten() {
+            Some(value) => Some(utf8(value)?),
+            None => None,
+        };
+        if !local {
+            words.push(arg.clone());
+            words.extend(value);
+        }
+    }
+    Ok(words)
+}
+
+fn utf8(arg: &OsString) -> Result<String, String> {
+    arg.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("Argument is not UTF-8: {}", arg.to_string_lossy()))
+}
+
+/// `word` as one word for a POSIX shell.
+fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `lillux` as main.rs builds it, as far as `exec` is concerned.
+    fn lillux() -> clap::Command {
+        clap::Command::new("lillux")
+            .arg(clap::Arg::new("output").long("output").global(true))
+            .subcommand(crate::exec::command())
+    }
+
+    fn words(args: &[&str]) -> Vec<String> {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        forwarded(&lillux(), &args).unwrap()
+    }
+
+    #[test]
+    fn a_childs_own_output_and_host_arguments_are_forwarded() {
+        assert_eq!(
+            words(&[
+                "lillux", "exec", "--host", "h", "run", "--cmd", "curl", "--arg", "--output",
+                "--arg", "f", "--arg", "--host=x",
+            ]),
+            [
+                "exec", "run", "--cmd", "curl", "--arg", "--output", "--arg", "f", "--arg",
+                "--host=x"
+            ]
+        );
+        // Global flags are still dropped after the subcommand.
+        assert_eq!(
+            words(&[
+                "lillux",
+                "exec",
+                "run",
+                "--cmd",
+                "true",
+                "--host",
+                "h",
+                "--output=text"
+            ]),
+            ["exec", "run", "--cmd", "true"]
+        );
+    }
+
+    #[test]
+    fn host_and_output_stay_local_and_words_are_quoted() {
+        let args: Vec<OsString> = [
+            "lillux",
+            "--output=text",
+            "exec",
+            "--host",
+            "ops@box",
+            "spawn",
+            "--cmd",
+            "sh",
+            "--arg",
+            "echo 'hi' $HOME",
+            "--name",
+            "web",
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        let words = forwarded(&lillux(), &args).unwrap();
+        assert_eq!(
+            words,
+            [
+                "exec",
+                "spawn",
+                "--cmd",
+                "sh",
+                "--arg",
+                "echo 'hi' $HOME",
+                "--name",
+                "web"
+            ]
+        );
+        assert_eq!(quote("web"), "web");
+        assert_eq!(quote("echo 'hi' $HOME"), r"'echo '\''hi'\'' $HOME'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn a_host_that_looks_like_an_ssh_option_is_refused() {
+        let refused = check_host("-oProxyCommand=sh -c 'touch /tmp/pwned'").unwrap_err();
+        assert_eq!(refused.code, ErrorCode::InvalidArgument);
+        assert!(check_host("ops@box").is_ok());
+        assert!(check_host("ops@box-2").is_ok());
+    }
+}
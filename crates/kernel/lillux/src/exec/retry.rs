@@ -0,0 +1,135 @@
+//! `spawn --spawn-retries N --spawn-backoff 500ms`: try a spawn again when
+//! it failed for a reason that tends to clear up by itself.
+//!
+//! Transient means `ETXTBSY` (the binary is still open for writing, as
+//! right after a build), `EAGAIN` (out of processes or memory for the
+//! moment), and, on Windows, a sharing or lock violation on the
+//! executable. The pause doubles after each failure, from `--spawn-backoff`
+//! up to [`MAX_BACKOFF`]. Any other failure is final at once.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::SysError;
+
+/// The longest pause between two attempts.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpawnRetry {
+    /// Attempts after the first.
+    pub retries: u32,
+    /// Pause before the first retry.
+    pub backoff: Duration,
+}
+
+impl Default for SpawnRetry {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A failed attempt, as reported under `"attempts"`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attempt {
+    pub attempt: u32,
+    pub error: String,
+    pub os_error: Option<i32>,
+    /// The pause before the next attempt; `None` after the last.
+    pub backoff_ms: Option<u64>,
+}
+
+/// Whether the OS error `errno` is worth another attempt.
+pub fn transient(errno: i32) -> bool {
+    #[cfg(unix)]
+    {
+        errno == libc::ETXTBSY || errno == libc::EAGAIN
+    }
+    #[cfg(windows)]
+    {
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        const ERROR_LOCK_VIOLATION: i32 = 33;
+        errno == ERROR_SHARING_VIOLATION || errno == ERROR_LOCK_VIOLATION
+    }
+}
+
+/// Call `spawn` until it succeeds, fails for good, or runs out of
+/// retries. Returns its last result and every failed attempt.
+pub fn run<T>(
+    retry: &SpawnRetry,
+    mut spawn: impl FnMut() -> Result<T, SysError>,
+) -> (Result<T, SysError>, Vec<Attempt>) {
+    let mut attempts = Vec::new();
+    let mut backoff = retry.backoff;
+    for attempt in 1.. {
+        let error = match spawn() {
+            Ok(spawned) => return (Ok(spawned), attempts),
+            Err(error) => error,
+        };
+        let os_error = error.os_error;
+        let again = attempt <= retry.retries && os_error.is_some_and(transient);
+        attempts.push(Attempt {
+            attempt,
+            error: error.message.clone(),
+            os_error,
+            backoff_ms: again.then_some(backoff.as_millis() as u64),
+        });
+        if !again {
+            return (Err(error), attempts);
+        }
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+    unreachable!("attempts are unbounded")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transient_failures_are_retried_with_doubling_backoff() {
+        let retry = SpawnRetry {
+            retries: 3,
+            backoff: Duration::from_millis(1),
+        };
+        let busy = SysError::io(
+            "Failed to spawn",
+            "execve",
+            &std::io::Error::from_raw_os_error(libc::ETXTBSY),
+        );
+        let mut calls = 0;
+        let (result, attempts) = run(&retry, || {
+            calls += 1;
+            if calls < 3 {
+                Err(busy.clone())
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result, Ok(3));
+        let pauses: Vec<_> = attempts.iter().map(|a| a.backoff_ms).collect();
+        assert_eq!(pauses, [Some(1), Some(2)]);
+        assert_eq!(attempts[0].os_error, Some(libc::ETXTBSY));
+
+        let (result, attempts) = run(&retry, || -> Result<(), SysError> {
+            Err(SysError::io(
+                "Failed to spawn",
+                "execve",
+                &std::io::Error::from_raw_os_error(libc::ENOENT),
+            ))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].backoff_ms, None);
+
+        let (_, attempts) = run(&SpawnRetry::default(), || -> Result<(), SysError> {
+            Err(busy.clone())
+        });
+        assert_eq!(attempts.len(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+//! What a child used by the time it exited, for the results of `run`,
+//! `serve`'s `wait`, and pipeline stages.
+//!
+//! The totals come from the kernel when the child is reaped: `wait4` for
+//! one child, or the growth of `RUSAGE_CHILDREN` across the wait when the
+//! reaping happens elsewhere. I/O is the filesystem traffic that reached a
+//! block device (`ru_inblock`/`ru_oublock`, counted in 512-byte units), so
+//! reads served from the page cache do not count. Unix only; elsewhere the
+//! result carries `"rusage": null`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub user_cpu_ms: f64,
+    pub system_cpu_ms: f64,
+    /// Peak resident set size.
+    pub max_rss_bytes: u64,
+    pub io_read_bytes: u64,
+    pub io_write_bytes: u64,
+    pub voluntary_context_switches: u64,
+    pub involuntary_context_switches: u64,
+}
+
+/// Size of the blocks `ru_inblock` and `ru_oublock` count.
+#[cfg(unix)]
+const BLOCK_BYTES: u64 = 512;
+
+#[cfg(unix)]
+impl From<&libc::rusage> for ResourceUsage {
+    fn from(usage: &libc::rusage) -> Self {
+        let ms = |time: libc::timeval| time.tv_sec as f64 * 1000.0 + time.tv_usec as f64 / 1000.0;
+        let count = |value: libc::c_long| value.max(0) as u64;
+        // Linux reports kilobytes; the BSDs and macOS, bytes.
+        let rss_unit = if cfg!(any(target_os = "macos", target_os = "ios")) {
+            1
+        } else {
+            1024
+        };
+        Self {
+            user_cpu_ms: ms(usage.ru_utime),
+            system_cpu_ms: ms(usage.ru_stime),
+            max_rss_bytes: count(usage.ru_maxrss) * rss_unit,
+            io_read_bytes: count(usage.ru_inblock) * BLOCK_BYTES,
+            io_write_bytes: count(usage.ru_oublock) * BLOCK_BYTES,
+            voluntary_context_switches: count(usage.ru_nvcsw),
+            involuntary_context_switches: count(usage.ru_nivcsw),
+        }
+    }
+}
+
+impl ResourceUsage {
+    /// What was used between `before` and `self`, two readings of
+    /// [`children`]. The peak RSS is `self`'s as is: the kernel keeps the
+    /// largest child's, not a sum.
+    pub fn since(self, before: Self) -> Self {
+        Self {
+            user_cpu_ms: (self.user_cpu_ms - before.user_cpu_ms).max(0.0),
+            system_cpu_ms: (self.system_cpu_ms - before.system_cpu_ms).max(0.0),
+            max_rss_bytes: self.max_rss_bytes,
+            io_read_bytes: self.io_read_bytes.saturating_sub(before.io_read_bytes),
+            io_write_bytes: self.io_write_bytes.saturating_sub(before.io_write_bytes),
+            voluntary_context_switches: self
+                .voluntary_context_switches
+                .saturating_sub(before.voluntary_context_switches),
+            involuntary_context_switches: self
+                .involuntary_context_switches
+                .saturating_sub(before.involuntary_context_switches),
+        }
+    }
+}
+
+/// Totals over every child this process has reaped so far.
+#[cfg(unix)]
+pub fn children() -> Option<ResourceUsage> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    (unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } == 0)
+        .then(|| ResourceUsage::from(&usage))
+}
+
+#[cfg(not(unix))]
+pub fn children() -> Option<ResourceUsage> {
+    None
+}
+
+/// Reap the child `pid`, returning its exit code (`128 + signal` when a
+/// signal killed it) and what it used. `None` when it cannot be reaped,
+/// e.g. because it is not our child.
+#[cfg(unix)]
+pub fn wait(pid: u32) -> Option<(Option<i32>, ResourceUsage)> {
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        let waited = unsafe { libc::wait4(pid as i32, &mut status, 0, &mut usage) };
+        if waited == pid as i32 {
+            break;
+        }
+        if waited < 0 && std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            return None;
+        }
+    }
+    let code = if libc::WIFEXITED(status) {
+        Some(libc::WEXITSTATUS(status))
+    } else if libc::WIFSIGNALED(status) {
+        Some(128 + libc::WTERMSIG(status))
+    } else {
+        None
+    };
+    Some((code, ResourceUsage::from(&usage)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::zombie_processes)] // reaped by `wait`, not `Child::wait`
+    fn wait_reports_the_cpu_a_child_burned() {
+        let child = std::process::Command::new("/bin/sh")
+            .args([
+                "-c",
+                "i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done; exit 3",
+            ])
+            .spawn()
+            .unwrap();
+        let (code, usage) = wait(child.id()).unwrap();
+        assert_eq!(code, Some(3));
+        assert!(usage.user_cpu_ms + usage.system_cpu_ms > 0.0, "{usage:?}");
+        assert!(usage.max_rss_bytes > 0);
+
+        let later = ResourceUsage {
+            user_cpu_ms: 15.0,
+            io_write_bytes: 4096,
+            max_rss_bytes: 20,
+            ..usage
+        };
+        let earlier = ResourceUsage {
+            user_cpu_ms: 5.0,
+            io_write_bytes: 1024,
+            max_rss_bytes: 10,
+            ..usage
+        };
+        let delta = later.since(earlier);
+        assert_eq!(delta.user_cpu_ms, 10.0);
+        assert_eq!(delta.io_write_bytes, 3072);
+        assert_eq!(delta.max_rss_bytes, 20);
+        assert_eq!(delta.involuntary_context_switches, 0);
+    }
+}
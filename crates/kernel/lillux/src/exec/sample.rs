@@ -0,0 +1,97 @@
+//! `lillux exec sample`: record resource usage of a process over time.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+use super::procinfo::{self, ResourceSample};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SampleFormat {
+    Ndjson,
+    Csv,
+}
+
+const CSV_HEADER: &str =
+    "timestamp,elapsed_ms,cpu_percent,cpu_time_ms,rss_bytes,threads,read_bytes,write_bytes";
+
+/// Summary of a finished sampling run.
+pub struct SampleRun {
+    pub samples: usize,
+    /// Whether sampling stopped because the process exited.
+    pub exited: bool,
+}
+
+/// Sample `pid` every `interval` until `duration` elapses or the process
+/// exits, writing one record per sample to `out`.
+pub fn record(
+    pid: u32,
+    interval: Duration,
+    duration: Duration,
+    format: SampleFormat,
+    out: &mut dyn Write,
+) -> Result<SampleRun, String> {
+    let write_err = |e: std::io::Error| format!("Failed to write sample: {e}");
+    if matches!(format, SampleFormat::Csv) {
+        writeln!(out, "{CSV_HEADER}").map_err(write_err)?;
+    }
+    let start = Instant::now();
+    let mut previous: Option<ResourceSample> = None;
+    let mut samples = 0;
+    loop {
+        let Some(sample) = procinfo::resource_sample(pid) else {
+            return Ok(SampleRun {
+                samples,
+                exited: true,
+            });
+        };
+        let cpu_percent = previous
+            .as_ref()
+            .and_then(|prev| cpu_percent(prev, &sample));
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        match format {
+            SampleFormat::Ndjson => {
+                let mut record = serde_json::to_value(&sample).unwrap_or_default();
+                record["pid"] = pid.into();
+                record["elapsed_ms"] = elapsed_ms.into();
+                record["cpu_percent"] = cpu_percent.into();
+                record["timestamp"] =
+                    crate::time::iso8601_from_unix_secs(sample.timestamp_ms / 1000).into();
+                writeln!(out, "{record}").map_err(write_err)?;
+            }
+            SampleFormat::Csv => {
+                let cell = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+                writeln!(
+                    out,
+                    "{},{elapsed_ms},{},{},{},{},{},{}",
+                    crate::time::iso8601_from_unix_secs(sample.timestamp_ms / 1000),
+                    cpu_percent.map(|v| v.to_string()).unwrap_or_default(),
+                    cell(sample.cpu_time_ms),
+                    cell(sample.rss_bytes),
+                    cell(sample.threads),
+                    cell(sample.read_bytes),
+                    cell(sample.write_bytes),
+                )
+                .map_err(write_err)?;
+            }
+        }
+        out.flush().map_err(write_err)?;
+        samples += 1;
+        previous = Some(sample);
+        if start.elapsed() + interval > duration {
+            return Ok(SampleRun {
+                samples,
+                exited: false,
+            });
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// CPU usage between two samples, as a percentage of one core.
+fn cpu_percent(prev: &ResourceSample, next: &ResourceSample) -> Option<f64> {
+    let cpu = next.cpu_time_ms?.checked_sub(prev.cpu_time_ms?)?;
+    let wall = next.timestamp_ms.checked_sub(prev.timestamp_ms)?;
+    (wall > 0).then(|| (cpu as f64 * 1000.0 / wall as f64).round() / 10.0)
+}
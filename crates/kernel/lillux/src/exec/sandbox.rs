@@ -0,0 +1,500 @@
+//! `spawn --sandbox`: isolation presets for bare processes, on Linux.
+//!
+//! A preset is a fixed set of layers, installed in the child between fork
+//! and exec:
+//!
+//! - `no-network`: a private network namespace, holding only a loopback
+//!   interface that is down. When lillux is not root the namespace sits in
+//!   a new user namespace that maps the caller's uid and gid to themselves.
+//! - `workspace-only`: Landlock lets the child create, change, and delete
+//!   files only beneath the workspace (the working directory at spawn) and
+//!   the temp directory, and write to `/dev/null`. Reading and executing
+//!   are not restricted.
+//! - `strict`: both, plus a seccomp filter that fails with `EPERM` the
+//!   syscalls that reach past the sandbox (ptrace, mounts, modules, bpf,
+//!   namespaces, io_uring, the clock, ...), no core dumps, and at most 1024
+//!   open files. `clone` is refused only with a `CLONE_NEW*` flag. `clone3`
+//!   passes its flags in memory the filter cannot read, so it fails with
+//!   `ENOSYS`, which sends libc back to `clone`.
+//!
+//! Every layer sets `no_new_privs`, so setuid binaries gain nothing. A
+//! layer the kernel cannot provide fails the spawn: the process never runs
+//! with less isolation than asked for.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// No isolation
+    #[default]
+    None,
+    /// Writes confined to the working directory and the temp directory
+    WorkspaceOnly,
+    /// No network but loopback, and that down
+    NoNetwork,
+    /// workspace-only and no-network, a syscall filter, and tighter limits
+    Strict,
+}
+
+/// The sandbox a registered process was started in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sandbox {
+    pub preset: Preset,
+    /// Where `workspace-only` allows writes.
+    pub workspace: String,
+    /// The layers `preset` stands for, e.g. `["network", "landlock"]`.
+    pub layers: Vec<String>,
+}
+
+/// Most descriptors a `strict` process may hold open.
+const STRICT_MAX_OPEN_FILES: u64 = 1024;
+
+impl Preset {
+    fn network(self) -> bool {
+        matches!(self, Self::NoNetwork | Self::Strict)
+    }
+
+    fn landlock(self) -> bool {
+        matches!(self, Self::WorkspaceOnly | Self::Strict)
+    }
+
+    fn strict(self) -> bool {
+        self == Self::Strict
+    }
+
+    pub fn layers(self) -> Vec<String> {
+        [
+            (self.network(), "network"),
+            (self.landlock(), "landlock"),
+            (self.strict(), "seccomp"),
+            (self.strict(), "rlimits"),
+        ]
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, layer)| layer.to_string())
+        .collect()
+    }
+}
+
+/// The sandbox for `preset` around `workspace`; `None` for [`Preset::None`].
+pub fn sandbox(preset: Preset, workspace: &Path) -> Result<Option<Sandbox>, String> {
+    if preset == Preset::None {
+        return Ok(None);
+    }
+    let workspace = workspace
+        .canonicalize()
+        .map_err(|e| format!("Invalid sandbox workspace {}: {e}", workspace.display()))?;
+    Ok(Some(Sandbox {
+        preset,
+        workspace: workspace.display().to_string(),
+        layers: preset.layers(),
+    }))
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::{prepare, Prepared};
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub use unsupported::{prepare, Prepared};
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod unsupported {
+    use super::Sandbox;
+
+    pub struct Prepared;
+
+    pub fn prepare(_sandbox: &Sandbox) -> Result<Prepared, String> {
+        Err("--sandbox is not supported on this platform".to_string())
+    }
+
+    impl Prepared {
+        pub fn install(&self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    use super::{Sandbox, STRICT_MAX_OPEN_FILES};
+
+    // Landlock ABI (linux/landlock.h).
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+    const ACCESS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_REMOVE_DIR: u64 = 1 << 4;
+    const ACCESS_REMOVE_FILE: u64 = 1 << 5;
+    const ACCESS_MAKE_CHAR: u64 = 1 << 6;
+    const ACCESS_MAKE_DIR: u64 = 1 << 7;
+    const ACCESS_MAKE_REG: u64 = 1 << 8;
+    const ACCESS_MAKE_SOCK: u64 = 1 << 9;
+    const ACCESS_MAKE_FIFO: u64 = 1 << 10;
+    const ACCESS_MAKE_BLOCK: u64 = 1 << 11;
+    const ACCESS_MAKE_SYM: u64 = 1 << 12;
+    /// ABI 2: linking or renaming a file into another directory.
+    const ACCESS_REFER: u64 = 1 << 13;
+    /// ABI 3.
+    const ACCESS_TRUNCATE: u64 = 1 << 14;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    /// x32 syscalls on x86_64 carry this bit; they bypass a filter keyed
+    /// on x86_64 numbers, so they are refused outright.
+    #[cfg(target_arch = "x86_64")]
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    /// Refused with `EPERM` under `strict`.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_open_tree,
+        libc::SYS_move_mount,
+        libc::SYS_fsopen,
+        libc::SYS_fsmount,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_reboot,
+        libc::SYS_kexec_load,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_acct,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_userfaultfd,
+        // Its submissions run syscalls the filter never sees.
+        libc::SYS_io_uring_setup,
+        libc::SYS_io_uring_enter,
+        libc::SYS_io_uring_register,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_iopl,
+        #[cfg(target_arch = "x86_64")]
+        libc::SYS_ioperm,
+    ];
+
+    /// `clone` flags that create namespaces, refused under `strict` as
+    /// `unshare` is. `CLONE_NEWTIME` is left out: in `clone` that bit is
+    /// part of the exit signal.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const CLONE_NAMESPACES: u32 = (libc::CLONE_NEWNS
+        | libc::CLONE_NEWCGROUP
+        | libc::CLONE_NEWUTS
+        | libc::CLONE_NEWIPC
+        | libc::CLONE_NEWUSER
+        | libc::CLONE_NEWPID
+        | libc::CLONE_NEWNET) as u32;
+
+    /// Everything the child needs, built before the fork so that
+    /// [`Prepared::install`] only makes syscalls.
+    pub struct Prepared {
+        network: Option<UserNamespace>,
+        ruleset: Option<OwnedFd>,
+        filter: Vec<libc::sock_filter>,
+        rlimits: bool,
+    }
+
+    /// `None` inside when lillux is root and needs no user namespace.
+    struct UserNamespace(Option<IdMaps>);
+
+    struct IdMaps {
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    pub fn prepare(sandbox: &Sandbox) -> Result<Prepared, String> {
+        let preset = sandbox.preset;
+        let network = preset.network().then(|| {
+            let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+            UserNamespace((uid != 0).then(|| IdMaps {
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+            }))
+        });
+        let ruleset = if preset.landlock() {
+            Some(ruleset(Path::new(&sandbox.workspace))?)
+        } else {
+            None
+        };
+        let filter = if preset.strict() {
+            filter()?
+        } else {
+            Vec::new()
+        };
+        Ok(Prepared {
+            network,
+            ruleset,
+            filter,
+            rlimits: preset.strict(),
+        })
+    }
+
+    impl Prepared {
+        /// Apply the sandbox to the calling process. Runs in `pre_exec`.
+        pub fn install(&self) -> io::Result<()> {
+            unsafe {
+                if let Some(UserNamespace(maps)) = &self.network {
+                    let user = if maps.is_some() {
+                        libc::CLONE_NEWUSER
+                    } else {
+                        0
+                    };
+                    check(libc::unshare(user | libc::CLONE_NEWNET))?;
+                    if let Some(maps) = maps {
+                        write_proc(c"/proc/self/setgroups", b"deny")?;
+                        write_proc(c"/proc/self/uid_map", &maps.uid_map)?;
+                        write_proc(c"/proc/self/gid_map", &maps.gid_map)?;
+                    }
+                }
+                if self.rlimits {
+                    let none = libc::rlimit {
+                        rlim_cur: 0,
+                        rlim_max: 0,
+                    };
+                    check(libc::setrlimit(libc::RLIMIT_CORE, &none))?;
+                    let mut files = libc::rlimit {
+                        rlim_cur: 0,
+                        rlim_max: 0,
+                    };
+                    check(libc::getrlimit(libc::RLIMIT_NOFILE, &mut files))?;
+                    files.rlim_max = files.rlim_max.min(STRICT_MAX_OPEN_FILES);
+                    files.rlim_cur = files.rlim_cur.min(files.rlim_max);
+                    check(libc::setrlimit(libc::RLIMIT_NOFILE, &files))?;
+                }
+                check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+                if let Some(ruleset) = &self.ruleset {
+                    check(
+                        libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0)
+                            as libc::c_int,
+                    )?;
+                }
+                if !self.filter.is_empty() {
+                    let program = libc::sock_fprog {
+                        len: self.filter.len() as libc::c_ushort,
+                        filter: self.filter.as_ptr() as *mut libc::sock_filter,
+                    };
+                    check(libc::prctl(
+                        libc::PR_SET_SECCOMP,
+                        libc::SECCOMP_MODE_FILTER,
+                        &program as *const libc::sock_fprog,
+                    ))?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn check(status: libc::c_int) -> io::Result<()> {
+        if status < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    unsafe fn write_proc(path: &std::ffi::CStr, contents: &[u8]) -> io::Result<()> {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        check(fd)?;
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        libc::close(fd);
+        if written != contents.len() as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// A Landlock ruleset handling every kind of write, allowing them
+    /// beneath `workspace` and the temp directory.
+    fn ruleset(workspace: &Path) -> Result<OwnedFd, String> {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Err(format!(
+                "Landlock, which --sandbox workspace-only needs, is not available: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let mut writes = ACCESS_WRITE_FILE
+            | ACCESS_REMOVE_DIR
+            | ACCESS_REMOVE_FILE
+            | ACCESS_MAKE_CHAR
+            | ACCESS_MAKE_DIR
+            | ACCESS_MAKE_REG
+            | ACCESS_MAKE_SOCK
+            | ACCESS_MAKE_FIFO
+            | ACCESS_MAKE_BLOCK
+            | ACCESS_MAKE_SYM;
+        let mut file_writes = ACCESS_WRITE_FILE;
+        if abi >= 2 {
+            writes |= ACCESS_REFER;
+        }
+        if abi >= 3 {
+            writes |= ACCESS_TRUNCATE;
+            file_writes |= ACCESS_TRUNCATE;
+        }
+        let attr = RulesetAttr {
+            handled_access_fs: writes,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(format!(
+                "Failed to create a Landlock ruleset: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+        allow(&ruleset, workspace, writes)?;
+        let temp = std::env::temp_dir();
+        if temp.is_dir() {
+            allow(&ruleset, &temp, writes)?;
+        }
+        allow(&ruleset, Path::new("/dev/null"), file_writes)?;
+        Ok(ruleset)
+    }
+
+    fn allow(ruleset: &OwnedFd, path: &Path, access: u64) -> Result<(), String> {
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| format!("Invalid sandbox path {}", path.display()))?;
+        let fd = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(format!(
+                "Failed to open {} for the sandbox: {}",
+                path.display(),
+                io::Error::last_os_error()
+            ));
+        }
+        let parent = unsafe { OwnedFd::from_raw_fd(fd) };
+        let rule = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: parent.as_raw_fd(),
+        };
+        let status = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                LANDLOCK_RULE_PATH_BENEATH,
+                &rule as *const PathBeneathAttr,
+                0,
+            )
+        };
+        if status < 0 {
+            return Err(format!(
+                "Failed to allow writes beneath {}: {}",
+                path.display(),
+                io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn filter() -> Result<Vec<libc::sock_filter>, String> {
+        let statement = |code: u32, k: u32| libc::sock_filter {
+            code: code as u16,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let jump = |code: u32, k: u32, jt: u8, jf: u8| libc::sock_filter {
+            code: code as u16,
+            jt,
+            jf,
+            k,
+        };
+        let load = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+        let equal = libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K;
+        let any_bit = libc::BPF_JMP | libc::BPF_JSET | libc::BPF_K;
+        let ret = libc::BPF_RET | libc::BPF_K;
+        let errno =
+            |errno: libc::c_int| libc::SECCOMP_RET_ERRNO | (errno as u32 & libc::SECCOMP_RET_DATA);
+        let refuse = errno(libc::EPERM);
+
+        // struct seccomp_data: nr at offset 0, arch at offset 4, args from
+        // offset 16. Both architectures are little-endian, so the low half
+        // of `clone`'s flags, where every `CLONE_NEW*` bit is, comes first.
+        let mut program = vec![
+            statement(load, 4),
+            jump(equal, AUDIT_ARCH, 1, 0),
+            statement(ret, libc::SECCOMP_RET_KILL_PROCESS),
+            statement(load, 0),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        program.extend([
+            jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                1,
+            ),
+            statement(ret, refuse),
+        ]);
+        program.extend([
+            jump(equal, libc::SYS_clone as u32, 0, 4),
+            statement(load, 16),
+            jump(any_bit, CLONE_NAMESPACES, 0, 1),
+            statement(ret, refuse),
+            statement(ret, libc::SECCOMP_RET_ALLOW),
+            jump(equal, libc::SYS_clone3 as u32, 0, 1),
+            statement(ret, errno(libc::ENOSYS)),
+        ]);
+        for nr in DENIED_SYSCALLS {
+            program.push(jump(equal, *nr as u32, 0, 1));
+            program.push(statement(ret, refuse));
+        }
+        program.push(statement(ret, libc::SECCOMP_RET_ALLOW));
+        Ok(program)
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn filter() -> Result<Vec<libc::sock_filter>, String> {
+        Err("--sandbox strict is not supported on this architecture".to_string())
+    }
+}
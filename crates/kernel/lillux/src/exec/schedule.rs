@@ -0,0 +1,234 @@
+//! `lillux exec schedule`: commands spawned by `exec daemon` on a cron
+//! schedule.
+//!
+//! Schedules live in the registry beside the processes they start, so any
+//! `lillux` invocation can add, list, or remove them while the daemon
+//! picks up changes on its next tick. Each run is an ordinary registered
+//! spawn named after its schedule; that name is how the overlap policy
+//! finds a previous run that is still going.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::cron::CronExpr;
+use super::registry::{Registry, RegistryEntry};
+use super::serve::Server;
+use super::sys_error::{ErrorCode, SysError};
+
+/// How often the daemon checks for due and queued runs.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Grace given to a previous run stopped by [`Overlap::KillPrevious`].
+const KILL_GRACE: f64 = 3.0;
+
+/// What happens when a schedule fires while its previous run is live.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overlap {
+    /// Do not start this run
+    Skip,
+    /// Start it once the previous run exits; firings while one run is
+    /// already queued coalesce into it
+    Queue,
+    /// Stop the previous run, then start this one
+    KillPrevious,
+}
+
+impl Overlap {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Overlap::Skip => "skip",
+            Overlap::Queue => "queue",
+            Overlap::KillPrevious => "kill-previous",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduleEntry {
+    pub name: String,
+    pub cron: String,
+    pub overlap: Overlap,
+    /// What each run spawns; its `name` is the schedule's.
+    pub template: RegistryEntry,
+    pub created_at: String,
+    pub last_fire_at: Option<String>,
+    /// The spawn result of the last firing, or why nothing was started.
+    pub last_result: Option<Value>,
+    /// A run is queued behind a live previous run.
+    pub pending: bool,
+}
+
+pub fn add(registry: Option<&str>, schedule: ScheduleEntry) -> Value {
+    let audited = json!({
+        "name": schedule.name,
+        "cron": schedule.cron,
+        "overlap": schedule.overlap,
+        "template": super::audit::entry_args(&schedule.template),
+    });
+    let mut result = match CronExpr::parse(&schedule.cron)
+        .map_err(|e| SysError::new(ErrorCode::InvalidArgument, e))
+        .and_then(|_| Ok(Registry::locate(registry)?))
+        .and_then(|r| r.add_schedule(&schedule))
+    {
+        Ok(()) => json!({
+            "success": true,
+            "name": schedule.name,
+            "next_fire_at": next_fire_at(&schedule.cron),
+        }),
+        Err(e) => e.failed_with(json!({ "name": schedule.name })),
+    };
+    super::audit::record(registry, "schedule.add", audited, &mut result);
+    result
+}
+
+pub fn remove(registry: Option<&str>, name: &str) -> Value {
+    let mut result = match Registry::locate(registry).and_then(|r| r.remove_schedule(name)) {
+        Ok(true) => json!({ "success": true, "name": name }),
+        Ok(false) => SysError::new(ErrorCode::NotFound, format!("No schedule named '{name}'"))
+            .failed_with(json!({ "name": name })),
+        Err(e) => json!({ "success": false, "name": name, "error": e }),
+    };
+    super::audit::record(
+        registry,
+        "schedule.remove",
+        json!({ "name": name }),
+        &mut result,
+    );
+    result
+}
+
+/// Every schedule with its next firing and the state of its latest run.
+pub fn list(registry: Option<&str>) -> Value {
+    let registry = match Registry::locate(registry) {
+        Ok(registry) => registry,
+        Err(e) => return json!({ "error": e }),
+    };
+    let schedules = match registry.schedules() {
+        Ok(schedules) => schedules,
+        Err(e) => return json!({ "error": e }),
+    };
+    let rows = schedules
+        .into_iter()
+        .map(|schedule| {
+            let last_run = registry
+                .find_latest(&schedule.name)
+                .ok()
+                .flatten()
+                .map(|run| {
+                    json!({
+                        "pid": run.pid,
+                        "alive": run.is_live(),
+                        "spawned_at": run.spawned_at,
+                        "exit_status": run.exit_status,
+                    })
+                });
+            json!({
+                "name": schedule.name,
+                "cron": schedule.cron,
+                "overlap": schedule.overlap,
+                "cmd": schedule.template.cmd,
+                "args": schedule.template.args,
+                "next_fire_at": next_fire_at(&schedule.cron),
+                "last_fire_at": schedule.last_fire_at,
+                "last_result": schedule.last_result,
+                "pending": schedule.pending,
+                "last_run": last_run,
+            })
+        })
+        .collect();
+    Value::Array(rows)
+}
+
+/// Run due schedules until the process exits. Called on its own thread by
+/// `exec daemon`, whose `server` spawns and reaps every run.
+pub fn run(server: &Arc<Server>) {
+    let mut last_minute = unix_secs() / 60;
+    loop {
+        std::thread::sleep(TICK);
+        let minute = unix_secs() / 60;
+        let Ok(schedules) = Registry::locate(server.registry()).and_then(|r| r.schedules()) else {
+            continue;
+        };
+        for schedule in schedules {
+            let Ok(cron) = CronExpr::parse(&schedule.cron) else {
+                continue;
+            };
+            // Check every minute since the last tick, in case one was
+            // missed while the machine was suspended or busy.
+            let due = (last_minute + 1..=minute)
+                .rev()
+                .take(60)
+                .any(|m| cron.matches(m * 60));
+            if due {
+                let _ = fire(server, &schedule);
+            } else if schedule.pending {
+                let _ = start_queued(server, &schedule);
+            }
+        }
+        last_minute = minute;
+    }
+}
+
+/// Apply the overlap policy to one firing and record its outcome.
+fn fire(server: &Arc<Server>, schedule: &ScheduleEntry) -> Result<(), String> {
+    let registry = Registry::locate(server.registry())?;
+    let previous = registry.find_live(&schedule.name)?;
+    let (result, pending) = match (previous, schedule.overlap) {
+        (None, _) => (start(server, schedule), false),
+        (Some(previous), Overlap::Skip) => (
+            json!({ "success": false, "skipped": true, "previous_pid": previous.pid }),
+            false,
+        ),
+        (Some(previous), Overlap::Queue) => (
+            json!({ "success": true, "queued": true, "previous_pid": previous.pid }),
+            true,
+        ),
+        (Some(_), Overlap::KillPrevious) => {
+            let killed = super::kill_registered(
+                server.registry(),
+                None,
+                Some(schedule.name.clone()),
+                KILL_GRACE,
+                true,
+            );
+            let mut result = start(server, schedule);
+            result["killed_previous"] = killed;
+            (result, false)
+        }
+    };
+    registry.record_fire(&schedule.name, &result, pending)
+}
+
+fn start_queued(server: &Arc<Server>, schedule: &ScheduleEntry) -> Result<(), String> {
+    let registry = Registry::locate(server.registry())?;
+    if registry.find_live(&schedule.name)?.is_some() {
+        return Ok(());
+    }
+    let result = start(server, schedule);
+    registry.record_fire(&schedule.name, &result, false)
+}
+
+fn start(server: &Arc<Server>, schedule: &ScheduleEntry) -> Value {
+    let mut result = server.spawn_entry(schedule.template.clone(), None, &Default::default());
+    result["schedule"] = schedule.name.clone().into();
+    result
+}
+
+fn next_fire_at(cron: &str) -> Option<String> {
+    CronExpr::parse(cron)
+        .ok()?
+        .next_after(unix_secs())
+        .map(crate::time::iso8601_from_unix_secs)
+}
+
+fn unix_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,616 @@
+//! `lillux exec serve --stdio` and `lillux exec daemon --socket`: a
+//! long-lived JSON-RPC 2.0 endpoint for the exec commands, so clients can
+//! drive many operations through one process instead of forking `lillux`
+//! for each. The daemon shares one server, and its children and registry,
+//! among every client connected to its Unix socket.
+//!
+//! Methods are `spawn`, `kill`, `status`, `wait`, `list`, and `logs`.
+//! Requests and responses are newline-delimited JSON. Each request runs on
+//! its own thread, so a slow `wait` or `kill` never blocks the others;
+//! responses carry the request id and may arrive out of order. Processes
+//! spawned through the server are its children: it reaps them and
+//! broadcasts an `exited` notification with the real exit code.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::rate_limit::SpawnLimit;
+use super::registry::{Registry, RegistryEntry};
+use super::retry::SpawnRetry;
+use super::rusage::ResourceUsage;
+use super::sys_error::{ErrorCode, SysError};
+
+/// Output stream shared by responses and notifications.
+pub type Sink = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// JSON-RPC error codes used by the server.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A method ran but reported failure; `data` carries its JSON result.
+const CALL_FAILED: i64 = -32000;
+
+/// How often `wait` re-checks a process that is not our child.
+const WAIT_POLL: Duration = Duration::from_millis(250);
+
+pub struct Server {
+    registry: Option<String>,
+    /// Children spawned by this server, with their exit once reaped.
+    children: Mutex<HashMap<u32, Option<Value>>>,
+    reaped: Condvar,
+    sinks: Mutex<Vec<Sink>>,
+    started: Instant,
+    /// Completed calls by method and outcome (`ok` or `error`).
+    calls: Mutex<BTreeMap<(String, &'static str), u64>>,
+    /// Adopted orphans reaped under `--subreaper`.
+    orphans_reaped: Mutex<u64>,
+    /// `--max-spawns-per-minute`, applied to every spawn.
+    spawn_limit: Option<SpawnLimit>,
+}
+
+#[derive(Deserialize)]
+struct SpawnParams {
+    cmd: String,
+    #[serde(default)]
+    args: Vec<String>,
+    log: Option<String>,
+    #[serde(default)]
+    envs: Vec<String>,
+    stdin: Option<String>,
+    stdin_fifo: Option<String>,
+    name: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    spawn_retries: u32,
+    spawn_backoff_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TargetParams {
+    pid: Option<u32>,
+    name: Option<String>,
+    #[serde(default = "default_grace")]
+    grace: f64,
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    self_only: bool,
+    #[serde(default)]
+    group: bool,
+}
+
+#[derive(Deserialize)]
+struct LogsParams {
+    name: String,
+    #[serde(default = "default_log_lines")]
+    lines: usize,
+}
+
+#[derive(Deserialize, Default)]
+struct ListParams {
+    tag: Option<String>,
+    name_prefix: Option<String>,
+}
+
+fn default_grace() -> f64 {
+    3.0
+}
+
+fn default_log_lines() -> usize {
+    100
+}
+
+impl Server {
+    pub fn new(registry: Option<String>) -> Arc<Self> {
+        Self::with_spawn_limit(registry, None)
+    }
+
+    pub fn with_spawn_limit(
+        registry: Option<String>,
+        spawn_limit: Option<SpawnLimit>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            registry,
+            children: Mutex::new(HashMap::new()),
+            reaped: Condvar::new(),
+            sinks: Mutex::new(Vec::new()),
+            started: Instant::now(),
+            calls: Mutex::new(BTreeMap::new()),
+            orphans_reaped: Mutex::new(0),
+            spawn_limit,
+        })
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// How many calls of each method have succeeded and failed.
+    pub fn call_counts(&self) -> Vec<(String, &'static str, u64)> {
+        lock(&self.calls)
+            .iter()
+            .map(|((method, outcome), count)| (method.clone(), *outcome, *count))
+            .collect()
+    }
+
+    /// The `--registry` path this server was started with.
+    pub fn registry(&self) -> Option<&str> {
+        self.registry.as_deref()
+    }
+
+    /// Serve one client: read requests from `input` until end of file,
+    /// answering on `sink`, then finish the requests still in flight.
+    /// Notifications reach every connected session.
+    pub fn session(self: &Arc<Self>, input: impl BufRead, sink: Sink) {
+        lock(&self.sinks).push(sink.clone());
+        let mut in_flight = Vec::new();
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let server = Arc::clone(self);
+            let sink = sink.clone();
+            in_flight.push(std::thread::spawn(move || {
+                if let Some(response) = server.respond(&line) {
+                    write_line(&sink, &response);
+                }
+            }));
+            in_flight.retain(|request| !request.is_finished());
+        }
+        for request in in_flight {
+            let _ = request.join();
+        }
+        lock(&self.sinks).retain(|other| !Arc::ptr_eq(other, &sink));
+    }
+
+    /// The response to one request line; `None` for notifications.
+    pub fn respond(self: &Arc<Self>, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    e.to_string(),
+                    None,
+                ))
+            }
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "Request has no method".to_string(),
+                None,
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+        let outcome = self.call(method, params);
+        let id = id?;
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message, data)) => error_response(id, code, message, data),
+        })
+    }
+
+    /// Run one method. Failures carry a JSON-RPC code, a message, and, for
+    /// failed calls, the method's own JSON result.
+    pub fn call(
+        self: &Arc<Self>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, (i64, String, Option<Value>)> {
+        let span = tracing::info_span!("exec.rpc", method, error = tracing::field::Empty);
+        let outcome = span.in_scope(|| self.dispatch(method, params));
+        if let Err((_, message, _)) = &outcome {
+            span.record("error", message.as_str());
+        }
+        if !matches!(outcome, Err((METHOD_NOT_FOUND, ..))) {
+            let label = if outcome.is_ok() { "ok" } else { "error" };
+            *lock(&self.calls)
+                .entry((method.to_string(), label))
+                .or_default() += 1;
+        }
+        outcome
+    }
+
+    fn dispatch(
+        self: &Arc<Self>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, (i64, String, Option<Value>)> {
+        let result = match method {
+            "spawn" => self.spawn(parse(params)?),
+            "kill" => {
+                let target: TargetParams = parse(params)?;
+                let group = super::kill_covers_group(
+                    target.name.as_deref(),
+                    target.self_only,
+                    target.group,
+                );
+                super::kill_registered(
+                    self.registry.as_deref(),
+                    target.pid,
+                    target.name,
+                    target.grace,
+                    group,
+                )
+            }
+            "status" => {
+                let target: TargetParams = parse(params)?;
+                match self.resolve(&target) {
+                    Ok(pid) => super::process_status(pid, super::StatusDetail::default()),
+                    Err(e) => e.failed(),
+                }
+            }
+            "wait" => {
+                let target: TargetParams = parse(params)?;
+                match self.resolve(&target) {
+                    Ok(pid) => {
+                        let span = tracing::info_span!("exec.wait", pid);
+                        span.in_scope(|| {
+                            self.wait(pid, target.timeout_ms.map(Duration::from_millis))
+                        })
+                    }
+                    Err(e) => e.failed(),
+                }
+            }
+            "list" => {
+                let filter: ListParams = if params.is_null() {
+                    ListParams::default()
+                } else {
+                    parse(params)?
+                };
+                super::list_registered(
+                    self.registry.as_deref(),
+                    filter.tag.as_deref(),
+                    filter.name_prefix.as_deref(),
+                    super::ListFormat::Json,
+                )
+            }
+            "logs" => {
+                let params: LogsParams = parse(params)?;
+                super::logged_entry(self.registry.as_deref(), &params.name)
+                    .and_then(|entry| {
+                        let log = entry.log.unwrap_or_default();
+                        let (lines, _) =
+                            super::logs::last_lines(std::path::Path::new(&log), params.lines)?;
+                        Ok(json!({ "name": params.name, "log": log, "lines": lines }))
+                    })
+                    .unwrap_or_else(|e| e.failed_with(json!({ "name": params.name })))
+            }
+            _ => return Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'"), None)),
+        };
+        match result.get("error").and_then(Value::as_str) {
+            Some(message) => Err((CALL_FAILED, message.to_string(), Some(result))),
+            None => Ok(result),
+        }
+    }
+
+    fn spawn(self: &Arc<Self>, params: SpawnParams) -> Value {
+        let mut retry = SpawnRetry {
+            retries: params.spawn_retries,
+            ..Default::default()
+        };
+        if let Some(ms) = params.spawn_backoff_ms {
+            retry.backoff = Duration::from_millis(ms);
+        }
+        let entry = RegistryEntry {
+            name: params.name,
+            tags: params.tags,
+            cmd: params.cmd,
+            args: params.args,
+            log: params.log,
+            stdin_fifo: params.stdin_fifo,
+            envs: params.envs,
+            ..Default::default()
+        };
+        self.spawn_entry(entry, params.stdin.as_deref(), &retry)
+    }
+
+    /// Spawn and register `entry` as a child this server reaps, once the
+    /// spawn limit allows.
+    pub fn spawn_entry(
+        self: &Arc<Self>,
+        entry: RegistryEntry,
+        stdin: Option<&str>,
+        retry: &SpawnRetry,
+    ) -> Value {
+        let queued = match self.spawn_limit.as_ref().map(SpawnLimit::acquire) {
+            Some(Err(mut rejected)) => {
+                rejected["name"] = json!(entry.name);
+                return rejected;
+            }
+            Some(Ok(queued)) => queued,
+            None => Duration::ZERO,
+        };
+        let name = entry.name.clone();
+        let mut result = super::spawn_registered(self.registry.as_deref(), entry, stdin, retry);
+        if let Some(pid) = result.get("pid").and_then(Value::as_u64) {
+            self.reap(pid as u32, name);
+        }
+        if queued >= Duration::from_millis(1) {
+            result["queued_ms"] = (queued.as_millis() as u64).into();
+        }
+        result
+    }
+
+    /// Spawns refused by `--max-spawns-per-minute`.
+    pub fn spawns_rejected(&self) -> u64 {
+        self.spawn_limit
+            .as_ref()
+            .map_or(0, SpawnLimit::rejected_count)
+    }
+
+    /// Reap `pid` in the background and broadcast its exit.
+    fn reap(self: &Arc<Self>, pid: u32, name: Option<String>) {
+        lock(&self.children).insert(pid, None);
+        let server = Arc::clone(self);
+        std::thread::spawn(move || {
+            let mut exit = json!({ "pid": pid, "name": name, "exited": true });
+            let (code, usage) = wait_child(pid);
+            exit["exit_code"] = code.into();
+            exit["rusage"] = json!(usage);
+            exit["timestamp"] = crate::time::iso8601_now().into();
+            if let Ok(registry) = Registry::locate(server.registry.as_deref()) {
+                if let Ok(Some(entry)) = registry.find_pid(pid) {
+                    let _ = registry.mark_ended(entry.id, "exited");
+                    match super::cores::collect(&registry, &entry) {
+                        Ok(dumps) if !dumps.is_empty() => exit["core_dumps"] = dumps.into(),
+                        Ok(_) => {}
+                        Err(e) => exit["core_dumps_error"] = e.into(),
+                    }
+                }
+            }
+            lock(&server.children).insert(pid, Some(exit.clone()));
+            server.reaped.notify_all();
+            server.notify("exited", exit);
+        });
+    }
+
+    /// Whether `pid` is a child this server spawned and reaps itself.
+    pub fn is_child(&self, pid: u32) -> bool {
+        lock(&self.children).contains_key(&pid)
+    }
+
+    /// Reap `pid`, an exited orphan adopted as subreaper, and broadcast
+    /// its exit.
+    pub fn reap_orphan(&self, pid: u32) {
+        let (code, usage) = wait_child(pid);
+        let exit = json!({
+            "pid": pid,
+            "exited": true,
+            "exit_code": code,
+            "rusage": usage,
+            "adopted": true,
+            "timestamp": crate::time::iso8601_now(),
+        });
+        *lock(&self.orphans_reaped) += 1;
+        self.notify("exited", exit);
+    }
+
+    pub fn orphans_reaped(&self) -> u64 {
+        *lock(&self.orphans_reaped)
+    }
+
+    /// Block until `pid` exits or `timeout` passes.
+    fn wait(&self, pid: u32, timeout: Option<Duration>) -> Value {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut children = lock(&self.children);
+        loop {
+            match children.get(&pid) {
+                Some(Some(exit)) => return exit.clone(),
+                Some(None) => {}
+                None if !super::is_alive(pid) => {
+                    // Not our child: the exit code is unobservable.
+                    return json!({ "pid": pid, "exited": true, "exit_code": null });
+                }
+                None => {}
+            }
+            let mut pause = WAIT_POLL;
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return json!({ "pid": pid, "exited": false });
+                }
+                pause = pause.min(deadline - now);
+            }
+            children = self
+                .reaped
+                .wait_timeout(children, pause)
+                .unwrap_or_else(|error| error.into_inner())
+                .0;
+        }
+    }
+
+    /// `pid`, or the process registered under `name`: the live one if any,
+    /// else the most recent so `wait` can report an exit already seen.
+    fn resolve(&self, target: &TargetParams) -> Result<u32, SysError> {
+        if let Some(pid) = target.pid {
+            return Ok(pid);
+        }
+        let Some(name) = &target.name else {
+            return Err(SysError::new(
+                ErrorCode::InvalidArgument,
+                "pid or name is required",
+            ));
+        };
+        let registry = Registry::locate(self.registry.as_deref())?;
+        match registry.find_live(name)? {
+            Some(entry) => Ok(entry.pid),
+            None => registry
+                .find_latest(name)?
+                .map(|entry| entry.pid)
+                .ok_or_else(|| {
+                    SysError::new(
+                        ErrorCode::NotFound,
+                        format!("No process named '{name}' is registered"),
+                    )
+                }),
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        for sink in lock(&self.sinks).iter() {
+            write_line(sink, &message);
+        }
+    }
+}
+
+/// Serve JSON-RPC on stdin/stdout until stdin closes.
+pub fn serve_stdio(registry: Option<String>, spawn_limit: Option<SpawnLimit>) {
+    let server = Server::with_spawn_limit(registry, spawn_limit);
+    let sink: Sink = Arc::new(Mutex::new(Box::new(std::io::stdout())));
+    server.session(std::io::stdin().lock(), sink);
+}
+
+/// Serve JSON-RPC to every client of a Unix socket at `path`. Only returns
+/// on failure to bind.
+#[cfg(unix)]
+pub fn serve_socket(
+    registry: Option<String>,
+    path: &str,
+    metrics: Option<&str>,
+    subreaper: bool,
+    spawn_limit: Option<SpawnLimit>,
+) -> Result<(), SysError> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(SysError::new(
+                ErrorCode::AlreadyExists,
+                format!("{path} exists and is not a socket"),
+            )
+            .path(path));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(SysError::new(
+                ErrorCode::AlreadyExists,
+                format!("A daemon is already listening on {path}"),
+            )
+            .path(path));
+        }
+        // Left behind by a daemon that did not shut down cleanly.
+        std::fs::remove_file(path).map_err(|e| {
+            SysError::io(format!("Failed to remove stale {path}"), "unlink", &e).path(path)
+        })?;
+    }
+    if subreaper {
+        super::subreaper::enable()?;
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|e| SysError::io(format!("Failed to bind {path}"), "bind", &e).path(path))?;
+    // Clients can spawn arbitrary commands: owner only.
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| SysError::io(format!("Failed to restrict {path}"), "chmod", &e).path(path))?;
+    let server = Server::with_spawn_limit(registry, spawn_limit);
+    let mut ready = json!({ "success": true, "socket": path, "pid": std::process::id() });
+    if subreaper {
+        ready["subreaper"] = true.into();
+    }
+    if let Some(address) = metrics {
+        ready["metrics"] = super::metrics::serve(&server, address)?.to_string().into();
+    }
+    let mut stdout = std::io::stdout();
+    let _ = writeln!(stdout, "{ready}");
+    let _ = stdout.flush();
+    // Under systemd `Type=notify`, ready means accepting connections.
+    let _ = super::systemd::notify(&format!("READY=1\nMAINPID={}", std::process::id()));
+    if let Some(interval) = super::systemd::watchdog_interval() {
+        std::thread::spawn(move || loop {
+            let _ = super::systemd::notify("WATCHDOG=1");
+            std::thread::sleep(interval);
+        });
+    }
+
+    // Schedules and health checks only run while a daemon is up.
+    {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || super::schedule::run(&server));
+    }
+    {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || super::health::run(&server));
+    }
+    if subreaper {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || super::subreaper::run(&server));
+    }
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let Ok(reader) = stream.try_clone() else {
+            continue;
+        };
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            let sink: Sink = Arc::new(Mutex::new(Box::new(stream)));
+            server.session(std::io::BufReader::new(reader), sink);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve_socket(
+    _registry: Option<String>,
+    _path: &str,
+    _metrics: Option<&str>,
+    _subreaper: bool,
+    _spawn_limit: Option<SpawnLimit>,
+) -> Result<(), SysError> {
+    Err(SysError::new(
+        ErrorCode::Unsupported,
+        "lillux exec daemon is not supported on this platform",
+    ))
+}
+
+fn parse<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, (i64, String, Option<Value>)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string(), None))
+}
+
+fn error_response(id: Value, code: i64, message: String, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+fn write_line(sink: &Sink, message: &Value) {
+    let mut out = lock(sink);
+    let _ = writeln!(out, "{message}");
+    let _ = out.flush();
+}
+
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Wait for our child `pid`; its exit code, or `128 + signal`, and what
+/// it used.
+#[cfg(unix)]
+fn wait_child(pid: u32) -> (Option<i32>, Option<ResourceUsage>) {
+    match super::rusage::wait(pid) {
+        Some((code, usage)) => (code, Some(usage)),
+        None => (None, None),
+    }
+}
+
+#[cfg(not(unix))]
+fn wait_child(pid: u32) -> (Option<i32>, Option<ResourceUsage>) {
+    while super::is_alive(pid) {
+        std::thread::sleep(WAIT_POLL);
+    }
+    (None, None)
+}
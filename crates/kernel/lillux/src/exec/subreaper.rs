@@ -0,0 +1,60 @@
+//! `lillux exec daemon --subreaper`: adopt orphaned descendants.
+//!
+//! A process that double-forks leaves its grandchild re-parented to init,
+//! where nobody here can observe its exit. As a child subreaper
+//! (`PR_SET_CHILD_SUBREAPER`, Linux only) the daemon inherits those
+//! orphans instead, and this module reaps them once they exit so they do
+//! not accumulate as zombies. Each reaped orphan is broadcast as an
+//! `exited` notification marked `"adopted": true`.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::serve::Server;
+
+/// How often the daemon looks for exited orphans.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Make this process the reaper of its orphaned descendants.
+#[cfg(target_os = "linux")]
+pub fn enable() -> Result<(), String> {
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        return Err(format!(
+            "Failed to become a child subreaper: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn enable() -> Result<(), String> {
+    Err("--subreaper is only supported on Linux".to_string())
+}
+
+/// Reap adopted orphans for the life of the daemon.
+///
+/// Only zombies the server is not already waiting for are reaped, and
+/// only once they have been seen on two consecutive ticks: a child that
+/// exits right after `spawn` is briefly a zombie before the server starts
+/// tracking it, and must be left for the server to reap with its name.
+pub fn run(server: &Arc<Server>) {
+    let me = std::process::id();
+    let mut seen = HashSet::new();
+    loop {
+        std::thread::sleep(TICK);
+        let Ok(table) = super::procinfo::snapshot() else {
+            continue;
+        };
+        let zombies: HashSet<u32> = table
+            .into_iter()
+            .filter(|p| p.ppid == me && p.state == "zombie" && !server.is_child(p.pid))
+            .map(|p| p.pid)
+            .collect();
+        for pid in zombies.intersection(&seen) {
+            server.reap_orphan(*pid);
+        }
+        seen = zombies;
+    }
+}
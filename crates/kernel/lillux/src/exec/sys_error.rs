@@ -0,0 +1,226 @@
+//! Errors that know which system call failed, on what, and why.
+//!
+//! A [`SysError`] keeps the usual message, ending in `(os error N)` like a
+//! rendered `io::Error`, so it reads as before. Besides that, it carries
+//! the [`ErrorCode`] it was created with, and a failed result carries it
+//! in `"error_detail"` with the system call, its symbolic error (`ESRCH`,
+//! `ERROR_ACCESS_DENIED`), and the path or PID it was given. The output
+//! envelope folds that into its `error` object, so callers can tell EPERM
+//! from ESRCH from ENOENT without reading prose.
+//!
+//! Any `String` error converts into a `SysError` coded `failed` without
+//! detail, and back, so functions can return one where their callers still
+//! expect the other.
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+pub use crate::output::ErrorCode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysError {
+    pub message: String,
+    pub code: ErrorCode,
+    /// `errno` on Unix, the Win32 error code on Windows.
+    pub os_error: Option<i32>,
+    pub syscall: Option<&'static str>,
+    pub path: Option<String>,
+    /// The target process (negative for a process group, as `kill` takes it).
+    pub pid: Option<i32>,
+}
+
+impl SysError {
+    /// A failure of kind `code` that no system call reported.
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            ..Self::from(message.into())
+        }
+    }
+
+    /// `context` failing in `syscall` with `error`.
+    pub fn io(context: impl fmt::Display, syscall: &'static str, error: &std::io::Error) -> Self {
+        Self {
+            message: format!("{context}: {error}"),
+            code: ErrorCode::of_io(error),
+            os_error: error.raw_os_error(),
+            syscall: Some(syscall),
+            path: None,
+            pid: None,
+        }
+    }
+
+    /// `context` failing in `syscall` with the calling thread's last OS error.
+    pub fn last(context: impl fmt::Display, syscall: &'static str) -> Self {
+        Self::io(context, syscall, &std::io::Error::last_os_error())
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn pid(mut self, pid: i32) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// The structured part, for `"error_detail"`; `None` for a plain
+    /// `failed` message.
+    pub fn detail(&self) -> Option<Value> {
+        if self.code == ErrorCode::Failed && self.os_error.is_none() && self.syscall.is_none() {
+            return None;
+        }
+        let mut detail = json!({
+            "code": self.code.as_str(),
+            "syscall": self.syscall,
+            "os_error": self.os_error,
+            "errno": self.os_error.and_then(errno_name),
+        });
+        if let Some(path) = &self.path {
+            detail["path"] = path.clone().into();
+        }
+        if let Some(pid) = self.pid {
+            detail["pid"] = pid.into();
+        }
+        Some(detail)
+    }
+
+    /// `{"success": false, "error", "error_detail"}`.
+    pub fn failed(&self) -> Value {
+        let mut result = json!({ "success": false, "error": self.message });
+        if let Some(detail) = self.detail() {
+            result["error_detail"] = detail;
+        }
+        result
+    }
+
+    /// [`failed`](Self::failed) with the members of `fields` added, such
+    /// as the name the caller asked about.
+    pub fn failed_with(&self, fields: Value) -> Value {
+        let mut result = self.failed();
+        if let (Value::Object(result), Value::Object(fields)) = (&mut result, fields) {
+            result.extend(fields);
+        }
+        result
+    }
+}
+
+impl fmt::Display for SysError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SysError {}
+
+impl From<String> for SysError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            code: ErrorCode::Failed,
+            os_error: None,
+            syscall: None,
+            path: None,
+            pid: None,
+        }
+    }
+}
+
+impl From<SysError> for String {
+    fn from(error: SysError) -> Self {
+        error.message
+    }
+}
+
+/// The symbolic name of an OS error code, for the common ones.
+#[cfg(unix)]
+pub fn errno_name(code: i32) -> Option<&'static str> {
+    const NAMES: &[(i32, &str)] = &[
+        (libc::EPERM, "EPERM"),
+        (libc::ENOENT, "ENOENT"),
+        (libc::ESRCH, "ESRCH"),
+        (libc::EINTR, "EINTR"),
+        (libc::EIO, "EIO"),
+        (libc::E2BIG, "E2BIG"),
+        (libc::ENOEXEC, "ENOEXEC"),
+        (libc::EBADF, "EBADF"),
+        (libc::ECHILD, "ECHILD"),
+        (libc::EAGAIN, "EAGAIN"),
+        (libc::ENOMEM, "ENOMEM"),
+        (libc::EACCES, "EACCES"),
+        (libc::EBUSY, "EBUSY"),
+        (libc::EEXIST, "EEXIST"),
+        (libc::EXDEV, "EXDEV"),
+        (libc::ENOTDIR, "ENOTDIR"),
+        (libc::EISDIR, "EISDIR"),
+        (libc::EINVAL, "EINVAL"),
+        (libc::ENFILE, "ENFILE"),
+        (libc::EMFILE, "EMFILE"),
+        (libc::ETXTBSY, "ETXTBSY"),
+        (libc::EFBIG, "EFBIG"),
+        (libc::ENOSPC, "ENOSPC"),
+        (libc::EROFS, "EROFS"),
+        (libc::EPIPE, "EPIPE"),
+        (libc::ENAMETOOLONG, "ENAMETOOLONG"),
+        (libc::ENOSYS, "ENOSYS"),
+        (libc::ENOTEMPTY, "ENOTEMPTY"),
+        (libc::ELOOP, "ELOOP"),
+        (libc::EADDRINUSE, "EADDRINUSE"),
+        (libc::ECONNREFUSED, "ECONNREFUSED"),
+        (libc::ETIMEDOUT, "ETIMEDOUT"),
+    ];
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(windows)]
+pub fn errno_name(code: i32) -> Option<&'static str> {
+    Some(match code {
+        2 => "ERROR_FILE_NOT_FOUND",
+        3 => "ERROR_PATH_NOT_FOUND",
+        5 => "ERROR_ACCESS_DENIED",
+        6 => "ERROR_INVALID_HANDLE",
+        8 => "ERROR_NOT_ENOUGH_MEMORY",
+        32 => "ERROR_SHARING_VIOLATION",
+        33 => "ERROR_LOCK_VIOLATION",
+        80 => "ERROR_FILE_EXISTS",
+        87 => "ERROR_INVALID_PARAMETER",
+        123 => "ERROR_INVALID_NAME",
+        183 => "ERROR_ALREADY_EXISTS",
+        193 => "ERROR_BAD_EXE_FORMAT",
+        _ => return None,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_failed_kill_names_the_syscall_errno_and_pid() {
+        let error = SysError::io(
+            "SIGTERM failed",
+            "kill",
+            &std::io::Error::from_raw_os_error(libc::ESRCH),
+        )
+        .pid(-4242);
+        assert!(error.message.starts_with("SIGTERM failed: "));
+        assert!(error
+            .message
+            .ends_with(&format!("(os error {})", libc::ESRCH)));
+        let failed = error.failed();
+        assert_eq!(failed["error_detail"]["code"], "os_error");
+        assert_eq!(failed["error_detail"]["syscall"], "kill");
+        assert_eq!(failed["error_detail"]["errno"], "ESRCH");
+        assert_eq!(failed["error_detail"]["pid"], -4242);
+        assert!(failed["error_detail"].get("path").is_none());
+
+        let plain = SysError::from("No such process".to_string());
+        assert_eq!(plain.failed().get("error_detail"), None);
+        assert_eq!(String::from(plain), "No such process");
+    }
+}
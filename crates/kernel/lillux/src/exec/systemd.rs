@@ -0,0 +1,144 @@
+//! systemd integration: `sd_notify` readiness/watchdog messages and unit
+//! files for `lillux exec export systemd`.
+
+use std::time::Duration;
+
+use super::registry::RegistryEntry;
+
+/// Send `state` (e.g. `READY=1`) to the service manager. `Ok(false)` when
+/// not running under systemd with `NOTIFY_SOCKET` set.
+#[cfg(unix)]
+pub fn notify(state: &str) -> Result<bool, String> {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET").filter(|v| !v.is_empty()) else {
+        return Ok(false);
+    };
+    let datagram =
+        UnixDatagram::unbound().map_err(|e| format!("Failed to create notify socket: {e}"))?;
+    let socket = socket.to_string_lossy().into_owned();
+    let sent = match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+                .and_then(|addr| datagram.send_to_addr(state.as_bytes(), &addr))
+        }
+        _ => datagram.send_to(state.as_bytes(), &socket),
+    };
+    sent.map(|_| true)
+        .map_err(|e| format!("Failed to notify {socket}: {e}"))
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> Result<bool, String> {
+    Ok(false)
+}
+
+/// How often to send `WATCHDOG=1`: half the interval systemd expects, or
+/// `None` when no watchdog is configured for this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// A unit file that runs `entry`'s command as a service, with the same
+/// environment and log file.
+pub fn render_unit(entry: &RegistryEntry) -> String {
+    let name = entry.name.as_deref().unwrap_or(&entry.cmd);
+    let exec_start: Vec<String> = std::iter::once(&entry.cmd)
+        .chain(&entry.args)
+        .map(|word| quote(word))
+        .collect();
+    let mut unit = format!(
+        "[Unit]\nDescription={} (exported from lillux exec)\n\n[Service]\n",
+        escape(name)
+    );
+    if entry.sd_notify {
+        // The service itself sends READY=1, not the lillux that spawned it.
+        unit.push_str("Type=notify\nNotifyAccess=main\n");
+    } else {
+        unit.push_str("Type=exec\n");
+    }
+    unit.push_str(&format!("ExecStart={}\n", exec_start.join(" ")));
+    for env in &entry.envs {
+        unit.push_str(&format!("Environment={}\n", quote(env)));
+    }
+    if let Some(log) = &entry.log {
+        let log = escape(log);
+        unit.push_str(&format!(
+            "StandardOutput=append:{log}\nStandardError=append:{log}\n"
+        ));
+    }
+    unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+/// Escape systemd specifiers and variable expansion.
+fn escape(value: &str) -> String {
+    value.replace('%', "%%").replace('$', "$$")
+}
+
+/// One word of a command line or `Environment=` assignment, quoted when
+/// systemd would otherwise split or unescape it.
+fn quote(word: &str) -> String {
+    let word = escape(word);
+    if !word.is_empty()
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'))
+    {
+        return word;
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_quotes_arguments_and_keeps_environment_and_log() {
+        let entry = RegistryEntry {
+            id: 1,
+            pid: 42,
+            name: Some("web".to_string()),
+            cmd: "/usr/bin/server".to_string(),
+            args: vec![
+                "--greeting".to_string(),
+                "hello \"world\"".to_string(),
+                "100%".to_string(),
+            ],
+            log: Some("/var/log/web.log".to_string()),
+            envs: vec!["HOME=/srv/web".to_string(), "MOTD=a b".to_string()],
+            sd_notify: true,
+            ..Default::default()
+        };
+        let unit = render_unit(&entry);
+        assert!(unit.contains("Description=web (exported from lillux exec)\n"));
+        assert!(unit.contains("Type=notify\n"));
+        assert!(
+            unit.contains("ExecStart=/usr/bin/server --greeting \"hello \\\"world\\\"\" 100%%\n")
+        );
+        assert!(unit.contains("Environment=HOME=/srv/web\nEnvironment=\"MOTD=a b\"\n"));
+        assert!(unit.contains("StandardOutput=append:/var/log/web.log\n"));
+    }
+}
@@ -4,6 +4,7 @@ pub mod crypto;
 pub mod exec;
 pub mod identity;
 pub mod locks;
+pub mod output;
 pub mod secure_fs;
 pub mod signature;
 pub mod time;
@@ -14,10 +15,12 @@ pub use exec::{
     configure_inherited_fds, configure_subprocess_limits, sealed_executable_memfd, sealed_memfd,
     supervised_launcher_attachment_status_pipe, supervised_launcher_status_pipe,
     validate_subprocess_limits, AbortedProcess, AttachmentAbortError, AttachmentReleaseError,
-    ForkSensitiveDescriptorLease, OutputLimitExceeded, ProcessAwaitingAttachment, RunningProcess,
-    SpawnResult, SubprocessLimits, SubprocessRequest, SubprocessResult,
+    ForkSensitiveDescriptorLease, OutputLimitExceeded, ProcessAwaitingAttachment, ProcessInfo,
+    RunningProcess, SpawnResult, SubprocessLimits, SubprocessRequest, SubprocessResult,
     SupervisedLauncherAttachmentStatusPipe, SupervisedLauncherStatusPipe, SupervisedProcessStatus,
 };
+#[cfg(feature = "orchestration")]
+pub use exec::{ExecError, KillOutcome, Killer, ProcessStatus, Spawned, Spawner};
 
 pub use atomic_fs::{
     atomic_exchange_paths, atomic_write, atomic_write_private, atomic_write_with_mode,
@@ -75,3 +78,7 @@ pub fn kill(pid: u32, grace: f64) -> Result<String, String> {
 pub fn is_alive(pid: u32) -> bool {
     exec::lib_is_alive(pid)
 }
+
+pub fn process_info(pid: u32) -> Option<ProcessInfo> {
+    exec::lib_process_info(pid)
+}
@@ -117,6 +117,31 @@ struct FileLockGuard {
     target_name: CString,
 }
 
+/// The error when a lock is still held by another process once a timed
+/// acquire gives up; callers can tell it from other failures by type.
+#[derive(Debug)]
+pub struct LockTimeout {
+    pub waited: std::time::Duration,
+    pub target: String,
+    pub holder: Option<i64>,
+}
+
+impl std::fmt::Display for LockTimeout {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let holder = self
+            .holder
+            .map_or_else(|| "unknown".to_string(), |pid| pid.to_string());
+        write!(
+            formatter,
+            "timed out after {:.1}s waiting for lock {} (holder pid: {holder})",
+            self.waited.as_secs_f64(),
+            self.target
+        )
+    }
+}
+
+impl std::error::Error for LockTimeout {}
+
 #[derive(Clone, Copy)]
 enum FileLockMode {
     Shared,
@@ -450,14 +475,12 @@ impl FileLockGuard {
                 return Err(error.into());
             }
             if started.elapsed() >= timeout {
-                let holder = linux_flock_holder_pid(&file)
-                    .map(|pid| pid.to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
-                anyhow::bail!(
-                    "timed out after {:.1}s waiting for lock {} (holder pid: {holder})",
-                    timeout.as_secs_f64(),
-                    String::from_utf8_lossy(target_bytes)
-                );
+                return Err(LockTimeout {
+                    waited: timeout,
+                    target: String::from_utf8_lossy(target_bytes).into_owned(),
+                    holder: linux_flock_holder_pid(&file),
+                }
+                .into());
             }
             std::thread::sleep(std::time::Duration::from_millis(50));
         }
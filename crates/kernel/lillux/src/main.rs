@@ -1,9 +1,17 @@
+mod completions;
+
 use lillux::cas;
 use lillux::exec;
 use lillux::identity;
+use lillux::output::{self, OutputFormat};
 use lillux::time;
 
-use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 
 #[derive(Parser)]
 #[command(
@@ -11,6 +19,11 @@ use clap::{Parser, Subcommand};
     about = "Lillux microkernel — Execute, Memory, Identity, Time"
 )]
 struct Cli {
+    /// How results are written: the `{ok, data, error}` envelope as JSON,
+    /// NDJSON, plain text, nothing but the exit status, or the command's raw
+    /// JSON [default: raw]
+    #[arg(long, global = true, value_enum)]
+    output: Option<OutputFormat>,
     #[command(subcommand)]
     command: Command,
 }
@@ -19,8 +32,10 @@ struct Cli {
 enum Command {
     /// Execute primitive — process lifecycle
     Exec {
+        #[command(flatten)]
+        options: exec::ExecOptions,
         #[command(subcommand)]
-        action: exec::ExecAction,
+        action: Box<exec::ExecAction>,
     },
     /// Memory primitive — content-addressed storage
     Cas {
@@ -37,21 +52,127 @@ enum Command {
         #[command(subcommand)]
         action: time::TimeAction,
     },
+    /// Print a completion script; `--name` completes live registered names
+    Completions { shell: Shell },
+    /// Print the man page, or write one per subcommand into a directory
+    Man {
+        #[arg(long)]
+        out_dir: Option<String>,
+    },
+}
+
+/// Primitives reachable busybox-style, through a link named
+/// `lillux-<primitive>`.
+const PRIMITIVES: &[&str] = &["exec", "cas", "identity", "time"];
+
+/// The command line as `lillux` expects it: invoked through a
+/// `lillux-exec` link (or `lillux-exec.exe`), `lillux-exec spawn ...`
+/// parses as `lillux exec spawn ...`.
+fn multicall_args(mut args: Vec<OsString>) -> Vec<OsString> {
+    let primitive = args
+        .first()
+        .and_then(|arg0| {
+            Path::new(arg0)
+                .file_stem()?
+                .to_str()?
+                .strip_prefix("lillux-")
+        })
+        .filter(|primitive| PRIMITIVES.contains(primitive))
+        .map(OsString::from);
+    if let Some(primitive) = primitive {
+        args[0] = "lillux".into();
+        args.insert(1, primitive);
+    }
+    args
+}
+
+/// `--output`, else every command's bare result, the shape its callers
+/// parsed before the envelope existed.
+fn output_format(cli: &Cli) -> OutputFormat {
+    cli.output.unwrap_or(OutputFormat::Raw)
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let args = multicall_args(std::env::args_os().collect());
+    let cli = Cli::parse_from(&args);
+    let format = output_format(&cli);
+    output::set_format(format);
 
     let result = match cli.command {
-        Command::Exec { action } => exec::run(action),
+        Command::Exec {
+            options: exec::ExecOptions {
+                host: Some(host), ..
+            },
+            ..
+        } => std::process::exit(exec::remote::run(&host, format, &Cli::command(), &args)),
+        Command::Exec { options, action } => exec::run(*action, options.registry.as_deref()),
         Command::Cas { action } => cas::run(action),
         Command::Identity { action } => identity::run(action),
         Command::Time { action } => time::run(action),
+        Command::Completions { shell } => {
+            let script = completions::script(shell, Cli::command());
+            let _ = std::io::stdout().write_all(script.as_bytes());
+            return;
+        }
+        Command::Man { out_dir: None } => match completions::man_page(Cli::command()) {
+            Ok(page) => {
+                let _ = std::io::stdout().write_all(&page);
+                return;
+            }
+            Err(e) => serde_json::json!({ "error": e }),
+        },
+        Command::Man { out_dir: Some(dir) } => {
+            match completions::man_pages(Cli::command(), Path::new(&dir)) {
+                Ok(pages) => serde_json::json!({ "out_dir": dir, "pages": pages }),
+                Err(e) => serde_json::json!({ "error": e }),
+            }
+        }
     };
 
-    println!("{}", result);
+    std::process::exit(output::emit(result));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<OsString> {
+        raw.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn primitive_links_dispatch_to_their_subcommand() {
+        assert_eq!(
+            multicall_args(args(&["/usr/local/bin/lillux-exec", "list"])),
+            args(&["lillux", "exec", "list"])
+        );
+        assert_eq!(
+            multicall_args(args(&["lillux", "time", "now"])),
+            args(&["lillux", "time", "now"])
+        );
+        assert_eq!(
+            multicall_args(args(&["lillux-watch", "thread"])),
+            args(&["lillux-watch", "thread"])
+        );
+        assert!(Cli::try_parse_from(multicall_args(args(&["lillux-time", "now"]))).is_ok());
+    }
 
-    if result.get("error").is_some() {
-        std::process::exit(1);
+    #[test]
+    fn the_envelope_is_opt_in() {
+        let format = |raw: &[&str]| output_format(&Cli::try_parse_from(args(raw)).unwrap());
+        assert_eq!(format(&["lillux", "exec", "list"]), OutputFormat::Raw);
+        assert_eq!(format(&["lillux", "time", "now"]), OutputFormat::Raw);
+        assert_eq!(
+            format(&["lillux", "cas", "verify", "--root", "/tmp", "--hash", "x"]),
+            OutputFormat::Raw
+        );
+        assert_eq!(
+            format(&["lillux", "--output", "json", "time", "now"]),
+            OutputFormat::Json
+        );
+        assert_eq!(
+            format(&["lillux", "exec", "--output", "json", "list"]),
+            OutputFormat::Json
+        );
     }
 }
@@ -0,0 +1,383 @@
+//! How command results reach stdout: the `--output` format and the
+//! envelope every format is built from.
+//!
+//! Commands return whatever JSON suits them, failing with an `"error"`
+//! string. At the edge that becomes `{"ok", "data", "error"}`: `data` is
+//! the result without its `error` (and without a `success` flag that only
+//! repeats `ok`), and `error` is `{code, message, os_error, errno}`, where
+//! `code` is one of [`CODES`], `os_error` the errno when the failure
+//! carries one, and `errno` its symbolic name; [`ERROR_FIELDS`] lists every
+//! member it can have. A result's `error_detail`
+//! (see [`SysError`](crate::exec::sys_error::SysError)) sets the `code`
+//! its error was created with and adds the failing `syscall` and its
+//! `path` or `pid`. Without one, the code follows from the errno, else it
+//! is `failed`; the wording of a message never decides it. Streams
+//! (`--follow`, `serve`, `mcp`) keep writing their own lines.
+//!
+//! The exit status follows from `error.code` (see [`exit_code`]), so shell
+//! callers can branch without reading the JSON at all.
+//!
+//! `raw` skips the envelope: the command's own JSON as it always was, and
+//! exit status 1 for any failure. It is the CLI's default, since callers
+//! parse that shape; the envelope is opt-in with `--output json`.
+
+use std::io::Write;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// The envelope as one JSON document
+    #[default]
+    Json,
+    /// The command's own JSON without the envelope; exit status 1 on failure
+    Raw,
+    /// One envelope per line; a list in `data` gets a line per element
+    Ndjson,
+    /// `key: value` lines; errors go to stderr
+    Text,
+    /// Nothing on stdout; errors go to stderr and the exit status says the rest
+    Quiet,
+}
+
+/// Every `error.code`.
+pub const CODES: &[&str] = &[
+    "not_found",
+    "already_exists",
+    "permission_denied",
+    "invalid_argument",
+    "timeout",
+    "unsupported",
+    "os_error",
+    "invalid_config",
+    "failed",
+];
+
+/// Every member of `error`. `code`, `message`, `os_error` and `errno` are
+/// always there (the last two may be null); `syscall`, `path` and `pid`
+/// only when the error carries them.
+pub const ERROR_FIELDS: &[&str] = &[
+    "code", "message", "os_error", "errno", "syscall", "path", "pid",
+];
+
+/// The kind of a failure, given where the error is created; `error.code`
+/// in the envelope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    InvalidArgument,
+    Timeout,
+    Unsupported,
+    /// A system call failed in a way none of the above describes.
+    OsError,
+    /// A `lillux exec` config file could not be read or parsed.
+    InvalidConfig,
+    #[default]
+    Failed,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "not_found",
+            Self::AlreadyExists => "already_exists",
+            Self::PermissionDenied => "permission_denied",
+            Self::InvalidArgument => "invalid_argument",
+            Self::Timeout => "timeout",
+            Self::Unsupported => "unsupported",
+            Self::OsError => "os_error",
+            Self::InvalidConfig => "invalid_config",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// The code for a failed system call.
+    pub fn of_io(error: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match error.kind() {
+            ErrorKind::NotFound => Self::NotFound,
+            ErrorKind::AlreadyExists => Self::AlreadyExists,
+            ErrorKind::PermissionDenied => Self::PermissionDenied,
+            ErrorKind::InvalidInput => Self::InvalidArgument,
+            ErrorKind::TimedOut => Self::Timeout,
+            ErrorKind::Unsupported => Self::Unsupported,
+            _ if error.raw_os_error().is_some() => Self::OsError,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// Exit statuses. These are stable: scripts branch on them.
+pub const EXIT_OK: i32 = 0;
+/// Any failure without a more specific status.
+pub const EXIT_FAILED: i32 = 1;
+/// Bad arguments; also what clap exits with for an unparsable command line.
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_PERMISSION: i32 = 4;
+pub const EXIT_TIMEOUT: i32 = 5;
+/// Some items of a multi-item command (`batch`) failed, others succeeded.
+pub const EXIT_PARTIAL: i32 = 6;
+pub const EXIT_ALREADY_EXISTS: i32 = 7;
+pub const EXIT_UNSUPPORTED: i32 = 8;
+pub const EXIT_CONFIG: i32 = 9;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Choose the format for this process; the first call wins.
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// `result` in the envelope.
+pub fn envelope(result: Value) -> Value {
+    let mut fields = match result {
+        Value::Object(fields) => fields,
+        data => return json!({ "ok": true, "data": data, "error": null }),
+    };
+    let error = fields.remove("error").filter(|error| !error.is_null());
+    let detail = fields.remove("error_detail");
+    let ok = error.is_none();
+    if fields.get("success") == Some(&Value::Bool(ok)) {
+        fields.remove("success");
+    }
+    let data = if fields.is_empty() && !ok {
+        Value::Null
+    } else {
+        Value::Object(fields)
+    };
+    let error = error.map(|error| {
+        let mut error = match error {
+            Value::String(message) => describe(&message),
+            other => describe(&other.to_string()),
+        };
+        if let Some(Value::Object(detail)) = detail {
+            for (key, value) in detail.into_iter().filter(|(_, value)| !value.is_null()) {
+                error[key] = value;
+            }
+        }
+        error
+    });
+    json!({ "ok": ok, "data": data, "error": error })
+}
+
+/// `{code, message, os_error, errno}` for an error message. An `io::Error`
+/// rendered into the message ends in `(os error N)`, which gives both the
+/// errno and the code; otherwise the code is `failed` until the result's
+/// `error_detail` says otherwise.
+pub fn describe(message: &str) -> Value {
+    let os_error = os_error(message);
+    let code = os_error.map_or(ErrorCode::Failed, |errno| {
+        ErrorCode::of_io(&std::io::Error::from_raw_os_error(errno))
+    });
+    json!({
+        "code": code.as_str(),
+        "message": message,
+        "os_error": os_error,
+        "errno": os_error.and_then(crate::exec::sys_error::errno_name),
+    })
+}
+
+pub(crate) fn os_error(message: &str) -> Option<i32> {
+    let (_, rest) = message.rsplit_once("(os error ")?;
+    rest.split_once(')')?.0.parse().ok()
+}
+
+/// The exit status for an envelope.
+pub fn exit_code(envelope: &Value) -> i32 {
+    if envelope["ok"] == true {
+        return EXIT_OK;
+    }
+    match envelope["error"]["code"].as_str() {
+        Some("not_found") => EXIT_NOT_FOUND,
+        Some("already_exists") => EXIT_ALREADY_EXISTS,
+        Some("permission_denied") => EXIT_PERMISSION,
+        Some("invalid_argument") => EXIT_USAGE,
+        Some("timeout") => EXIT_TIMEOUT,
+        Some("unsupported") => EXIT_UNSUPPORTED,
+        Some("invalid_config") => EXIT_CONFIG,
+        _ => EXIT_FAILED,
+    }
+}
+
+/// Write `result` to stdout in the chosen format. Returns the exit status.
+pub fn emit(result: Value) -> i32 {
+    let mut stdout = std::io::stdout();
+    if format() == OutputFormat::Raw {
+        let failed = result.get("error").is_some_and(|error| !error.is_null());
+        let _ = writeln!(stdout, "{result}");
+        let _ = stdout.flush();
+        return if failed { EXIT_FAILED } else { EXIT_OK };
+    }
+    let envelope = envelope(result);
+    let ok = envelope["ok"] == true;
+    match format() {
+        OutputFormat::Raw => unreachable!("written above"),
+        OutputFormat::Json => {
+            let _ = writeln!(stdout, "{envelope}");
+        }
+        OutputFormat::Ndjson => match &envelope["data"] {
+            Value::Array(items) if ok => {
+                for item in items {
+                    let line = json!({ "ok": true, "data": item, "error": null });
+                    let _ = writeln!(stdout, "{line}");
+                }
+            }
+            _ => {
+                let _ = writeln!(stdout, "{envelope}");
+            }
+        },
+        OutputFormat::Text if ok => {
+            let text = text(&envelope["data"]);
+            if !text.is_empty() {
+                let _ = writeln!(stdout, "{text}");
+            }
+        }
+        OutputFormat::Text | OutputFormat::Quiet => {
+            if !ok {
+                eprintln!(
+                    "error: {}",
+                    envelope["error"]["message"].as_str().unwrap_or_default()
+                );
+            }
+        }
+    }
+    let _ = stdout.flush();
+    exit_code(&envelope)
+}
+
+/// `data` for a terminal: an object as `key: value` lines, a list as a
+/// line per element.
+fn text(data: &Value) -> String {
+    match data {
+        Value::Null => String::new(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| format!("{key}: {}", scalar(value)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Object(fields) => pairs(fields),
+                other => scalar(other),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => scalar(other),
+    }
+}
+
+fn pairs(fields: &Map<String, Value>) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| format!("{key}={}", scalar(value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::sys_error::SysError;
+
+    #[test]
+    fn results_and_errors_share_one_envelope() {
+        assert_eq!(
+            envelope(json!({ "success": true, "pid": 7 })),
+            json!({ "ok": true, "data": { "pid": 7 }, "error": null })
+        );
+        // A child's own failure is data, not an error.
+        assert_eq!(
+            envelope(json!({ "success": false, "return_code": 2 }))["data"],
+            json!({ "success": false, "return_code": 2 })
+        );
+        let mut missing =
+            SysError::new(ErrorCode::NotFound, "No live process named 'web'").failed();
+        missing["name"] = "web".into();
+        assert_eq!(
+            envelope(missing),
+            json!({
+                "ok": false,
+                "data": { "name": "web" },
+                "error": { "code": "not_found", "message": "No live process named 'web'", "os_error": null, "errno": null },
+            })
+        );
+        assert_eq!(envelope(json!([1, 2]))["data"], json!([1, 2]));
+    }
+
+    #[test]
+    fn error_codes_map_to_stable_exit_statuses() {
+        let status = |code, error: &str| exit_code(&envelope(SysError::new(code, error).failed()));
+        assert_eq!(exit_code(&envelope(json!({ "pid": 7 }))), EXIT_OK);
+        assert_eq!(
+            status(ErrorCode::NotFound, "No live process named 'web'"),
+            EXIT_NOT_FOUND
+        );
+        assert_eq!(
+            status(ErrorCode::InvalidArgument, "--pid or --name is required"),
+            EXIT_USAGE
+        );
+        assert_eq!(
+            status(ErrorCode::Timeout, "Timed out waiting for lock 'db'"),
+            EXIT_TIMEOUT
+        );
+        // The wording does not decide the code.
+        assert_eq!(
+            exit_code(&envelope(json!({ "error": "'web' is already running" }))),
+            EXIT_FAILED
+        );
+        assert_eq!(
+            exit_code(&envelope(json!({ "error": "--name must not be empty" }))),
+            EXIT_FAILED
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn io_errors_carry_their_errno() {
+        let io = std::io::Error::from_raw_os_error(libc::EACCES);
+        let failed = envelope(json!({ "error": format!("Failed to open /x: {io}") }));
+        assert_eq!(failed["data"], Value::Null);
+        assert_eq!(failed["error"]["code"], "permission_denied");
+        assert_eq!(failed["error"]["os_error"], libc::EACCES);
+        assert_eq!(failed["error"]["errno"], "EACCES");
+
+        let failed = envelope(
+            SysError::io("Failed to open log file", "open", &io)
+                .path("/x")
+                .failed(),
+        );
+        assert_eq!(failed["data"], Value::Null);
+        assert_eq!(failed["error"]["syscall"], "open");
+        assert_eq!(failed["error"]["path"], "/x");
+        assert_eq!(failed["error"]["code"], "permission_denied");
+    }
+
+    #[test]
+    fn text_renders_objects_as_lines_and_lists_as_pairs() {
+        assert_eq!(
+            text(&json!({ "pid": 7, "name": "web" })),
+            "name: web\npid: 7"
+        );
+        assert_eq!(
+            text(&json!([{ "pid": 7, "tags": ["a"] }, "plain"])),
+            "pid=7 tags=[\"a\"]\nplain"
+        );
+    }
+}
@@ -417,3 +417,26 @@ fn pinned_atomic_batch_does_not_rebind_after_root_replacement() {
     );
     assert!(!root_path.join("objects/aa/bb/value.json").exists());
 }
+
+#[test]
+fn cli_failures_exit_1_raw_and_by_class_under_output_json() {
+    let root = tempfile::tempdir().unwrap();
+    let missing = "a".repeat(64);
+    let fetch = |output: &str, hash: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_lillux"))
+            .args(["--output", output, "cas", "fetch", "--root"])
+            .arg(root.path())
+            .args(["--hash", hash])
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(fetch("raw", "abc"), Some(1));
+    assert_eq!(fetch("raw", &missing), Some(1));
+    assert_eq!(fetch("json", "abc"), Some(lillux::output::EXIT_USAGE));
+    assert_eq!(
+        fetch("json", &missing),
+        Some(lillux::output::EXIT_NOT_FOUND)
+    );
+}
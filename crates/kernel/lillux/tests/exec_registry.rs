@@ -0,0 +1,691 @@
+//! The `lillux exec` process registry (Unix).
+//!
+//! Entries are pinned to the exact process by start time: a dead child or
+//! a PID recycled by an unrelated process must never read as live.
+
+#![cfg(unix)]
+
+use lillux::exec::container::{Container, Runtime};
+use lillux::exec::{Registry, RegistryEntry};
+use lillux::{process_info, spawn, SubprocessRequest};
+
+fn sleeper() -> SubprocessRequest {
+    SubprocessRequest {
+        cmd: "/bin/sh".to_string(),
+        argv0: None,
+        args: vec!["-c".to_string(), "sleep 5".to_string()],
+        cwd: None,
+        envs: vec![("PATH".to_string(), "/usr/bin:/bin".to_string())],
+        stdin_data: None,
+        timeout: 30.0,
+        limits: None,
+        inherited_fds: Vec::new(),
+        supervised_status: None,
+    }
+}
+
+fn entry(pid: u32, name: &str, started_at_ms: Option<u64>) -> RegistryEntry {
+    RegistryEntry {
+        pid,
+        name: Some(name.to_string()),
+        tags: vec!["test".to_string()],
+        cmd: "/bin/sh".to_string(),
+        started_at_ms,
+        spawned_at: "2026-01-01T00:00:00Z".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn recorded_entries_round_trip_and_missing_registry_is_empty() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("absent.db"));
+    assert!(registry.entries().unwrap().is_empty());
+    assert!(registry.find_pid(1).unwrap().is_none());
+
+    let registry = Registry::open(tmp.path().join("state").join("registry.db"));
+    let mut recorded = entry(2_000_000_000, "gone", None);
+    recorded.id = registry.record(&recorded).unwrap();
+
+    assert_eq!(registry.entries().unwrap(), vec![recorded.clone()]);
+    assert_eq!(registry.find_pid(2_000_000_000).unwrap(), Some(recorded));
+}
+
+#[test]
+fn container_entries_keep_their_runtime_configuration() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let mut recorded = entry(2_000_000_001, "site", None);
+    recorded.container = Some(Container {
+        runtime: Runtime::Podman,
+        id: "3f2a9c".to_string(),
+        image: "nginx:1.27".to_string(),
+        mounts: vec!["/srv/site:/usr/share/nginx/html:ro".to_string()],
+        ports: vec!["8080:80".to_string()],
+    });
+    recorded.id = registry.record(&recorded).unwrap();
+
+    assert_eq!(registry.find_latest("site").unwrap(), Some(recorded));
+}
+
+#[test]
+fn ended_processes_keep_their_first_exit_status() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let running = spawn(sleeper()).unwrap_or_else(|_| panic!("spawn failed"));
+    let started = process_info(running.pid).and_then(|info| info.start_time_ms);
+    let id = registry
+        .record(&entry(running.pid, "web", started))
+        .unwrap();
+
+    registry.mark_ended(id, "terminated").unwrap();
+    registry.mark_ended(id, "exited").unwrap();
+
+    let ended = registry.find_pid(running.pid).unwrap().unwrap();
+    assert_eq!(ended.exit_status.as_deref(), Some("terminated"));
+    assert!(ended.ended_at.is_some());
+    // An ended row never reads as live, even while the PID still runs.
+    assert!(registry.find_live("web").unwrap().is_none());
+
+    running.abort();
+}
+
+#[test]
+fn live_lookup_requires_a_matching_start_time() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let running = spawn(sleeper()).unwrap_or_else(|_| panic!("spawn failed"));
+    let started = process_info(running.pid).and_then(|info| info.start_time_ms);
+
+    registry
+        .record(&entry(running.pid, "web", started))
+        .unwrap();
+    assert_eq!(
+        registry.find_live("web").unwrap().map(|e| e.pid),
+        Some(running.pid)
+    );
+
+    // Same PID, different birth: a recycled PID is not the recorded process.
+    let recycled = entry(running.pid, "old", started.map(|ms| ms - 60_000));
+    registry.record(&recycled).unwrap();
+    assert!(!recycled.is_live());
+    assert!(registry.find_live("old").unwrap().is_none());
+    assert_eq!(
+        registry.find_latest("old").unwrap().map(|e| e.pid),
+        Some(running.pid)
+    );
+
+    running.abort();
+}
+
+#[test]
+fn gc_prunes_dead_entries_but_keeps_live_ones() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let running = spawn(sleeper()).unwrap_or_else(|_| panic!("spawn failed"));
+    let started = process_info(running.pid).and_then(|info| info.start_time_ms);
+    registry
+        .record(&entry(running.pid, "live", started))
+        .unwrap();
+    registry
+        .record(&entry(2_000_000_000, "dead", None))
+        .unwrap();
+
+    let preview = registry.gc(true).unwrap();
+    assert_eq!(preview.dead_entries.len(), 1);
+    assert_eq!(registry.entries().unwrap().len(), 2);
+
+    let report = registry.gc(false).unwrap();
+    assert_eq!(report.kept, 1);
+    assert_eq!(report.dead_entries[0].name.as_deref(), Some("dead"));
+    let remaining: Vec<_> = registry.entries().unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].pid, running.pid);
+
+    running.abort();
+}
+
+#[test]
+fn databases_from_before_stdin_fifos_are_migrated() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let path = tmp.path().join("registry.db");
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE processes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, pid INTEGER NOT NULL, name TEXT,
+                tags TEXT NOT NULL DEFAULT '[]', cmd TEXT NOT NULL, args TEXT NOT NULL DEFAULT '[]',
+                cmdline_hash TEXT NOT NULL, log TEXT, started_at_ms INTEGER,
+                spawned_at TEXT NOT NULL, exit_status TEXT, ended_at TEXT);
+             INSERT INTO processes (pid, name, cmd, cmdline_hash, spawned_at)
+                VALUES (2000000000, 'old', '/bin/sh', '', '2026-01-01T00:00:00Z');",
+        )
+        .unwrap();
+
+    let registry = Registry::open(&path);
+    let old = registry.find_latest("old").unwrap().unwrap();
+    assert_eq!(old.stdin_fifo, None);
+
+    let mut fresh = entry(2_000_000_001, "new", None);
+    fresh.stdin_fifo = Some("/tmp/new.in".to_string());
+    registry.record(&fresh).unwrap();
+    assert_eq!(
+        registry.find_latest("new").unwrap().unwrap().stdin_fifo,
+        fresh.stdin_fifo
+    );
+}
+
+#[test]
+fn sessions_upgrading_the_same_registry_at_once_all_succeed() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let path = tmp.path().join("registry.db");
+    // An empty file: every session finds a database with nothing applied.
+    std::fs::File::create(&path).unwrap();
+    let sessions = 8;
+    let barrier = std::sync::Barrier::new(sessions);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..sessions)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    Registry::open(&path).entries().map(|entries| entries.len())
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(0));
+        }
+    });
+}
+
+#[test]
+fn supervised_pipeline_records_each_stage_exit_code() {
+    use lillux::exec::pipeline::{supervise, Pipeline};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let log = tmp.path().join("pipeline.log");
+    let argv = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    let pipeline = Pipeline {
+        stages: vec![
+            argv(&["/bin/sh", "-c", "printf 'a\\nb\\n'; exit 3"]),
+            argv(&["/bin/sh", "-c", "tr a-z A-Z"]),
+        ],
+        name: Some("upper".to_string()),
+        tags: Vec::new(),
+        log: Some(log.to_string_lossy().into_owned()),
+        envs: vec!["PATH=/usr/bin:/bin".to_string()],
+        multiplex: false,
+    };
+
+    assert_eq!(supervise(&pipeline, &registry), 0);
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "A\nB\n");
+    let recorded = registry.find_latest("upper").unwrap().unwrap();
+    let codes: Vec<_> = recorded.stages.iter().map(|s| s.exit_code).collect();
+    assert_eq!(codes, vec![Some(3), Some(0)]);
+    assert_eq!(recorded.exit_status.as_deref(), Some("exited"));
+}
+
+#[test]
+fn multiplexed_pipeline_prefixes_each_stage_and_keeps_its_own_file() {
+    use lillux::exec::pipeline::{supervise, Pipeline};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = Registry::open(tmp.path().join("registry.db"));
+    let log = tmp.path().join("web.log");
+    let argv = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    let pipeline = Pipeline {
+        stages: vec![
+            argv(&["/bin/sh", "-c", "echo api up; echo api warn >&2"]),
+            argv(&["/bin/sh", "-c", "cat; echo db warn >&2"]),
+            argv(&["/usr/bin/tr", "a-z", "A-Z"]),
+        ],
+        name: Some("services".to_string()),
+        tags: Vec::new(),
+        log: Some(log.to_string_lossy().into_owned()),
+        envs: vec!["PATH=/usr/bin:/bin".to_string()],
+        multiplex: true,
+    };
+
+    assert_eq!(supervise(&pipeline, &registry), 0);
+    let combined = std::fs::read_to_string(&log).unwrap();
+    let mut lines: Vec<_> = combined.lines().collect();
+    lines.sort_unstable();
+    assert_eq!(lines, ["[sh-1] api warn", "[sh-2] db warn", "[tr] API UP"]);
+    let own = |file: &str| std::fs::read_to_string(tmp.path().join(file)).unwrap();
+    assert_eq!(own("web.sh-1.log"), "api warn\n");
+    assert_eq!(own("web.tr.log"), "API UP\n");
+
+    let recorded = registry.find_latest("services").unwrap().unwrap();
+    let labels: Vec<_> = recorded.stages.iter().map(|s| s.label.as_deref()).collect();
+    assert_eq!(labels, [Some("sh-1"), Some("sh-2"), Some("tr")]);
+}
+
+#[test]
+fn audit_log_is_append_only_and_chained() {
+    use lillux::exec::audit::verify;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let path = tmp.path().join("registry.db");
+    let registry = Registry::open(&path);
+    for (operation, pid) in [("spawn", 41), ("kill", 41), ("spawn", 42)] {
+        let args = serde_json::json!({ "pid": pid });
+        let outcome = serde_json::json!({ "success": true, "pid": pid });
+        registry
+            .append_audit(
+                operation,
+                Some("4bf92f3577b34da6a3ce929d0e0e4736"),
+                &args,
+                &outcome,
+            )
+            .unwrap();
+    }
+    let records = registry.audit_records(0).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].prev_hash, records[0].hash);
+    assert_eq!(verify(&records)["valid"], true);
+    assert_eq!(verify(&records)["head"], records[2].hash.as_str());
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    assert!(conn
+        .execute("UPDATE audit SET operation = 'status' WHERE seq = 2", [])
+        .is_err());
+    assert!(conn.execute("DELETE FROM audit WHERE seq = 2", []).is_err());
+
+    conn.execute_batch(
+        "DROP TRIGGER audit_no_update;
+         UPDATE audit SET args = '{\"pid\":7}' WHERE seq = 2;",
+    )
+    .unwrap();
+    let report = verify(&registry.audit_records(0).unwrap());
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["broken_at"], 2);
+}
+
+#[test]
+fn typed_api_spawns_reports_and_kills_by_name() {
+    use lillux::{ExecError, KillOutcome, Killer, ProcessStatus, Spawner};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    let spawned = Spawner::new("/bin/sh")
+        .args(["-c", "sleep 30"])
+        .name("api")
+        .registry(&registry)
+        .spawn()
+        .expect("spawn");
+    assert_eq!(spawned.name.as_deref(), Some("api"));
+
+    let again = Spawner::new("/bin/sh")
+        .name("api")
+        .registry(&registry)
+        .spawn();
+    assert_eq!(
+        again.unwrap_err(),
+        ExecError::AlreadyRunning {
+            name: "api".to_string(),
+            pid: spawned.pid
+        }
+    );
+
+    let status = ProcessStatus::of(Some(&registry), "api").expect("status");
+    assert!(status.alive);
+    assert_eq!(status.pid, spawned.pid);
+
+    let outcome = Killer::name("api").registry(&registry).grace(0.5).kill();
+    assert!(matches!(
+        outcome,
+        Ok(KillOutcome::Terminated | KillOutcome::Killed)
+    ));
+    assert!(!ProcessStatus::of(Some(&registry), "api").unwrap().alive);
+    assert_eq!(
+        Killer::name("api").registry(&registry).kill().unwrap_err(),
+        ExecError::NotFound("api".to_string())
+    );
+
+    let missing = Spawner::new("/nonexistent/lillux-api-test")
+        .registry(&registry)
+        .spawn();
+    let Err(ExecError::Failed(error)) = missing else {
+        panic!("expected a failed spawn, got {missing:?}");
+    };
+    assert_eq!(error.os_error, Some(libc::ENOENT));
+    assert_eq!(error.syscall, Some("execve"));
+    assert_eq!(error.path.as_deref(), Some("/nonexistent/lillux-api-test"));
+}
+
+#[test]
+fn kill_covers_the_process_group_by_name_unless_self_only_and_by_pid_with_group() {
+    use lillux::{is_alive, Killer, Spawner};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    // A shell that starts a grandchild and records its PID.
+    let spawn_family = |name: &str| {
+        let pid_file = tmp.path().join(format!("{name}.pid"));
+        let spawned = Spawner::new("/bin/sh")
+            .args(["-c", "sleep 30 & echo $! > \"$0\"; wait"])
+            .arg(pid_file.to_string_lossy())
+            .name(name)
+            .registry(&registry)
+            .spawn()
+            .expect("spawn");
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let grandchild = loop {
+            if let Some(pid) = std::fs::read_to_string(&pid_file)
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u32>().ok())
+            {
+                break pid;
+            }
+            assert!(std::time::Instant::now() < deadline, "no grandchild PID");
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        };
+        (spawned.pid, grandchild)
+    };
+
+    let (pid, grandchild) = spawn_family("whole");
+    let recorded = Registry::open(&registry)
+        .find_live("whole")
+        .unwrap()
+        .unwrap();
+    assert_eq!(recorded.pgid, Some(pid));
+    Killer::name("whole")
+        .registry(&registry)
+        .grace(0.5)
+        .kill()
+        .expect("kill");
+    // Reaping the orphan is init's business; a zombie has been killed.
+    assert!(matches!(
+        lillux::exec::procinfo::state(grandchild),
+        None | Some("zombie" | "dead")
+    ));
+
+    let (_, grandchild) = spawn_family("leader");
+    Killer::name("leader")
+        .registry(&registry)
+        .grace(0.5)
+        .self_only()
+        .kill()
+        .expect("kill");
+    assert!(is_alive(grandchild));
+    unsafe { libc::kill(grandchild as i32, libc::SIGKILL) };
+
+    // A bare PID is just that process unless the group is asked for.
+    let (pid, grandchild) = spawn_family("by-pid");
+    Killer::pid(pid)
+        .registry(&registry)
+        .grace(0.5)
+        .kill()
+        .expect("kill");
+    assert!(is_alive(grandchild));
+    unsafe { libc::kill(grandchild as i32, libc::SIGKILL) };
+
+    let (pid, grandchild) = spawn_family("by-pid-group");
+    Killer::pid(pid)
+        .registry(&registry)
+        .grace(0.5)
+        .group()
+        .kill()
+        .expect("kill");
+    assert!(matches!(
+        lillux::exec::procinfo::state(grandchild),
+        None | Some("zombie" | "dead")
+    ));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_api_waits_on_timers_instead_of_threads() {
+    use lillux::{KillOutcome, Killer, ProcessStatus, Spawner};
+    use std::time::Duration;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("runtime");
+    runtime.block_on(async {
+        for name in ["first", "second"] {
+            Spawner::new("/bin/sh")
+                .args(["-c", "sleep 30"])
+                .name(name)
+                .registry(&registry)
+                .spawn_async()
+                .await
+                .expect("spawn");
+        }
+        let names = vec!["first".to_string(), "second".to_string()];
+        let statuses = ProcessStatus::all_async(Some(registry.clone()), names).await;
+        assert!(statuses.iter().all(|status| status.as_ref().unwrap().alive));
+
+        let still_running = ProcessStatus::wait_exit(
+            Some(registry.clone()),
+            "first".to_string(),
+            Some(Duration::from_millis(200)),
+        )
+        .await
+        .expect("wait");
+        assert!(still_running.alive);
+
+        let kills: Vec<_> = ["first", "second"]
+            .into_iter()
+            .map(|name| {
+                tokio::spawn(
+                    Killer::name(name)
+                        .registry(&registry)
+                        .grace(0.5)
+                        .kill_async(),
+                )
+            })
+            .collect();
+        for kill in kills {
+            let outcome = kill.await.expect("join");
+            assert!(matches!(
+                outcome,
+                Ok(KillOutcome::Terminated | KillOutcome::Killed)
+            ));
+        }
+        let gone = ProcessStatus::wait_exit(Some(registry.clone()), "first".to_string(), None)
+            .await
+            .expect("wait");
+        assert!(!gone.alive);
+    });
+}
+
+#[test]
+fn a_broken_config_fails_only_commands_that_read_it() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let config = tmp.path().join("config.toml");
+    std::fs::write(&config, "grace = \"soon\"\n").unwrap();
+    let registry = tmp.path().join("registry.db");
+    let lillux = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_lillux"))
+            .args(["--output", "json"])
+            .args(args)
+            .env("LILLUX_CONFIG", &config)
+            .env_remove("LILLUX_EXEC_REGISTRY")
+            .current_dir(tmp.path())
+            .output()
+            .unwrap();
+        let envelope: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (output.status.code(), envelope)
+    };
+
+    let (status, ran) = lillux(&["exec", "run", "--cmd", "/bin/true"]);
+    assert_eq!(status, Some(0), "{ran}");
+
+    let registry = registry.to_str().unwrap();
+    let (status, killed) = lillux(&[
+        "exec",
+        "--registry",
+        registry,
+        "kill",
+        "--name",
+        "x",
+        "--grace",
+        "0",
+    ]);
+    assert_eq!(killed["error"]["code"], "not_found");
+    assert_eq!(status, Some(lillux::output::EXIT_NOT_FOUND));
+
+    for args in [
+        &["exec", "list"][..],
+        &["exec", "--registry", registry, "kill", "--pid", "1"],
+    ] {
+        let (status, failed) = lillux(args);
+        assert_eq!(failed["error"]["code"], "invalid_config", "{args:?}");
+        assert_eq!(status, Some(lillux::output::EXIT_CONFIG));
+    }
+}
+
+#[test]
+fn spawn_reads_the_working_directory_only_for_a_sandbox() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp.path().join("registry.db");
+    let gone = tmp.path().join("gone");
+    // From a working directory deleted under it.
+    let spawn_from_gone = |extra: &[&str]| {
+        std::fs::create_dir(&gone).unwrap();
+        let output = std::process::Command::new("/bin/sh")
+            .args(["-c", r#"cd "$1" && rmdir "$1" && shift && exec "$@""#, "sh"])
+            .arg(&gone)
+            .arg(env!("CARGO_BIN_EXE_lillux"))
+            .args(["--output", "json", "exec", "--registry"])
+            .arg(&registry)
+            .args(["spawn", "--cmd", "/bin/true"])
+            .args(extra)
+            .output()
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let spawned = spawn_from_gone(&[]);
+    assert_eq!(spawned["ok"], true, "{spawned}");
+    let sandboxed = spawn_from_gone(&["--sandbox", "no-network"]);
+    assert_eq!(sandboxed["ok"], false, "{sandboxed}");
+    assert!(
+        sandboxed["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("working directory"),
+        "{sandboxed}"
+    );
+}
+
+#[test]
+fn exists_exits_not_found_for_a_name_that_is_not_running() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_lillux"))
+        .args(["exec", "--registry"])
+        .arg(tmp.path().join("registry.db"))
+        .args(["exists", "--name", "web"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(lillux::output::EXIT_NOT_FOUND));
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["exists"], false);
+}
+
+#[test]
+fn concurrent_spawns_of_one_name_register_only_one() {
+    use lillux::{ExecError, Killer, Spawner};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    let spawns = 6;
+    let barrier = std::sync::Barrier::new(spawns);
+    let outcomes: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..spawns)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    Spawner::new("/bin/sh")
+                        .args(["-c", "sleep 30"])
+                        .name("web")
+                        .registry(registry.clone())
+                        .spawn()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let spawned: Vec<_> = outcomes.iter().filter_map(|o| o.as_ref().ok()).collect();
+    assert_eq!(spawned.len(), 1, "{outcomes:?}");
+    for outcome in &outcomes {
+        if let Err(e) = outcome {
+            assert!(
+                matches!(e, ExecError::AlreadyRunning { pid, .. } if *pid == spawned[0].pid),
+                "{e}"
+            );
+        }
+    }
+    let live: Vec<_> = Registry::open(&registry)
+        .entries()
+        .unwrap()
+        .into_iter()
+        .filter(|entry| entry.is_live())
+        .collect();
+    assert_eq!(live.len(), 1, "{live:?}");
+    Killer::name("web")
+        .registry(registry.clone())
+        .grace(0.0)
+        .kill()
+        .unwrap();
+}
+
+#[test]
+fn kill_escalates_to_sigkill_when_sigterm_is_ignored() {
+    use lillux::{KillOutcome, Killer, Spawner};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    let spawn = |name: &str, script: &str| {
+        Spawner::new("/bin/sh")
+            .args(["-c", script])
+            .name(name)
+            .registry(&registry)
+            .spawn()
+            .expect("spawn")
+    };
+    spawn("stubborn", "trap '' TERM; sleep 30");
+    // Let the trap be set before the signal arrives.
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    let kill = |name: &str| Killer::name(name).registry(&registry).grace(0.3);
+    assert_eq!(kill("stubborn").kill(), Ok(KillOutcome::Killed));
+
+    #[cfg(feature = "async")]
+    {
+        spawn("stubborn", "trap '' TERM; sleep 30");
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        let outcome = runtime.block_on(kill("stubborn").kill_async());
+        assert_eq!(outcome, Ok(KillOutcome::Killed));
+    }
+}
@@ -0,0 +1,281 @@
+//! JSON-RPC dispatch of `lillux exec serve` and the daemon around it (Unix).
+
+#![cfg(unix)]
+
+use lillux::exec::serve::Server;
+use serde_json::{json, Value};
+
+fn request(server: &std::sync::Arc<Server>, id: u64, method: &str, params: Value) -> Value {
+    let line = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    server.respond(&line.to_string()).expect("response")
+}
+
+#[test]
+fn spawned_children_are_reaped_with_their_exit_code() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp.path().join("registry.db");
+    let server = Server::new(Some(registry.to_string_lossy().into_owned()));
+
+    let spawned = request(
+        &server,
+        1,
+        "spawn",
+        json!({ "cmd": "/bin/sh", "args": ["-c", "exit 7"], "name": "seven" }),
+    );
+    assert_eq!(spawned["id"], 1);
+    assert_eq!(spawned["result"]["success"], true);
+
+    let waited = request(
+        &server,
+        2,
+        "wait",
+        json!({ "name": "seven", "timeout_ms": 10_000 }),
+    );
+    assert_eq!(waited["result"]["exited"], true);
+    assert_eq!(waited["result"]["exit_code"], 7);
+    assert_eq!(waited["result"]["pid"], spawned["result"]["pid"]);
+    assert!(
+        waited["result"]["rusage"]["max_rss_bytes"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+    assert!(waited["result"]["rusage"]["user_cpu_ms"].is_number());
+}
+
+#[test]
+fn protocol_errors_use_json_rpc_codes() {
+    let server = Server::new(Some("/nonexistent/registry.db".to_string()));
+
+    let unparsable = server.respond("{not json").expect("response");
+    assert_eq!(unparsable["error"]["code"], -32700);
+    assert_eq!(unparsable["id"], Value::Null);
+
+    assert_eq!(
+        request(&server, 1, "reboot", json!({}))["error"]["code"],
+        -32601
+    );
+    assert_eq!(
+        request(&server, 2, "spawn", json!({ "args": [] }))["error"]["code"],
+        -32602
+    );
+
+    let failed = request(&server, 3, "status", json!({}));
+    assert_eq!(failed["error"]["code"], -32000);
+    assert_eq!(failed["error"]["message"], "pid or name is required");
+
+    // Requests without an id are notifications and get no response.
+    let notification = json!({ "jsonrpc": "2.0", "method": "list" }).to_string();
+    assert!(server.respond(&notification).is_none());
+}
+
+#[test]
+fn daemon_socket_serves_concurrent_clients() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let socket = tmp.path().join("exec.sock");
+    let registry = tmp
+        .path()
+        .join("registry.db")
+        .to_string_lossy()
+        .into_owned();
+    let path = socket.to_string_lossy().into_owned();
+    std::thread::spawn(move || {
+        lillux::exec::serve::serve_socket(Some(registry), &path, None, false, None)
+    });
+
+    let connect = || {
+        for _ in 0..100 {
+            if let Ok(stream) = UnixStream::connect(&socket) {
+                return stream;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("daemon never listened");
+    };
+    let (mut first, mut second) = (connect(), connect());
+    for (stream, id) in [(&mut first, 1), (&mut second, 2)] {
+        let line = json!({ "jsonrpc": "2.0", "id": id, "method": "list" });
+        writeln!(stream, "{line}").unwrap();
+        let mut response = String::new();
+        BufReader::new(stream.try_clone().unwrap())
+            .read_line(&mut response)
+            .unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], id);
+        assert_eq!(response["result"], json!([]));
+    }
+
+    let err = lillux::exec::serve::serve_socket(None, &socket.to_string_lossy(), None, false, None)
+        .unwrap_err();
+    assert!(err.message.contains("already listening"), "{err}");
+}
+
+#[test]
+fn mcp_lists_typed_tools_and_reports_tool_errors_in_results() {
+    use lillux::exec::mcp;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let server = Server::new(Some(
+        tmp.path()
+            .join("registry.db")
+            .to_string_lossy()
+            .into_owned(),
+    ));
+    let message = |id: u64, method: &str, params: Value| {
+        let line = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        mcp::respond(&server, &line.to_string()).expect("response")
+    };
+
+    let init = message(1, "initialize", json!({ "protocolVersion": "2024-11-05" }));
+    assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+    assert!(init["result"]["capabilities"]["tools"].is_object());
+
+    let tools = message(2, "tools/list", json!({}));
+    let names: Vec<_> = tools["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["spawn", "kill", "status", "list", "logs"]);
+    assert_eq!(
+        tools["result"]["tools"][0]["inputSchema"]["required"],
+        json!(["cmd"])
+    );
+
+    let listed = message(3, "tools/call", json!({ "name": "list", "arguments": {} }));
+    assert_eq!(listed["result"]["isError"], false);
+    assert_eq!(listed["result"]["content"][0]["text"], "[]");
+
+    let missing = message(
+        4,
+        "tools/call",
+        json!({ "name": "logs", "arguments": { "name": "web" } }),
+    );
+    assert_eq!(missing["result"]["isError"], true);
+    let text = missing["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("No process named 'web'"), "{text}");
+}
+
+#[test]
+fn spawns_over_the_rate_limit_are_rejected_with_a_retry_hint() {
+    use lillux::exec::rate_limit::{OnLimit, SpawnLimit};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp.path().join("registry.db");
+    let server = Server::with_spawn_limit(
+        Some(registry.to_string_lossy().into_owned()),
+        Some(SpawnLimit::new(2, OnLimit::Reject)),
+    );
+    let spawn = |id, name: &str| {
+        request(
+            &server,
+            id,
+            "spawn",
+            json!({ "cmd": "/bin/sh", "args": ["-c", "exit 0"], "name": name }),
+        )
+    };
+    assert_eq!(spawn(1, "one")["result"]["success"], true);
+    assert_eq!(spawn(2, "two")["result"]["success"], true);
+
+    let third = spawn(3, "three");
+    assert_eq!(third["error"]["code"], -32000);
+    let data = &third["error"]["data"];
+    assert_eq!(data["name"], "three");
+    // Two a minute: the next token is about 30 seconds away.
+    let retry = data["retry_after_ms"].as_u64().unwrap();
+    assert!(retry > 25_000 && retry <= 30_000, "{retry}");
+    assert_eq!(server.spawns_rejected(), 1);
+    assert!(lillux::exec::metrics::render(&server)
+        .contains("\nlillux_exec_daemon_spawns_rejected_total 1\n"));
+}
+
+#[test]
+fn metrics_endpoint_exposes_call_counters() {
+    use std::io::{Read, Write};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let server = Server::new(Some(
+        tmp.path()
+            .join("registry.db")
+            .to_string_lossy()
+            .into_owned(),
+    ));
+    let address = lillux::exec::metrics::serve(&server, "127.0.0.1:0").unwrap();
+    request(&server, 1, "list", json!({}));
+    request(&server, 2, "status", json!({}));
+
+    let get = |path: &str| {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let metrics = get("/metrics");
+    assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"), "{metrics}");
+    assert!(metrics.contains("\nlillux_exec_processes 0\n"), "{metrics}");
+    assert!(metrics.contains("lillux_exec_daemon_calls_total{method=\"list\",outcome=\"ok\"} 1\n"));
+    assert!(
+        metrics.contains("lillux_exec_daemon_calls_total{method=\"status\",outcome=\"error\"} 1\n")
+    );
+    assert!(get("/").starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn batch_answers_each_command_in_order_with_its_id() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let registry = tmp.path().join("registry.db");
+    let input = [
+        json!({ "id": "a", "method": "spawn", "params": { "cmd": "/bin/sh", "args": ["-c", "exit 3"], "name": "three" } }),
+        json!({ "id": "b", "method": "wait", "params": { "name": "three", "timeout_ms": 10_000 } }),
+        json!({ "id": "c", "method": "kill", "params": { "name": "nobody" } }),
+        json!({ "id": "d", "method": "launch" }),
+    ]
+    .iter()
+    .map(|command| format!("{command}\n"))
+    .collect::<String>()
+        + "not json\n";
+
+    let mut out = Vec::new();
+    let status = lillux::exec::batch::run(
+        Some(registry.to_string_lossy().into_owned()),
+        input.as_bytes(),
+        &mut out,
+        false,
+    );
+    assert_eq!(status, lillux::output::EXIT_PARTIAL);
+    let results: Vec<Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let ids: Vec<&Value> = results.iter().map(|result| &result["id"]).collect();
+    assert_eq!(
+        ids,
+        [
+            &json!("a"),
+            &json!("b"),
+            &json!("c"),
+            &json!("d"),
+            &Value::Null
+        ]
+    );
+    assert_eq!(results[0]["ok"], true);
+    assert_eq!(results[1]["data"]["exit_code"], 3);
+    assert_eq!(results[2]["error"]["code"], "not_found");
+    assert_eq!(results[3]["error"]["code"], "invalid_argument");
+    assert_eq!(results[4]["ok"], false);
+
+    let mut out = Vec::new();
+    lillux::exec::batch::run(
+        Some(registry.to_string_lossy().into_owned()),
+        input.as_bytes(),
+        &mut out,
+        true,
+    );
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 3);
+}
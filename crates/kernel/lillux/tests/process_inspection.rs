@@ -0,0 +1,224 @@
+//! Read-only process inspection behind `lillux exec status` (Unix).
+//!
+//! Inspection must describe a live process without side effects and must
+//! report an absent PID as absent rather than as a zeroed record.
+
+#![cfg(unix)]
+
+use lillux::exec::ports::listening;
+use lillux::exec::procinfo::{descendant_tree, fd_usage};
+use lillux::{process_info, spawn, SubprocessRequest};
+
+/// A `/bin/sh -c <script>` request with coreutils on PATH.
+fn sh(script: &str) -> SubprocessRequest {
+    SubprocessRequest {
+        cmd: "/bin/sh".to_string(),
+        argv0: None,
+        args: vec!["-c".to_string(), script.to_string()],
+        cwd: None,
+        envs: vec![("PATH".to_string(), "/usr/bin:/bin".to_string())],
+        stdin_data: None,
+        timeout: 30.0,
+        limits: None,
+        inherited_fds: Vec::new(),
+        supervised_status: None,
+    }
+}
+
+// ── status ─────────────────────────────────────────────────────────────
+
+#[test]
+fn process_info_describes_the_current_process() {
+    let info = process_info(std::process::id()).expect("self is alive");
+
+    assert_eq!(info.pid, std::process::id());
+    assert!(info.ppid.is_some());
+    assert!(info.rss_bytes.unwrap_or(0) > 0);
+    assert!(info.start_time_ms.is_some());
+    assert!(info.cmdline.is_some_and(|argv| !argv.is_empty()));
+}
+
+#[test]
+fn process_info_reports_a_child_cwd_and_parent() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let mut request = sh("sleep 5");
+    request.cwd = Some(tmp.path().to_string_lossy().into_owned());
+    let running = spawn(request).unwrap_or_else(|_| panic!("spawn failed"));
+
+    let info = process_info(running.pid).expect("child is alive");
+
+    assert_eq!(info.ppid, Some(std::process::id()));
+    assert_eq!(info.name.as_deref(), Some("sh"));
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+        info.cwd.map(std::path::PathBuf::from),
+        Some(tmp.path().canonicalize().unwrap())
+    );
+    running.abort();
+}
+
+#[test]
+fn an_unreaped_child_is_reported_as_a_zombie_not_alive() {
+    let mut child = std::process::Command::new("/bin/sh")
+        .args(["-c", "exit 0"])
+        .spawn()
+        .unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let info = loop {
+        let info = process_info(child.id()).expect("a zombie still has a PID");
+        if info.state == Some("zombie") || std::time::Instant::now() > deadline {
+            break info;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+
+    assert_eq!(info.state, Some("zombie"));
+    assert!(info.is_defunct());
+    assert_eq!(info.to_json()["alive"], false);
+    #[cfg(target_os = "linux")]
+    assert!(!lillux::is_alive(child.id()));
+    child.wait().unwrap();
+}
+
+#[test]
+fn process_info_is_none_for_unused_pid() {
+    assert!(process_info(2_000_000_000).is_none());
+}
+
+#[test]
+fn fd_usage_counts_a_newly_opened_descriptor() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let before = fd_usage(std::process::id(), false).expect("fd usage");
+    assert!(before.files.is_none());
+
+    let path = tmp.path().join("held.txt");
+    let _held = std::fs::File::create(&path).expect("create");
+    let after = fd_usage(std::process::id(), true).expect("fd usage");
+
+    assert!(after.count > before.count);
+    let files = after.files.expect("detail requested");
+    assert!(
+        files.iter().any(|f| f.target.ends_with("held.txt")),
+        "{files:?}"
+    );
+}
+
+// ── tree ───────────────────────────────────────────────────────────────
+
+#[test]
+fn descendant_tree_nests_grandchildren_under_their_parent() {
+    let running = spawn(sh("sleep 5 & wait")).unwrap_or_else(|_| panic!("spawn failed"));
+
+    let mut tree = None;
+    for _ in 0..50 {
+        tree = descendant_tree(running.pid).expect("snapshot");
+        if tree
+            .as_ref()
+            .is_some_and(|t| !t["children"].as_array().unwrap().is_empty())
+        {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let tree = tree.expect("child is alive");
+
+    assert_eq!(tree["pid"], running.pid);
+    assert_eq!(tree["children"][0]["name"], "sleep");
+    assert_eq!(tree["children"][0]["state"], "sleeping");
+    running.abort();
+}
+
+#[test]
+fn descendant_tree_is_none_for_unused_pid() {
+    assert!(descendant_tree(2_000_000_000).unwrap().is_none());
+}
+
+// ── ports ──────────────────────────────────────────────────────────────
+
+#[test]
+fn listening_reports_a_bound_tcp_listener_of_the_caller() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().unwrap().port();
+
+    let sockets = listening(&[std::process::id()]).expect("inspect sockets");
+
+    assert!(
+        sockets
+            .iter()
+            .any(|s| s.protocol == "tcp" && s.address == "127.0.0.1" && s.port == port),
+        "{sockets:?}"
+    );
+}
+
+// ── sample ─────────────────────────────────────────────────────────────
+
+#[test]
+fn sample_writes_a_csv_row_per_interval_until_the_duration() {
+    use lillux::exec::sample::{record, SampleFormat};
+    use std::time::Duration;
+
+    let mut out = Vec::new();
+    let run = record(
+        std::process::id(),
+        Duration::from_millis(20),
+        Duration::from_millis(50),
+        SampleFormat::Csv,
+        &mut out,
+    )
+    .expect("sample");
+
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(!run.exited);
+    assert_eq!(lines.len(), run.samples + 1, "{text}");
+    assert!(lines[0].starts_with("timestamp,elapsed_ms,cpu_percent"));
+    assert!(run.samples >= 2);
+}
+
+#[test]
+fn sample_stops_with_exited_for_an_absent_process() {
+    use lillux::exec::sample::{record, SampleFormat};
+    use std::time::Duration;
+
+    let mut out = Vec::new();
+    let run = record(
+        2_000_000_000,
+        Duration::from_millis(10),
+        Duration::from_secs(1),
+        SampleFormat::Ndjson,
+        &mut out,
+    )
+    .expect("sample");
+
+    assert!(run.exited);
+    assert_eq!(run.samples, 0);
+    assert!(out.is_empty());
+}
+
+// ── ps ─────────────────────────────────────────────────────────────────
+
+#[test]
+fn ps_lists_the_caller_under_its_name_and_owner() {
+    use lillux::exec::ps::list;
+
+    let me = std::process::id();
+    let table = list().expect("ps");
+    let entry = table.iter().find(|entry| entry.pid == me).expect("caller");
+    assert!(table.windows(2).all(|pair| pair[0].pid < pair[1].pid));
+    assert_eq!(entry.uid, Some(unsafe { libc::getuid() }));
+    assert!(entry.rss_bytes.is_some_and(|rss| rss > 0));
+    assert!(entry.start_time.is_some());
+
+    let name = entry.name.clone().unwrap();
+    let uid = entry.uid.unwrap().to_string();
+    let found = lillux::exec::ps::run(Some(&name.to_uppercase()), Some(&uid));
+    let pids: Vec<_> = found
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["pid"].as_u64().unwrap() as u32)
+        .collect();
+    assert!(pids.contains(&me), "{found}");
+    let none = lillux::exec::ps::run(Some("no-process-is-named-this"), None);
+    assert_eq!(none, serde_json::json!([]));
+}
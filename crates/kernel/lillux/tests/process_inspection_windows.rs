@@ -0,0 +1,33 @@
+//! Read-only process inspection behind `lillux exec status` (Windows).
+//!
+//! A query handle gives the name, times, and working set; the fields that
+//! would take reading the process's memory stay `None`.
+
+#![cfg(windows)]
+
+use lillux::exec::procinfo::resource_sample;
+use lillux::process_info;
+
+#[test]
+fn process_info_reports_times_and_memory_of_the_current_process() {
+    let info = process_info(std::process::id()).expect("self is alive");
+
+    assert_eq!(info.pid, std::process::id());
+    assert!(info
+        .name
+        .as_deref()
+        .is_some_and(|name| name.ends_with(".exe")));
+    assert!(info.rss_bytes.unwrap_or(0) > 0);
+    assert!(info.start_time_ms.is_some());
+    assert!(info.uptime_ms.is_some());
+    assert!(info.cpu_percent.is_some());
+    assert_eq!(info.cmdline, None);
+}
+
+#[test]
+fn resource_sample_counts_cpu_time_and_the_working_set() {
+    let sample = resource_sample(std::process::id()).expect("self is alive");
+
+    assert!(sample.cpu_time_ms.is_some());
+    assert!(sample.rss_bytes.unwrap_or(0) > 0);
+}
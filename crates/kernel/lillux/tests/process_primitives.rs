@@ -583,3 +583,220 @@ fn kill_reports_already_dead_for_unused_pid() {
     let method = kill(2_000_000_000, 0.1).expect("kill");
     assert_eq!(method, "already_dead");
 }
+
+// ── on-exit hooks ──────────────────────────────────────────────────────
+
+#[test]
+fn exit_hook_runs_after_the_target_exits_with_metadata() {
+    use lillux::exec::on_exit::{watch_and_run, ExitHook};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let out = tmp.path().join("hook.out");
+    let mut request = sh(&["-c", "sleep 0.3"]);
+    request.envs = path_env();
+    let Ok(running) = spawn(request) else {
+        panic!("spawn failed");
+    };
+    let pid = running.pid;
+    let started = lillux::process_info(pid).and_then(|info| info.start_time_ms);
+    let waiter = std::thread::spawn(move || running.wait());
+
+    let code = watch_and_run(&ExitHook {
+        pid,
+        started_at_ms: started,
+        cmd: "/bin/sh".to_string(),
+        args: vec![
+            "-c".to_string(),
+            format!(
+                "printf '%s %s' \"$LILLUX_EXIT_PID\" \"$LILLUX_EXIT_NAME\" > {}",
+                out.display()
+            ),
+        ],
+        envs: Vec::new(),
+        log: None,
+    });
+
+    assert_eq!(code, 0);
+    assert!(waiter.join().unwrap().success);
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), format!("{pid} sh"));
+}
+
+// ── sandbox presets ────────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+#[test]
+fn strict_sandbox_confines_writes_network_and_syscalls() {
+    use lillux::exec::sandbox::{prepare, sandbox, Preset};
+    use std::os::unix::process::CommandExt;
+
+    let workspace = tempfile::tempdir().unwrap();
+    // Outside the temp directory, which the sandbox also leaves writable.
+    let outside = tempfile::tempdir_in(env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let sandbox = sandbox(Preset::Strict, workspace.path()).unwrap().unwrap();
+    assert_eq!(
+        sandbox.layers,
+        ["network", "landlock", "seccomp", "rlimits"]
+    );
+    let prepared = match prepare(&sandbox) {
+        Ok(prepared) => prepared,
+        Err(e) if e.contains("Landlock") => {
+            eprintln!("skipping: {e}");
+            return;
+        }
+        Err(e) => panic!("{e}"),
+    };
+
+    let script = r#"
+        touch "$1/inside" && echo wrote-inside
+        touch "$2/outside" 2>/dev/null || echo refused-outside
+        grep -c : /proc/net/dev
+        unshare -n true 2>/dev/null || echo refused-unshare
+        ulimit -n
+    "#;
+    let mut command = std::process::Command::new("/bin/sh");
+    command
+        .args(["-c", script, "sh"])
+        .arg(workspace.path())
+        .arg(outside.path());
+    unsafe {
+        command.pre_exec(move || prepared.install());
+    }
+    let output = command.output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "wrote-inside",
+            "refused-outside",
+            "1",
+            "refused-unshare",
+            "1024"
+        ],
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!outside.path().join("outside").exists());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn strict_sandbox_filters_clone_flags_clone3_and_io_uring() {
+    use lillux::exec::sandbox::{prepare, sandbox, Preset};
+
+    let workspace = tempfile::tempdir().unwrap();
+    let sandbox = sandbox(Preset::Strict, workspace.path()).unwrap().unwrap();
+    let prepared = match prepare(&sandbox) {
+        Ok(prepared) => prepared,
+        Err(e) if e.contains("Landlock") => {
+            eprintln!("skipping: {e}");
+            return;
+        }
+        Err(e) => panic!("{e}"),
+    };
+
+    // Each check sets a bit of the child's exit status when the syscall
+    // fails as the filter should make it.
+    const CLONE_NEWUSER: i32 = 1;
+    const CLONE_NEWPID: i32 = 2;
+    const PLAIN_CLONE: i32 = 4;
+    const CLONE3: i32 = 8;
+    const IO_URING_SETUP: i32 = 16;
+    const IO_URING_ENTER: i32 = 32;
+    const IO_URING_REGISTER: i32 = 64;
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            if prepared.install().is_err() {
+                libc::_exit(255);
+            }
+            let errno = || *libc::__errno_location();
+            let clone = |flags: libc::c_int| {
+                let child = libc::syscall(libc::SYS_clone, flags | libc::SIGCHLD, 0, 0, 0, 0);
+                if child == 0 {
+                    libc::_exit(0);
+                }
+                if child > 0 {
+                    libc::waitpid(child as libc::pid_t, std::ptr::null_mut(), 0);
+                }
+                child
+            };
+            let mut passed = 0;
+            if clone(libc::CLONE_NEWUSER) < 0 && errno() == libc::EPERM {
+                passed |= CLONE_NEWUSER;
+            }
+            if clone(libc::CLONE_NEWPID) < 0 && errno() == libc::EPERM {
+                passed |= CLONE_NEWPID;
+            }
+            if clone(0) > 0 {
+                passed |= PLAIN_CLONE;
+            }
+            if libc::syscall(libc::SYS_clone3, 0, 0) < 0 && errno() == libc::ENOSYS {
+                passed |= CLONE3;
+            }
+            if libc::syscall(libc::SYS_io_uring_setup, 1, 0) < 0 && errno() == libc::EPERM {
+                passed |= IO_URING_SETUP;
+            }
+            if libc::syscall(libc::SYS_io_uring_enter, -1, 0, 0, 0, 0, 0) < 0
+                && errno() == libc::EPERM
+            {
+                passed |= IO_URING_ENTER;
+            }
+            if libc::syscall(libc::SYS_io_uring_register, -1, 0, 0, 0) < 0 && errno() == libc::EPERM
+            {
+                passed |= IO_URING_REGISTER;
+            }
+            libc::_exit(passed);
+        }
+    }
+    let mut status = 0;
+    unsafe { libc::waitpid(pid, &mut status, 0) };
+    assert!(libc::WIFEXITED(status), "{status}");
+    let passed = libc::WEXITSTATUS(status);
+    assert_ne!(passed, 255, "the sandbox failed to install");
+    for (bit, check) in [
+        (CLONE_NEWUSER, "clone(CLONE_NEWUSER) fails with EPERM"),
+        (CLONE_NEWPID, "clone(CLONE_NEWPID) fails with EPERM"),
+        (PLAIN_CLONE, "clone without namespace flags still forks"),
+        (CLONE3, "clone3 fails with ENOSYS"),
+        (IO_URING_SETUP, "io_uring_setup fails with EPERM"),
+        (IO_URING_ENTER, "io_uring_enter fails with EPERM"),
+        (IO_URING_REGISTER, "io_uring_register fails with EPERM"),
+    ] {
+        assert_ne!(passed & bit, 0, "{check}");
+    }
+}
+
+// ── proxy: foreground stand-in ─────────────────────────────────────────
+
+#[test]
+fn proxy_forwards_sigterm_and_relays_the_exit_code() {
+    use std::io::{BufRead, BufReader};
+
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("proxy.log");
+    let mut proxy = std::process::Command::new(env!("CARGO_BIN_EXE_lillux"))
+        .args(["exec", "proxy", "--log"])
+        .arg(&log)
+        .args(["--cmd", "/bin/sh", "--arg", "-c", "--arg"])
+        .arg(r#"trap 'echo terminated; exit 42' TERM; echo ready; sleep 30 & wait"#)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(proxy.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "ready\n");
+
+    assert_eq!(unsafe { libc::kill(proxy.id() as i32, libc::SIGTERM) }, 0);
+    let status = proxy.wait().unwrap();
+    assert_eq!(status.code(), Some(42));
+    line.clear();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "terminated\n");
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "ready\nterminated\n"
+    );
+}